@@ -4,6 +4,7 @@ use std::string::FromUtf8Error;
 use hex::FromHexError;
 use strum::ParseError;
 use crate::shared::errors::IdentifierError;
+use crate::shared::models::network_id::NetworkId;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -97,4 +98,25 @@ pub enum PayloadError {
     AnyError(#[from] anyhow::Error),
     #[error(transparent)]
     IdentifierError(#[from] IdentifierError)
-}
\ No newline at end of file
+}
+
+/* ADL/RML validation failures, answered with the MSNP error code instead of OK */
+#[derive(Error, Debug, PartialEq)]
+pub enum ContactListError {
+    #[error("ADL/RML payload is corrupted: {}", .0)]
+    CorruptedPayload(String),
+    #[error("ADL/RML payload has an invalid modification: {}", .0)]
+    InvalidModification(String),
+    #[error("Invalid network id {:?} for contact: {}", .network_id, .contact)]
+    InvalidNetworkId { contact: String, network_id: NetworkId }
+}
+
+impl ContactListError {
+    pub fn get_error_code(&self) -> u32 {
+        match self {
+            ContactListError::CorruptedPayload(_) => 240,
+            ContactListError::InvalidModification(_) => 241,
+            ContactListError::InvalidNetworkId { .. } => 204
+        }
+    }
+}
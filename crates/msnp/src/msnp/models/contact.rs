@@ -25,6 +25,14 @@ impl Contact {
         self.memberships & role as u8 != 0
     }
 
+    pub fn add_roles(&mut self, roles: u8) {
+        self.memberships |= roles;
+    }
+
+    pub fn remove_roles(&mut self, roles: u8) {
+        self.memberships &= !roles;
+    }
+
     pub fn is_from_network(&self, network_id: NetworkId) -> bool {
         self.network_id == network_id
     }
@@ -1,55 +1,173 @@
 use std::collections::HashMap;
+use std::str::FromStr;
 
 use strum::IntoEnumIterator;
 
+use crate::msnp::error::ContactListError;
 use crate::msnp::models::contact::Contact;
+use crate::msnp::notification::command::adl::{ADLContact, ADLDomain, ADLPayload, AdlServer, RmlServer};
+use crate::msnp::notification::command::command::NotificationServerCommand;
 use crate::shared::models::email_address::EmailAddress;
+use crate::shared::models::network_id::NetworkId;
 use crate::shared::models::role_list::RoleList;
 
+/* WLM refuses to send more than that in a single ADL/RML, anything bigger is garbage */
+pub const MAX_CONTACTS_PER_PAYLOAD: usize = 150;
+
+const ALL_LISTS: u8 = RoleList::Forward as u8 | RoleList::Allow as u8 | RoleList::Block as u8 | RoleList::Reverse as u8 | RoleList::Pending as u8;
+const SERVER_LISTS: u8 = RoleList::Reverse as u8 | RoleList::Pending as u8;
+const ALLOW_BLOCK: u8 = RoleList::Allow as u8 | RoleList::Block as u8;
+
+/* A list change the client doesn't know about yet */
+#[derive(Debug, Clone)]
+enum ListPush {
+    Add { email_address: EmailAddress, network_id: NetworkId, lists: u8, display_name: Option<String> },
+    Remove { email_address: EmailAddress, network_id: NetworkId, lists: u8 }
+}
+
+/*
+    FL/AL/BL are owned by the client and changed with ADL/RML.
+    RL/PL are owned by the server, changes are queued and pushed to the client as ADL/RML.
+ */
 pub struct ContactList {
     pub contact_list: HashMap<EmailAddress, Contact>,
+    pending_pushes: Vec<ListPush>
 }
 
 impl Default for ContactList {
     fn default() -> Self {
        Self{
            contact_list: HashMap::default(),
+           pending_pushes: Vec::new()
        }
     }
 }
 
 impl ContactList {
 
-    pub fn add_contacts(&mut self, mut contacts: Vec<Contact>, is_initial: bool) {
-        for current in contacts.drain(..) {
-            match self.contact_list.get_mut(&current.email_address) {
-                None => {
-                    self.contact_list.insert(current.email_address.clone(), current);
-                },
+    pub fn apply_adl(&mut self, payload: &ADLPayload) -> Result<(), ContactListError> {
+        let is_initial = payload.is_initial();
+        let entries = Self::validate_payload(payload)?;
+
+        let mut updated: HashMap<EmailAddress, Contact> = HashMap::new();
+        for current in entries {
+            if !is_initial && current.memberships & SERVER_LISTS != 0 {
+                return Err(ContactListError::InvalidModification(format!("RL/PL are server managed: {} l={}", &current.email_address, current.memberships)));
+            }
+
+            let contact = match updated.get_mut(&current.email_address) {
                 Some(contact) => {
-                    if is_initial {
-                        contact.memberships = current.memberships;
-                    } else {
-                        contact.memberships += current.memberships;
-                    }
+                    contact.add_roles(current.memberships);
+                    contact
+                },
+                None => {
+                    let mut contact = match self.contact_list.get(&current.email_address) {
+                        None => Contact::new(current.email_address.clone(), current.network_id.clone(), 0),
+                        Some(existing) => {
+                            let mut contact = existing.clone();
+                            if is_initial {
+                                contact.memberships &= SERVER_LISTS;
+                            }
+                            contact
+                        }
+                    };
+                    contact.add_roles(current.memberships);
+                    updated.entry(current.email_address.clone()).or_insert(contact)
                 }
             };
+
+            if contact.memberships & ALLOW_BLOCK == ALLOW_BLOCK {
+                return Err(ContactListError::InvalidModification(format!("Contact can't be both on the Allow and Block lists: {}", &contact.email_address)));
+            }
+        }
+
+        if is_initial {
+            /* The initial ADL is the client's whole FL/AL/BL, what we know of RL/PL stays */
+            self.contact_list.retain(|_, contact| {
+                contact.memberships &= SERVER_LISTS;
+                contact.memberships != 0
+            });
         }
+
+        self.contact_list.extend(updated);
+        Ok(())
     }
 
-    pub fn remove_contacts(&mut self, mut contacts: Vec<Contact>) {
-        for current in contacts.drain(..) {
-            match self.contact_list.get_mut(&current.email_address) {
-                None => {
-                },
-                Some(contact) => {
-                        contact.memberships -= current.memberships;
-                        if contact.memberships == 0 {
-                            self.contact_list.remove(&current.email_address);
-                        }
+    pub fn apply_rml(&mut self, payload: &ADLPayload) -> Result<(), ContactListError> {
+        let entries = Self::validate_payload(payload)?;
+
+        if let Some(current) = entries.iter().find(|current| current.has_role(RoleList::Reverse)) {
+            return Err(ContactListError::InvalidModification(format!("RL is server managed: {}", &current.email_address)));
+        }
+
+        for current in entries {
+            if let Some(contact) = self.contact_list.get_mut(&current.email_address) {
+                contact.remove_roles(current.memberships);
+                if contact.memberships == 0 {
+                    self.contact_list.remove(&current.email_address);
                 }
-            };
+            }
         }
+
+        Ok(())
+    }
+
+    /* Server side list changes (someone added us, a pending invite showed up...), pushed to the client with take_pushes */
+    pub fn add_server_memberships(&mut self, email_address: &EmailAddress, network_id: NetworkId, lists: u8, display_name: Option<&str>) {
+        let contact = self.contact_list.entry(email_address.clone()).or_insert_with(|| Contact::new(email_address.clone(), network_id.clone(), 0));
+        let added = lists & !contact.memberships;
+
+        if added == 0 {
+            return;
+        }
+
+        contact.add_roles(added);
+        self.pending_pushes.push(ListPush::Add { email_address: email_address.clone(), network_id, lists: added, display_name: display_name.map(str::to_string) });
+    }
+
+    /* RL/PL the client already got from the address book, nothing is pushed for them */
+    pub fn seed_server_memberships(&mut self, email_address: &EmailAddress, network_id: NetworkId, lists: u8) {
+        let contact = self.contact_list.entry(email_address.clone()).or_insert_with(|| Contact::new(email_address.clone(), network_id, 0));
+        contact.add_roles(lists & SERVER_LISTS);
+
+        if contact.memberships == 0 {
+            self.contact_list.remove(email_address);
+        }
+    }
+
+    pub fn remove_server_memberships(&mut self, email_address: &EmailAddress, lists: u8) {
+        let Some(contact) = self.contact_list.get_mut(email_address) else {
+            return;
+        };
+
+        let removed = lists & contact.memberships;
+        if removed == 0 {
+            return;
+        }
+
+        contact.remove_roles(removed);
+        self.pending_pushes.push(ListPush::Remove { email_address: email_address.clone(), network_id: contact.network_id.clone(), lists: removed });
+
+        if contact.memberships == 0 {
+            self.contact_list.remove(email_address);
+        }
+    }
+
+    pub fn has_pending_pushes(&self) -> bool {
+        !self.pending_pushes.is_empty()
+    }
+
+    pub fn take_pushes(&mut self) -> Vec<NotificationServerCommand> {
+        self.pending_pushes.drain(..).map(|push| {
+            match push {
+                ListPush::Add { email_address, network_id, lists, display_name } => {
+                    NotificationServerCommand::ADL(AdlServer { payload: Self::single_contact_payload(&email_address, network_id, lists, display_name) })
+                },
+                ListPush::Remove { email_address, network_id, lists } => {
+                    NotificationServerCommand::RML(RmlServer { payload: Self::single_contact_payload(&email_address, network_id, lists, None) })
+                }
+            }
+        }).collect()
     }
 
     pub fn get_memberships(&self) -> HashMap<RoleList, Vec<&Contact>> {
@@ -70,5 +188,210 @@ impl ContactList {
         self.contact_list.iter().filter(|(k, v )| v.memberships & (RoleList::Forward as u8) != 0 ).map(|(k, v)| v.clone()).collect()
     }
 
+    fn validate_payload(payload: &ADLPayload) -> Result<Vec<Contact>, ContactListError> {
+        let contact_count: usize = payload.domains.iter().map(|domain| domain.contacts.len()).sum();
+        if contact_count > MAX_CONTACTS_PER_PAYLOAD {
+            return Err(ContactListError::CorruptedPayload(format!("Too many contacts in payload: {}", contact_count)));
+        }
+
+        let mut out = Vec::with_capacity(contact_count);
+        for domain in &payload.domains {
+            if !Self::is_valid_address_part(&domain.domain) {
+                return Err(ContactListError::CorruptedPayload(format!("Invalid domain: {}", &domain.domain)));
+            }
+
+            for contact in &domain.contacts {
+                if !Self::is_valid_address_part(&contact.email_part) {
+                    return Err(ContactListError::CorruptedPayload(format!("Invalid contact name: {}@{}", &contact.email_part, &domain.domain)));
+                }
+
+                let email_address = EmailAddress::from_str(&format!("{}@{}", &contact.email_part, &domain.domain))
+                    .map_err(|e| ContactListError::CorruptedPayload(e.to_string()))?;
+
+                if matches!(contact.contact_type, NetworkId::None | NetworkId::TemporaryGroup) {
+                    return Err(ContactListError::InvalidNetworkId { contact: email_address.to_string(), network_id: contact.contact_type.clone() });
+                }
+
+                if contact.list_type == 0 || contact.list_type & !ALL_LISTS != 0 {
+                    return Err(ContactListError::InvalidModification(format!("Invalid lists for {}: l={}", &email_address, contact.list_type)));
+                }
+
+                if contact.list_type & ALLOW_BLOCK == ALLOW_BLOCK {
+                    return Err(ContactListError::InvalidModification(format!("Contact can't be both on the Allow and Block lists: {}", &email_address)));
+                }
+
+                out.push(Contact::new(email_address, contact.contact_type.clone(), contact.list_type));
+            }
+        }
+
+        Ok(out)
+    }
+
+    fn is_valid_address_part(part: &str) -> bool {
+        !part.is_empty() && !part.contains('@') && !part.chars().any(char::is_whitespace)
+    }
+
+    fn single_contact_payload(email_address: &EmailAddress, network_id: NetworkId, lists: u8, display_name: Option<String>) -> ADLPayload {
+        let (email_part, domain) = email_address.crack();
+        let contact = ADLContact {
+            email_part: email_part.to_string(),
+            list_type: lists,
+            contact_type: network_id,
+            display_name: display_name.map(|display_name| ADLContact::encode_display_name(&display_name))
+        };
+
+        ADLPayload { l: None, domains: vec![ADLDomain { domain: domain.to_string(), contacts: vec![contact] }] }
+    }
+
 }
 
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use crate::msnp::error::ContactListError;
+    use crate::msnp::notification::command::adl::ADLPayload;
+    use crate::shared::models::email_address::EmailAddress;
+    use crate::shared::models::network_id::NetworkId;
+    use crate::shared::models::role_list::RoleList;
+    use crate::shared::traits::MSNPCommand;
+
+    use super::ContactList;
+
+    fn memberships_of(contact_list: &ContactList, email: &str) -> u8 {
+        contact_list.contact_list.get(&EmailAddress::from_str(email).unwrap()).map(|c| c.memberships).unwrap_or(0)
+    }
+
+    #[test]
+    fn test_initial_adl_replaces_lists() {
+        let mut contact_list = ContactList::default();
+        contact_list.apply_adl(&ADLPayload::from_str("<ml><d n=\"shlasouf.local\"><c n=\"aeon\" l=\"3\" t=\"1\"/><c n=\"gone\" l=\"1\" t=\"1\"/></d></ml>").unwrap()).unwrap();
+        contact_list.apply_adl(&ADLPayload::from_str("<ml l=\"1\"><d n=\"shlasouf.local\"><c n=\"aeon\" l=\"1\" t=\"1\"/><c n=\"aeon\" l=\"4\" t=\"1\"/></d></ml>").unwrap()).unwrap();
+
+        assert_eq!(memberships_of(&contact_list, "aeon@shlasouf.local"), RoleList::Forward as u8 | RoleList::Block as u8);
+        assert_eq!(memberships_of(&contact_list, "gone@shlasouf.local"), 0);
+    }
+
+    #[test]
+    fn test_initial_adl_keeps_server_lists() {
+        let mut contact_list = ContactList::default();
+        contact_list.add_server_memberships(&EmailAddress::from_str("aeon@shlasouf.local").unwrap(), NetworkId::WindowsLive, RoleList::Reverse as u8, None);
+        contact_list.apply_adl(&ADLPayload::from_str("<ml l=\"1\"><d n=\"shlasouf.local\"><c n=\"aeon\" l=\"1\" t=\"1\"/></d></ml>").unwrap()).unwrap();
+
+        assert_eq!(memberships_of(&contact_list, "aeon@shlasouf.local"), RoleList::Forward as u8 | RoleList::Reverse as u8);
+    }
+
+    #[test]
+    fn test_adl_adds_lists() {
+        let mut contact_list = ContactList::default();
+        contact_list.apply_adl(&ADLPayload::from_str("<ml><d n=\"shlasouf.local\"><c n=\"aeon\" l=\"1\" t=\"1\"/></d></ml>").unwrap()).unwrap();
+        contact_list.apply_adl(&ADLPayload::from_str("<ml><d n=\"shlasouf.local\"><c n=\"aeon\" l=\"2\" t=\"1\"/></d></ml>").unwrap()).unwrap();
+
+        assert_eq!(memberships_of(&contact_list, "aeon@shlasouf.local"), RoleList::Forward as u8 | RoleList::Allow as u8);
+    }
+
+    #[test]
+    fn test_adl_allow_and_block_is_rejected_atomically() {
+        let mut contact_list = ContactList::default();
+        contact_list.apply_adl(&ADLPayload::from_str("<ml><d n=\"shlasouf.local\"><c n=\"aeon\" l=\"4\" t=\"1\"/></d></ml>").unwrap()).unwrap();
+
+        let result = contact_list.apply_adl(&ADLPayload::from_str("<ml><d n=\"shlasouf.local\"><c n=\"other\" l=\"1\" t=\"1\"/><c n=\"aeon\" l=\"2\" t=\"1\"/></d></ml>").unwrap());
+
+        assert_eq!(result.unwrap_err().get_error_code(), 241);
+        assert_eq!(memberships_of(&contact_list, "aeon@shlasouf.local"), RoleList::Block as u8);
+        assert_eq!(memberships_of(&contact_list, "other@shlasouf.local"), 0);
+
+        let result = contact_list.apply_adl(&ADLPayload::from_str("<ml><d n=\"shlasouf.local\"><c n=\"new\" l=\"6\" t=\"1\"/></d></ml>").unwrap());
+        assert_eq!(result.unwrap_err().get_error_code(), 241);
+    }
+
+    #[test]
+    fn test_adl_server_lists_are_rejected() {
+        let mut contact_list = ContactList::default();
+        let result = contact_list.apply_adl(&ADLPayload::from_str("<ml><d n=\"shlasouf.local\"><c n=\"aeon\" l=\"8\" t=\"1\"/></d></ml>").unwrap());
+
+        assert!(matches!(result, Err(ContactListError::InvalidModification(_))));
+    }
+
+    #[test]
+    fn test_adl_invalid_payloads() {
+        let mut contact_list = ContactList::default();
+
+        let result = contact_list.apply_adl(&ADLPayload::from_str("<ml><d n=\"\"><c n=\"aeon\" l=\"1\" t=\"1\"/></d></ml>").unwrap());
+        assert_eq!(result.unwrap_err().get_error_code(), 240);
+
+        let result = contact_list.apply_adl(&ADLPayload::from_str("<ml><d n=\"shlasouf local\"><c n=\"aeon\" l=\"1\" t=\"1\"/></d></ml>").unwrap());
+        assert_eq!(result.unwrap_err().get_error_code(), 240);
+
+        let result = contact_list.apply_adl(&ADLPayload::from_str("<ml><d n=\"shlasouf.local\"><c n=\"ae@on\" l=\"1\" t=\"1\"/></d></ml>").unwrap());
+        assert_eq!(result.unwrap_err().get_error_code(), 240);
+
+        let result = contact_list.apply_adl(&ADLPayload::from_str("<ml><d n=\"shlasouf.local\"><c n=\"aeon\" l=\"0\" t=\"1\"/></d></ml>").unwrap());
+        assert_eq!(result.unwrap_err().get_error_code(), 241);
+
+        let result = contact_list.apply_adl(&ADLPayload::from_str("<ml><d n=\"shlasouf.local\"><c n=\"aeon\" l=\"1\" t=\"0\"/></d></ml>").unwrap());
+        assert_eq!(result.unwrap_err(), ContactListError::InvalidNetworkId { contact: "aeon@shlasouf.local".into(), network_id: NetworkId::None });
+
+        assert!(contact_list.contact_list.is_empty());
+    }
+
+    #[test]
+    fn test_adl_oversized_batch() {
+        let contacts: String = (0..151).map(|i| format!("<c n=\"contact{}\" l=\"1\" t=\"1\"/>", i)).collect();
+        let mut contact_list = ContactList::default();
+
+        let result = contact_list.apply_adl(&ADLPayload::from_str(&format!("<ml><d n=\"shlasouf.local\">{}</d></ml>", contacts)).unwrap());
+
+        assert_eq!(result.unwrap_err().get_error_code(), 240);
+        assert!(contact_list.contact_list.is_empty());
+    }
+
+    #[test]
+    fn test_rml() {
+        let mut contact_list = ContactList::default();
+        contact_list.add_server_memberships(&EmailAddress::from_str("aeon@shlasouf.local").unwrap(), NetworkId::WindowsLive, RoleList::Reverse as u8 | RoleList::Pending as u8, None);
+        contact_list.apply_adl(&ADLPayload::from_str("<ml><d n=\"shlasouf.local\"><c n=\"aeon\" l=\"3\" t=\"1\"/></d></ml>").unwrap()).unwrap();
+
+        let result = contact_list.apply_rml(&ADLPayload::from_str("<ml><d n=\"shlasouf.local\"><c n=\"aeon\" l=\"8\" t=\"1\"/></d></ml>").unwrap());
+        assert_eq!(result.unwrap_err().get_error_code(), 241);
+
+        contact_list.apply_rml(&ADLPayload::from_str("<ml><d n=\"shlasouf.local\"><c n=\"aeon\" l=\"16\" t=\"1\"/><c n=\"unknown\" l=\"1\" t=\"1\"/></d></ml>").unwrap()).unwrap();
+        assert_eq!(memberships_of(&contact_list, "aeon@shlasouf.local"), RoleList::Forward as u8 | RoleList::Allow as u8 | RoleList::Reverse as u8);
+    }
+
+    #[test]
+    fn test_server_pushes() {
+        let email = EmailAddress::from_str("aeon@shlasouf.local").unwrap();
+        let mut contact_list = ContactList::default();
+
+        contact_list.add_server_memberships(&email, NetworkId::WindowsLive, RoleList::Reverse as u8 | RoleList::Pending as u8, Some("Aeon"));
+        contact_list.add_server_memberships(&email, NetworkId::WindowsLive, RoleList::Reverse as u8, Some("Aeon"));
+        contact_list.remove_server_memberships(&email, RoleList::Pending as u8);
+        contact_list.remove_server_memberships(&email, RoleList::Pending as u8);
+
+        let pushes: Vec<String> = contact_list.take_pushes().into_iter().map(|push| String::from_utf8(push.into_bytes()).unwrap()).collect();
+
+        let adl_payload = "<ml><d n=\"shlasouf.local\"><c n=\"aeon\" l=\"24\" t=\"1\" f=\"Aeon\" /></d></ml>";
+        let rml_payload = "<ml><d n=\"shlasouf.local\"><c n=\"aeon\" l=\"16\" t=\"1\" /></d></ml>";
+        assert_eq!(pushes, vec![format!("ADL 0 {}\r\n{}", adl_payload.len(), adl_payload), format!("RML 0 {}\r\n{}", rml_payload.len(), rml_payload)]);
+        assert!(!contact_list.has_pending_pushes());
+        assert_eq!(memberships_of(&contact_list, "aeon@shlasouf.local"), RoleList::Reverse as u8);
+    }
+
+    #[test]
+    fn test_seeded_server_lists_are_not_pushed() {
+        let email = EmailAddress::from_str("aeon@shlasouf.local").unwrap();
+        let mut contact_list = ContactList::default();
+
+        contact_list.seed_server_memberships(&email, NetworkId::WindowsLive, RoleList::Reverse as u8 | RoleList::Pending as u8 | RoleList::Forward as u8);
+        contact_list.add_server_memberships(&email, NetworkId::WindowsLive, RoleList::Pending as u8, Some("Aeon"));
+
+        assert!(!contact_list.has_pending_pushes());
+        assert_eq!(memberships_of(&contact_list, "aeon@shlasouf.local"), RoleList::Reverse as u8 | RoleList::Pending as u8);
+
+        contact_list.remove_server_memberships(&email, RoleList::Pending as u8);
+        contact_list.add_server_memberships(&email, NetworkId::WindowsLive, RoleList::Pending as u8, Some("Aeon"));
+        assert_eq!(contact_list.take_pushes().len(), 2);
+    }
+
+}
@@ -22,6 +22,18 @@ pub struct AdlClient {
 
 pub type RmlClient = AdlClient;
 
+/* Pushed to the client when someone adds us, WLM shows the "X added you" prompt */
+#[derive(Debug)]
+pub struct AdlServer {
+    pub payload: ADLPayload
+}
+
+/* Pushed to the client when a contact leaves one of our server-managed lists (RL/PL) */
+#[derive(Debug)]
+pub struct RmlServer {
+    pub payload: ADLPayload
+}
+
 impl AdlClient {
     pub fn get_ok_response(&self, operand: &str) -> OkCommand {
         OkCommand { tr_id: self.tr_id, operand: operand.to_string() }
@@ -29,6 +41,51 @@ impl AdlClient {
 }
 
 
+/* Server pushes are not transactional, they carry a 0 tr_id */
+fn parse_server_push_payload(command: RawCommand) -> Result<ADLPayload, CommandError> {
+    let mut split = command.command_split;
+    let _operand = split.pop_front();
+
+    let raw_tr_id = split.pop_front().ok_or(CommandError::MissingArgument(command.command.clone(), "tr_id".into(), 1))?;
+    let _tr_id = u128::from_str(&raw_tr_id)?;
+
+    if command.expected_payload_size == 0 {
+        Err(PayloadError::MissingPayload { command: command.command })?;
+    }
+
+    Ok(ADLPayload::try_from_bytes(command.payload)?)
+}
+
+impl MSNPCommand for AdlServer {
+    type Err = CommandError;
+
+    fn try_from_raw(raw: RawCommand) -> Result<Self, Self::Err> {
+        Ok(Self { payload: parse_server_push_payload(raw)? })
+    }
+
+    fn into_bytes(self) -> Vec<u8> {
+        let mut payload = self.payload.into_bytes();
+        let mut out = format!("ADL 0 {}\r\n", payload.len()).into_bytes();
+        out.append(&mut payload);
+        out
+    }
+}
+
+impl MSNPCommand for RmlServer {
+    type Err = CommandError;
+
+    fn try_from_raw(raw: RawCommand) -> Result<Self, Self::Err> {
+        Ok(Self { payload: parse_server_push_payload(raw)? })
+    }
+
+    fn into_bytes(self) -> Vec<u8> {
+        let mut payload = self.payload.into_bytes();
+        let mut out = format!("RML 0 {}\r\n", payload.len()).into_bytes();
+        out.append(&mut payload);
+        out
+    }
+}
+
 impl MSNPCommand for AdlClient {
 
     type Err = CommandError;
@@ -112,10 +169,18 @@ pub struct ADLContact {
     pub list_type: u8,
 
     #[yaserde(rename = "t", attribute)]
-    pub contact_type: NetworkId
+    pub contact_type: NetworkId,
+
+    #[yaserde(rename = "f", attribute)]
+    pub display_name: Option<String>
 }
 
 impl ADLContact {
+    /* WLM reads the f attribute as an URL encoded friendly name */
+    pub fn encode_display_name(display_name: &str) -> String {
+        urlencoding::encode(display_name).into_owned()
+    }
+
     pub fn get_contact(&self, domain: &str) -> Result<Contact, IdentifierError> {
         Ok(Contact::new(EmailAddress::from_str(&format!("{}@{}", &self.email_part, domain))?, self.contact_type.clone(), self.list_type))
     }
@@ -162,6 +227,18 @@ impl FromStr for ADLPayload {
 
 impl ADLPayload {
 
+    pub fn new_pending_contact(email_address: &EmailAddress, display_name: &str) -> Self {
+        let (email_part, domain) = email_address.crack();
+        let contact = ADLContact {
+            email_part: email_part.to_string(),
+            list_type: RoleList::Reverse as u8 | RoleList::Pending as u8,
+            contact_type: NetworkId::WindowsLive,
+            display_name: Some(ADLContact::encode_display_name(display_name)),
+        };
+
+        Self { l: None, domains: vec![ADLDomain { domain: domain.to_string(), contacts: vec![contact] }] }
+    }
+
     pub fn is_initial(&self) -> bool {
         if self.l.is_none() {
             return false;
@@ -178,9 +255,12 @@ impl ADLPayload {
 mod tests {
     use std::str::FromStr;
 
+    use crate::msnp::raw_command_parser::{RawCommand, RawCommandParser};
+    use crate::shared::models::email_address::EmailAddress;
     use crate::shared::models::role_list::RoleList;
+    use crate::shared::traits::MSNPCommand;
 
-    use super::ADLPayload;
+    use super::{ADLPayload, AdlServer};
 
     #[test]
     fn test_deserialize() {
@@ -218,6 +298,33 @@ mod tests {
     //     let test = 0;
     // }
 
+    #[test]
+    fn test_pending_contact_server_ser() {
+        let payload = ADLPayload::new_pending_contact(&EmailAddress::from_str("aeon@shlasouf.local").unwrap(), "Aeon & co");
+        let adl = AdlServer { payload };
+
+        let serialized = String::from_utf8(adl.into_bytes()).unwrap();
+        let expected_payload = "<ml><d n=\"shlasouf.local\"><c n=\"aeon\" l=\"24\" t=\"1\" f=\"Aeon%20%26%20co\" /></d></ml>";
+
+        assert_eq!(format!("ADL 0 {}\r\n{}", expected_payload.len(), expected_payload), serialized);
+    }
+
+    #[test]
+    fn test_pending_contact_server_deser() {
+        let payload = ADLPayload::new_pending_contact(&EmailAddress::from_str("aeon@shlasouf.local").unwrap(), "Aeon");
+        let serialized = AdlServer { payload }.into_bytes();
+
+        let mut parser = RawCommandParser::new();
+        let raw = parser.parse_message(&serialized).unwrap().pop().unwrap();
+        let adl = AdlServer::try_from_raw(raw).unwrap();
+
+        let contact = &adl.payload.domains[0].contacts[0];
+        assert_eq!(contact.email_part.as_str(), "aeon");
+        assert_eq!(contact.list_type, RoleList::Reverse as u8 | RoleList::Pending as u8);
+
+        assert!(AdlServer::try_from_raw(RawCommand::without_payload("ADL 0 0")).is_err());
+    }
+
     #[test]
     fn test_serialize() {
         let payload = ADLPayload::from_str("<ml><d n=\"shlasouf.local\"><c n=\"facebookbot\" l=\"1\" t=\"1\"/><c n=\"facebookbot1\" l=\"1\" t=\"1\"/></d></ml>").unwrap();
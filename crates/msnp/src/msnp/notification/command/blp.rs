@@ -7,8 +7,8 @@ use crate::{msnp::{error::CommandError, raw_command_parser::RawCommand}};
 use crate::shared::traits::{MSNPCommand};
 
 pub struct Blp{
-    tr_id: u128,
-    list_type: ListType
+    pub tr_id: u128,
+    pub list_type: ListType
 }
 
 pub type BlpClient = Blp;
@@ -37,7 +37,7 @@ impl MSNPCommand for Blp {
     }
 }
 
-#[derive(Display, EnumString)]
+#[derive(Clone, Copy, Debug, PartialEq, Display, EnumString)]
 pub enum ListType {
     #[strum(serialize = "AL")]
     AllowList,
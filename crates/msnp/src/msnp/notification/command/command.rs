@@ -2,6 +2,7 @@
 use strum_macros::Display;
 
 use crate::msnp::{error::CommandError, raw_command_parser::RawCommand};
+use crate::msnp::notification::command::adl::{AdlServer, RmlServer};
 use crate::msnp::notification::command::blp::BlpServer;
 use crate::msnp::notification::command::chg::ChgServer;
 use crate::msnp::notification::command::cvr::CvrServer;
@@ -17,6 +18,7 @@ use crate::msnp::notification::command::usr::UsrServer;
 use crate::msnp::notification::command::uum::UumClient;
 use crate::msnp::notification::command::uux::UuxServer;
 use crate::msnp::notification::command::ver::VerServer;
+use crate::shared::command::error::ErrorCommand;
 use crate::shared::command::ok::OkCommand;
 use crate::shared::traits::MSNPCommand;
 
@@ -97,6 +99,9 @@ pub enum NotificationServerCommand {
     NLN(NlnServer),
     PUT(PutServer),
     SDG(SdgServer),
+    ADL(AdlServer),
+    RML(RmlServer),
+    Error(ErrorCommand),
     OUT,
     RAW(RawCommand)
 }
@@ -128,6 +133,9 @@ impl MSNPCommand for NotificationServerCommand {
             NotificationServerCommand::PUT(content) => {content.into_bytes()}
             NotificationServerCommand::NLN(content) => { content.into_bytes() }
             NotificationServerCommand::SDG(content) => { content.into_bytes() }
+            NotificationServerCommand::ADL(content) => { content.into_bytes() }
+            NotificationServerCommand::RML(content) => { content.into_bytes() }
+            NotificationServerCommand::Error(command) => command.into_bytes(),
         }
     }
 }
@@ -12,7 +12,8 @@ use crate::shared::command::ok::OkCommand;
 use crate::shared::models::endpoint_id::EndpointId;
 use crate::shared::models::network_id::NetworkId;
 use crate::shared::payload::msg::datacast_msg::{DatacastMessageContent, DatacastType};
-use crate::shared::payload::msg::raw_msg_payload::RawMsgPayload;
+use crate::shared::payload::msg::emoticon_msg::EmoticonMessageContent;
+use crate::shared::payload::msg::raw_msg_payload::{MsgContentType, RawMsgPayload};
 use crate::shared::payload::msg::text_msg::TextMessageContent;
 use crate::shared::payload::msg::typing_user_msg::TypingUserMessageContent;
 use crate::shared::traits::{IntoBytes, MSGPayload, MSNPCommand, MSNPPayload};
@@ -99,6 +100,33 @@ mod tests {
             assert!(content.is_styling_default());
         }
     }
+
+    #[test]
+    pub fn uum_client_emoticon_deser() {
+        let mut command_parser = RawCommandParser::new();
+        let body = "MIME-Version: 1.0\r\nContent-Type: text/x-mms-emoticon\r\n\r\n(dog)\t<msnobj Creator=\"aeoncl@shlasouf.local\" Size=\"1337\" Type=\"2\" Location=\"TFR2C.tmp\" Friendly=\"AAA=\" SHA1D=\"trC8SlFx2sWQxZMIBAWSEnXc8oQ=\" SHA1C=\"U6lcjTw+y5cnzUdFDGpP6tOT6JA=\"/>\t";
+        let raw = format!("UUM 28 aeoncl@shlasouf.local 1 1 {}\r\n{}", body.len(), body);
+        let raw_command = command_parser.parse_message(raw.as_bytes()).unwrap().pop().unwrap();
+        let uum_client = UumClient::try_from_raw(raw_command).unwrap();
+
+        assert!(matches!(uum_client.payload, UumPayload::Emoticon(_)));
+
+        if let UumPayload::Emoticon(content) = uum_client.payload {
+            assert_eq!(1, content.emoticons.len());
+            assert_eq!("(dog)", &content.emoticons[0].shortcut);
+        }
+    }
+
+    #[test]
+    pub fn uum_client_wink_deser() {
+        let mut command_parser = RawCommandParser::new();
+        let body = "MIME-Version: 1.0\r\nContent-Type: text/x-msnmsgr-datacast\r\n\r\nID: 2\r\nData: <msnobj Creator=\"aeoncl@shlasouf.local\" Type=\"8\" SHA1D=\"ZrBqPeI0+PvpbXgDPnTr8Xiywz8=\" Size=\"28379\" Location=\"TFR375.cab\" Friendly=\"AAA=\" contenttype=\"P\"/>\r\n\r\n";
+        let raw = format!("UUM 29 aeoncl@shlasouf.local 1 3 {}\r\n{}", body.len(), body);
+        let raw_command = command_parser.parse_message(raw.as_bytes()).unwrap().pop().unwrap();
+        let uum_client = UumClient::try_from_raw(raw_command).unwrap();
+
+        assert!(matches!(uum_client.payload, UumPayload::Wink(_)));
+    }
 }
 
 
@@ -119,8 +147,10 @@ pub enum MessageType {
 
 pub enum UumPayload {
     TextMessage(TextMessageContent),
+    Emoticon(EmoticonMessageContent),
     TypingUser(TypingUserMessageContent),
     Nudge(DatacastMessageContent),
+    Wink(DatacastMessageContent),
     Raw(RawMsgPayload),
 }
 
@@ -130,8 +160,16 @@ impl UumPayload {
         match payload_type {
             MessageType::TextMessage => {
                 let raw = RawMsgPayload::try_from_bytes(payload)?;
-                let content = TextMessageContent::try_from_raw(raw)?;
-                Ok(UumPayload::TextMessage(content))
+                match raw.get_content_type()? {
+                    MsgContentType::Emoticon | MsgContentType::AnimEmoticon => {
+                        let content = EmoticonMessageContent::try_from_raw(raw)?;
+                        Ok(UumPayload::Emoticon(content))
+                    },
+                    _ => {
+                        let content = TextMessageContent::try_from_raw(raw)?;
+                        Ok(UumPayload::TextMessage(content))
+                    }
+                }
             },
             MessageType::TypingUser => {
                 let raw = RawMsgPayload::try_from_bytes(payload)?;
@@ -141,10 +179,11 @@ impl UumPayload {
             MessageType::Nudge => {
                 let raw = RawMsgPayload::try_from_bytes(payload)?;
                 let content = DatacastMessageContent::try_from_raw(raw)?;
-                if content.get_type() != DatacastType::Nudge {
-                    return Err(PayloadError::AnyError(anyhow!("Wrong datacast type for UUM Nudge message: expected 1, got {}", content.get_type() as u32)))
+                match content.get_type() {
+                    DatacastType::Nudge => Ok(UumPayload::Nudge(content)),
+                    DatacastType::Wink => Ok(UumPayload::Wink(content)),
+                    other => Err(PayloadError::AnyError(anyhow!("Wrong datacast type for UUM Nudge message: expected 1 or 2, got {}", other as u32)))
                 }
-                Ok(UumPayload::Nudge(content))
             },
             MessageType::UnknownYet => {
                 let raw = RawMsgPayload::try_from_bytes(payload)?;
@@ -160,10 +199,13 @@ impl IntoBytes for UumPayload {
             UumPayload::TextMessage(content) => {
                 content.into_bytes()
             }
+            UumPayload::Emoticon(content) => {
+                content.into_bytes()
+            }
             UumPayload::TypingUser(content) => {
                 content.into_bytes()
             }
-            UumPayload::Nudge(content) => {
+            UumPayload::Nudge(content) | UumPayload::Wink(content) => {
                 content.into_bytes()
             }
             UumPayload::Raw(content) => {
@@ -176,13 +218,13 @@ impl IntoBytes for UumPayload {
 impl From<&UumPayload> for MessageType {
     fn from(value: &UumPayload) -> Self {
         match value {
-            UumPayload::TextMessage(_) => {
+            UumPayload::TextMessage(_) | UumPayload::Emoticon(_) => {
                 MessageType::TextMessage
             }
             UumPayload::TypingUser(_) => {
                 MessageType::TypingUser
             }
-            UumPayload::Nudge(_) => {
+            UumPayload::Nudge(_) | UumPayload::Wink(_) => {
                 MessageType::Nudge
             }
             UumPayload::Raw(_) => {
@@ -10,10 +10,48 @@ use crate::{msnp::{error::{CommandError, PayloadError}, notification::models::en
 use crate::shared::models::endpoint_id::EndpointId;
 use crate::shared::traits::{MSNPCommand, MSNPCommandPart, MSNPPayload};
 
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use crate::msnp::raw_command_parser::RawCommandParser;
+    use crate::shared::models::endpoint_id::EndpointId;
+    use crate::shared::traits::{MSNPCommand, MSNPPayload};
+
+    use super::{UbnServer, UunClient, UunPayload};
+
+    #[test]
+    fn uun_conversation_window_closed_deser() {
+        let mut command_parser = RawCommandParser::new();
+        let raw = "UUN 8 aeoncl@shlasouf.local 5 19\r\nbob@shlasouf.local\r\n";
+        let raw_command = command_parser.parse_message(raw.as_bytes()).unwrap().pop().unwrap();
+        let uun_client = UunClient::try_from_raw(raw_command).unwrap();
+
+        assert_eq!(8, uun_client.tr_id);
+        assert_eq!("aeoncl@shlasouf.local", uun_client.destination.email_addr.as_str());
+
+        if let UunPayload::ConversationWindowClosed { email_addr } = uun_client.payload {
+            assert_eq!("bob@shlasouf.local", &email_addr);
+        } else {
+            panic!("Expected ConversationWindowClosed payload");
+        }
+    }
+
+    #[test]
+    fn ubn_conversation_window_closed_ser() {
+        let ubn = UbnServer {
+            destination: EndpointId::from_str("aeoncl@shlasouf.local").unwrap(),
+            payload: UunPayload::ConversationWindowClosed { email_addr: "bob@shlasouf.local".to_string() },
+        };
+
+        assert_eq!("UBN aeoncl@shlasouf.local 5 18\r\nbob@shlasouf.local", &String::from_utf8(ubn.into_bytes()).unwrap());
+    }
+}
+
 pub struct UunClient {
-    tr_id: u128,
-    destination: EndpointId,
-    payload: UunPayload
+    pub tr_id: u128,
+    pub destination: EndpointId,
+    pub payload: UunPayload
 }
 
 impl UunClient {
@@ -70,7 +108,7 @@ impl MSNPPayload for UunPayload {
         match self {
             UunPayload::DisconnectClient => b"goawyplzthxbye".to_vec(),
             UunPayload::DisconnectAllClients => b"gtfo".to_vec(),
-            UunPayload::ConversationWindowClosed { email_addr } => todo!(),
+            UunPayload::ConversationWindowClosed { email_addr } => email_addr.into_bytes(),
             UunPayload::DismissUserInvite { email_addr, unknown } => format!("{} {}", email_addr, unknown).as_bytes().to_vec(),
             UunPayload::Resynchronize(payload) => payload.to_string().as_bytes().to_vec(),
             UunPayload::Unknown(payload) => payload.to_owned(),
@@ -91,6 +129,10 @@ impl UunPayload {
             },
             UserNotificationType::DisconnectAllClients => {
                 Self::DisconnectAllClients
+            },
+            UserNotificationType::ClosedConversation => {
+                let email_addr = from_utf8(&payload)?.trim().to_string();
+                Self::ConversationWindowClosed { email_addr }
             }
             _ => {
                 Self::Unknown(payload)
@@ -173,8 +215,8 @@ impl Display for UunSoapStatePayload {
 
 pub type UbnPayload = UunPayload;
 pub struct UbnServer {
-    destination: EndpointId,
-    payload: UbnPayload
+    pub destination: EndpointId,
+    pub payload: UbnPayload
 }
 
 impl MSNPCommand for UbnServer {
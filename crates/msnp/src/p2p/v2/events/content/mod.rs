@@ -3,3 +3,6 @@ pub mod message_event_content;
 pub mod file_transfer_accepted_event_content;
 pub mod msn_object_requested_event_content;
 pub mod msb_object_received_event_content;
+pub mod session_declined_event_content;
+pub mod session_cancelled_event_content;
+pub mod session_timed_out_event_content;
@@ -0,0 +1,13 @@
+use crate::{p2p::v2::{session::p2p_session_type::P2PSessionType, slp_payload::SlpStatusCode}, shared::models::msn_user::MsnUser};
+
+
+#[derive(Clone, Debug)]
+pub struct SessionCancelledEventContent {
+   pub session_id: u32,
+   pub sender: MsnUser,
+   pub receiver: MsnUser,
+   /* What the session was transferring, when we still knew about it */
+   pub session_type: Option<P2PSessionType>,
+   /* Set when the session was aborted by an SLP error response instead of a BYE */
+   pub status_code: Option<SlpStatusCode>
+}
@@ -0,0 +1,10 @@
+use crate::{p2p::v2::session::p2p_session_type::P2PSessionType, shared::models::msn_user::MsnUser};
+
+
+#[derive(Clone, Debug)]
+pub struct SessionDeclinedEventContent {
+   pub session_id: u32,
+   pub sender: MsnUser,
+   pub receiver: MsnUser,
+   pub session_type: Option<P2PSessionType>
+}
@@ -0,0 +1,12 @@
+use crate::{p2p::v2::session::{p2p_direction::P2PDirection, p2p_session_type::P2PSessionType, p2p_status::P2PSessionStatus}, shared::models::msn_user::MsnUser};
+
+
+#[derive(Clone, Debug)]
+pub struct SessionTimedOutEventContent {
+   pub session_id: u32,
+   pub direction: P2PDirection,
+   pub status: P2PSessionStatus,
+   pub inviter: MsnUser,
+   pub invitee: MsnUser,
+   pub session_type: P2PSessionType
+}
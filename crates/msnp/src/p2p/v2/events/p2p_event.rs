@@ -1,4 +1,4 @@
-use super::content::{file_received_event_content::FileReceivedEventContent, file_transfer_accepted_event_content::FileTransferAcceptedEventContent, message_event_content::MessageEventContent, msb_object_received_event_content::MSNObjectReceivedEventContent, msn_object_requested_event_content::MSNObjectRequestedEventContent};
+use super::content::{file_received_event_content::FileReceivedEventContent, file_transfer_accepted_event_content::FileTransferAcceptedEventContent, message_event_content::MessageEventContent, msb_object_received_event_content::MSNObjectReceivedEventContent, msn_object_requested_event_content::MSNObjectRequestedEventContent, session_cancelled_event_content::SessionCancelledEventContent, session_declined_event_content::SessionDeclinedEventContent, session_timed_out_event_content::SessionTimedOutEventContent};
 
 
 #[derive(Debug)]
@@ -7,5 +7,8 @@ pub enum P2PEvent {
     Message(MessageEventContent),
     FileTransferAccepted(FileTransferAcceptedEventContent),
     MSNObjectRequested(MSNObjectRequestedEventContent),
-    MSNObjectReceived(MSNObjectReceivedEventContent)
+    MSNObjectReceived(MSNObjectReceivedEventContent),
+    SessionDeclined(SessionDeclinedEventContent),
+    SessionCancelled(SessionCancelledEventContent),
+    SessionTimedOut(SessionTimedOutEventContent)
 }
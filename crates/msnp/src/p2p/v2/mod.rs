@@ -12,6 +12,8 @@ pub mod session;
 pub mod app_id;
 pub mod models;
 pub mod error;
+pub mod p2p_client;
+pub mod p2p_client_config;
 
 pub mod factories {
     use base64::Engine;
@@ -20,7 +22,7 @@ pub mod factories {
 
     use crate::{msnp::error::PayloadError, shared::models::{msn_object::MsnObject, msn_user::MsnUser, uuid::Uuid}};
 
-    use super::{p2p_payload::P2PPayload, p2p_transport_packet::P2PTransportPacket, slp_context::PreviewData, slp_payload::{EufGUID, SlpPayload}, tlv::TLV};
+    use super::{p2p_payload::P2PPayload, p2p_transport_packet::P2PTransportPacket, slp_context::PreviewData, slp_payload::{EufGUID, SlpPayload, SlpStatusCode, SLP_CANCEL_CONTEXT}, tlv::TLV};
 
     /**
  * RT5'}L³E[@
@@ -370,6 +372,70 @@ SessionID: 2216804035
             return Ok(out);
         }
 
+        pub fn get_error_response(request: &SlpPayload, status: SlpStatusCode) -> Result<SlpPayload, PayloadError> {
+            let mut out = SlpPayload::new();
+            out.first_line = format!("MSNSLP/1.0 {}", status);
+
+            out.add_header(String::from("To"), request.get_header(&String::from("From"))
+                .ok_or(PayloadError::MandatoryPartNotFound {name: String::from("From"), payload: format!("{:?}", &request) })?
+                .to_owned());
+
+            out.add_header(String::from("From"), request.get_header(&String::from("To"))
+                .ok_or(PayloadError::MandatoryPartNotFound {name: String::from("To"), payload: format!("{:?}", &request) })?
+                .to_owned());
+
+            out.add_header(String::from("Via"), request.get_header(&String::from("Via"))
+                .ok_or(PayloadError::MandatoryPartNotFound {name: String::from("Via"), payload: format!("{:?}", &request) })?
+                .to_owned());
+
+            let cseq = request.headers.get("CSeq")
+                .ok_or(PayloadError::MandatoryPartNotFound {name: String::from("CSeq"), payload: format!("{:?}", &request) })?
+                .trim()
+                .parse::<i32>()? + 1;
+
+            out.add_header(String::from("CSeq"), cseq.to_string());
+
+            out.add_header(String::from("Call-ID"), request.get_header(&String::from("Call-ID"))
+                .ok_or(PayloadError::MandatoryPartNotFound {name: String::from("Call-ID"), payload: format!("{:?}", &request) })?
+                .to_owned());
+
+            out.add_header(String::from("Max-Forwards"), String::from("0"));
+
+            match request.get_body_property("SessionID") {
+                Some(session_id) => {
+                    out.add_header(String::from("Content-Type"), String::from("application/x-msnmsgr-sessionreqbody"));
+                    out.add_body_property(String::from("SessionID"), session_id.to_owned());
+                },
+                None => {
+                    out.add_header(String::from("Content-Type"), String::from("null"));
+                }
+            }
+
+            return Ok(out);
+        }
+
+        pub fn get_404_not_found(request: &SlpPayload) -> Result<SlpPayload, PayloadError> {
+            return SlpPayloadFactory::get_error_response(request, SlpStatusCode::NotFound);
+        }
+
+        pub fn get_481_no_such_call(request: &SlpPayload) -> Result<SlpPayload, PayloadError> {
+            return SlpPayloadFactory::get_error_response(request, SlpStatusCode::NoSuchCall);
+        }
+
+        pub fn get_500_internal_error(request: &SlpPayload) -> Result<SlpPayload, PayloadError> {
+            return SlpPayloadFactory::get_error_response(request, SlpStatusCode::InternalError);
+        }
+
+        pub fn get_603_decline(invite: &SlpPayload) -> Result<SlpPayload, PayloadError> {
+            return SlpPayloadFactory::get_error_response(invite, SlpStatusCode::Decline);
+        }
+
+        pub fn get_session_cancel(sender: &MsnUser, receiver: &MsnUser, call_id: Uuid, session_id: String) -> Result<SlpPayload, PayloadError> {
+            let mut out = SlpPayloadFactory::get_session_bye(sender, receiver, call_id, session_id)?;
+            out.add_body_property(String::from("Context"), String::from(SLP_CANCEL_CONTEXT));
+            return Ok(out);
+        }
+
         pub fn get_transport_request(sender: &MsnUser, receiver: &MsnUser) -> SlpPayload {
             let mut out = SlpPayload::new();
 
@@ -0,0 +1,21 @@
+use std::time::Duration;
+
+#[derive(Clone, Debug)]
+pub struct P2PClientConfig {
+    /* A session without any packet for this long is closed with a BYE */
+    pub session_idle_timeout: Duration,
+    /* Chunked packets and packets waiting for the handshake are dropped after this long */
+    pub pending_packet_timeout: Duration,
+    /* How often the background sweep runs */
+    pub sweep_interval: Duration
+}
+
+impl Default for P2PClientConfig {
+    fn default() -> Self {
+        P2PClientConfig {
+            session_idle_timeout: Duration::from_secs(120),
+            pending_packet_timeout: Duration::from_secs(60),
+            sweep_interval: Duration::from_secs(15)
+        }
+    }
+}
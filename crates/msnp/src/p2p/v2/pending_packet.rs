@@ -1,5 +1,7 @@
 
 
+use std::time::Instant;
+
 use crate::{msnp::error::PayloadError, shared::models::msn_user::MsnUser};
 
 use super::p2p_transport_packet::P2PTransportPacket;
@@ -9,17 +11,23 @@ pub struct PendingPacket {
     pub packet: P2PTransportPacket,
     chunks: Vec<P2PTransportPacket>,
     pub sender: MsnUser,
-    pub receiver: MsnUser
+    pub receiver: MsnUser,
+    last_activity: Instant
 }
 
 impl PendingPacket {
     
     pub fn new(packet: P2PTransportPacket, sender: MsnUser, receiver: MsnUser) -> Self {
-        return PendingPacket{ packet, sender, receiver, chunks: Vec::new() };
+        return PendingPacket{ packet, sender, receiver, chunks: Vec::new(), last_activity: Instant::now() };
     }
 
     pub fn add_chunk(&mut self, packet: P2PTransportPacket) {
         self.chunks.push(packet);
+        self.last_activity = Instant::now();
+    }
+
+    pub fn get_last_activity(&self) -> Instant {
+        self.last_activity
     }
 
     pub fn get_packet(&self) -> Result<P2PTransportPacket, PayloadError> {
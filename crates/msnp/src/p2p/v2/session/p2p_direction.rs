@@ -1,4 +1,5 @@
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum P2PDirection {
     INBOUND,
     OUTBOUND
-}
\ No newline at end of file
+}
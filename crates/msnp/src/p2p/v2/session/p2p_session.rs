@@ -1,5 +1,7 @@
 
-use crate::{p2p::v2::pending_packet::PendingPacket, shared::models::msn_user::MsnUser};
+use std::time::{Duration, Instant};
+
+use crate::{p2p::v2::{pending_packet::PendingPacket, slp_payload::SlpPayload}, shared::models::{msn_user::MsnUser, uuid::Uuid}};
 
 use super::{p2p_session_type::P2PSessionType, p2p_status::P2PSessionStatus};
 
@@ -8,15 +10,21 @@ pub struct P2PSession {
     session_type: P2PSessionType,
     session_id: u32,
     status: P2PSessionStatus,
+    call_id: Option<Uuid>,
     inviter: MsnUser,
     invitee: MsnUser,
     content: Option<PendingPacket>,
+    /* The INVITE of an inbound session, kept until we answer it */
+    invite: Option<SlpPayload>,
+    created_at: Instant,
+    last_activity: Instant,
 }
 
 impl P2PSession {
 
     pub fn new(session_type: P2PSessionType, session_id: u32, inviter: MsnUser, invitee: MsnUser) -> Self {
-        P2PSession { session_type, session_id, status: P2PSessionStatus::WAITING, inviter, invitee, content: None }
+        let now = Instant::now();
+        P2PSession { session_type, session_id, status: P2PSessionStatus::WAITING, call_id: None, inviter, invitee, content: None, invite: None, created_at: now, last_activity: now }
     }
 
     pub fn set_session_id(&mut self, session_id: u32) {
@@ -31,6 +39,18 @@ impl P2PSession {
         self.status
     }
 
+    pub fn set_status(&mut self, status: P2PSessionStatus) {
+        self.status = status;
+    }
+
+    pub fn set_call_id(&mut self, call_id: Uuid) {
+        self.call_id = Some(call_id);
+    }
+
+    pub fn get_call_id(&self) -> Option<&Uuid> {
+        self.call_id.as_ref()
+    }
+
     pub fn get_inviter(&self) -> MsnUser {
         self.inviter.clone()
     }
@@ -39,6 +59,14 @@ impl P2PSession {
         self.invitee.clone()
     }
 
+    pub fn set_invite(&mut self, invite: SlpPayload) {
+        self.invite = Some(invite);
+    }
+
+    pub fn get_invite(&self) -> Option<&SlpPayload> {
+        self.invite.as_ref()
+    }
+
     pub fn get_content(&self) -> Option<&PendingPacket> {
         self.content.as_ref()
     }
@@ -51,4 +79,20 @@ impl P2PSession {
         &self.session_type
     }
 
+    pub fn touch(&mut self) {
+        self.last_activity = Instant::now();
+    }
+
+    pub fn get_created_at(&self) -> Instant {
+        self.created_at
+    }
+
+    pub fn get_last_activity(&self) -> Instant {
+        self.last_activity
+    }
+
+    pub fn get_idle_time(&self, now: Instant) -> Duration {
+        now.saturating_duration_since(self.last_activity)
+    }
+
 }
\ No newline at end of file
@@ -6,5 +6,7 @@ pub enum P2PSessionStatus {
     WAITING,
     ONGOING,
     CANCELLED,
-    DONE
-}
\ No newline at end of file
+    DONE,
+    DECLINED,
+    ERROR
+}
@@ -140,6 +140,37 @@ impl SlpPayload {
         return self.first_line.contains("200 OK");
     }
 
+    pub fn is_bye(&self) -> bool {
+        return self.first_line.starts_with("BYE");
+    }
+
+    pub fn is_response(&self) -> bool {
+        return self.first_line.starts_with("MSNSLP/");
+    }
+
+    pub fn get_status_code(&self) -> Option<SlpStatusCode> {
+        if !self.is_response() {
+            return None;
+        }
+
+        let code = self.first_line.split_whitespace().nth(1)?;
+        return SlpStatusCode::from_str(code).ok();
+    }
+
+    pub fn get_session_id(&self) -> Result<Option<u32>, PayloadError> {
+        let session_id = self.get_body_property("SessionID");
+        if session_id.is_none() {
+            return Ok(None);
+        }
+
+        return Ok(Some(session_id.unwrap().trim().parse::<u32>()?));
+    }
+
+    /* A BYE carrying this context is sent by the inviter to cancel a session before it was accepted */
+    pub fn is_cancel(&self) -> bool {
+        return self.is_bye() && self.get_body_property("Context") == Some(SLP_CANCEL_CONTEXT);
+    }
+
 }
 
 impl FromStr for SlpPayload {
@@ -211,6 +242,60 @@ impl Display for SlpPayload {
     }
 }
 
+pub const SLP_CANCEL_CONTEXT: &str = "dAMAgQ==";
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SlpStatusCode {
+    Ok,
+    NotFound,
+    NoSuchCall,
+    InternalError,
+    Decline
+}
+
+impl SlpStatusCode {
+    pub fn get_code(&self) -> u32 {
+        match self {
+            SlpStatusCode::Ok => 200,
+            SlpStatusCode::NotFound => 404,
+            SlpStatusCode::NoSuchCall => 481,
+            SlpStatusCode::InternalError => 500,
+            SlpStatusCode::Decline => 603
+        }
+    }
+
+    pub fn get_reason(&self) -> &str {
+        match self {
+            SlpStatusCode::Ok => "OK",
+            SlpStatusCode::NotFound => "Not Found",
+            SlpStatusCode::NoSuchCall => "No Such Call",
+            SlpStatusCode::InternalError => "Internal Error",
+            SlpStatusCode::Decline => "Decline"
+        }
+    }
+}
+
+impl Display for SlpStatusCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        return write!(f, "{} {}", self.get_code(), self.get_reason());
+    }
+}
+
+impl FromStr for SlpStatusCode {
+    type Err = PayloadError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "200" => Ok(SlpStatusCode::Ok),
+            "404" => Ok(SlpStatusCode::NotFound),
+            "481" => Ok(SlpStatusCode::NoSuchCall),
+            "500" => Ok(SlpStatusCode::InternalError),
+            "603" => Ok(SlpStatusCode::Decline),
+            _ => Err(PayloadError::StringPayloadParsingError { payload: s.to_string(), source: anyhow!("Unknown SLP status code") })
+        }
+    }
+}
+
 #[derive(PartialEq, Debug)]
 pub enum EufGUID {
     MSNObject,
@@ -282,7 +367,7 @@ mod tests {
 
     use crate::p2p::v2::slp_payload::SlpPayload;
 
-    use super::EufGUID;
+    use super::{EufGUID, SlpStatusCode};
 
     #[test]
     fn test_euf_guid_try_from_str() {
@@ -311,4 +396,26 @@ mod tests {
         let result = payload.get_euf_guid().unwrap();
         assert_eq!(result.is_none(), true);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_slp_payload_get_status_code() {
+        let mut payload = SlpPayload::new();
+        payload.first_line = String::from("MSNSLP/1.0 603 Decline");
+
+        assert!(payload.is_response());
+        assert_eq!(payload.get_status_code(), Some(SlpStatusCode::Decline));
+    }
+
+    #[test]
+    fn test_slp_payload_cancel() {
+        let mut payload = SlpPayload::new();
+        payload.first_line = String::from("BYE MSNMSGR:aeontest@shl.local MSNSLP/1.0");
+        payload.add_body_property(String::from("SessionID"), String::from("2216804035"));
+        payload.add_body_property(String::from("Context"), String::from("dAMAgQ=="));
+
+        assert!(payload.is_bye());
+        assert!(payload.is_cancel());
+        assert_eq!(payload.get_status_code(), None);
+        assert_eq!(payload.get_session_id().unwrap(), Some(2216804035));
+    }
+}
@@ -0,0 +1,60 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+use crate::msnp::error::CommandError;
+use crate::msnp::raw_command_parser::RawCommand;
+use crate::shared::traits::MSNPCommand;
+
+/* Numeric MSNP error reply to a client command, e.g. 241 for an invalid ADL */
+pub struct ErrorCommand {
+    pub error_code: u32,
+    pub tr_id: u128
+}
+
+impl Display for ErrorCommand {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{error_code} {tr_id}\r\n", error_code = self.error_code, tr_id = self.tr_id)
+    }
+}
+
+impl MSNPCommand for ErrorCommand {
+    type Err = CommandError;
+
+    fn try_from_raw(raw: RawCommand) -> Result<Self, Self::Err> {
+        let mut split = raw.command_split;
+
+        let raw_error_code = split.pop_front().ok_or(CommandError::MissingArgument(raw.command.clone(), "error_code".into(), 0))?;
+        let error_code = u32::from_str(&raw_error_code)?;
+
+        let raw_tr_id = split.pop_front().ok_or(CommandError::MissingArgument(raw.command.clone(), "tr_id".into(), 1))?;
+        let tr_id = u128::from_str(&raw_tr_id)?;
+
+        Ok(Self { error_code, tr_id })
+    }
+
+    fn into_bytes(self) -> Vec<u8> {
+        self.to_string().into_bytes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::msnp::raw_command_parser::RawCommand;
+    use crate::shared::command::error::ErrorCommand;
+    use crate::shared::traits::MSNPCommand;
+
+    #[test]
+    fn test_serialize() {
+        let error = ErrorCommand { error_code: 241, tr_id: 12 };
+        assert_eq!("241 12\r\n", error.to_string().as_str());
+    }
+
+    #[test]
+    fn test_deserialize() {
+        let error = ErrorCommand::try_from_raw(RawCommand::without_payload("241 12")).unwrap();
+        assert_eq!(error.error_code, 241);
+        assert_eq!(error.tr_id, 12);
+
+        assert!(ErrorCommand::try_from_raw(RawCommand::without_payload("241")).is_err());
+    }
+}
@@ -1,2 +1,3 @@
 pub mod command;
-pub mod ok;
\ No newline at end of file
+pub mod ok;
+pub mod error;
\ No newline at end of file
@@ -0,0 +1,94 @@
+use byteorder::{ByteOrder, LittleEndian};
+use md5::{Digest, Md5};
+use rand::Rng;
+
+#[cfg(test)]
+mod tests {
+    use super::{compute_challenge_response, generate_challenge, get_product_key, PRODUCT_ID_MSNP15, PRODUCT_KEY_MSNP15};
+
+    #[test]
+    fn compute_msnp11_challenge() {
+        let response = compute_challenge_response("13038318816579321232", "PROD0090YUAUV{2B", "YMM8C_H7KCQ2S_KL");
+        assert_eq!("b01c13020e374d4fa20abfad6981b7a9", &response);
+    }
+
+    #[test]
+    fn generated_challenge_is_numeric() {
+        let challenge = generate_challenge();
+        assert_eq!(20, challenge.len());
+        assert!(challenge.chars().all(|c| c.is_ascii_digit()));
+    }
+
+    #[test]
+    fn unknown_product_id() {
+        assert_eq!(None, get_product_key("PROD0000000000"));
+        assert_eq!(Some(PRODUCT_KEY_MSNP15), get_product_key(PRODUCT_ID_MSNP15));
+    }
+}
+
+pub const PRODUCT_ID_MSNP15: &str = "PROD0119GSJUC$18";
+pub const PRODUCT_KEY_MSNP15: &str = "ILTXC!4IXB5FB*PX";
+
+pub const PRODUCT_ID_WLM2009: &str = "PROD0120PW!CCV9@";
+pub const PRODUCT_KEY_WLM2009: &str = "C1BX{V4W}Q3*10SM";
+
+const MAGIC: u64 = 0x0E79A9C1;
+const MODULO: u64 = 0x7FFFFFFF;
+
+/* The product key is the shared secret matching the product id (the appid of the OIM Ticket) */
+pub fn get_product_key(product_id: &str) -> Option<&'static str> {
+    match product_id {
+        PRODUCT_ID_MSNP15 => Some(PRODUCT_KEY_MSNP15),
+        PRODUCT_ID_WLM2009 => Some(PRODUCT_KEY_WLM2009),
+        _ => None
+    }
+}
+
+/* CHL challenges are 20 digits long */
+pub fn generate_challenge() -> String {
+    let mut rng = rand::thread_rng();
+    (0..20).map(|_| char::from(b'0' + rng.gen_range(0..10))).collect()
+}
+
+/* The CHL/QRY algorithm, also used as the OIM lock key */
+pub fn compute_challenge_response(challenge: &str, product_id: &str, product_key: &str) -> String {
+    let mut md5 = Md5::new();
+    md5.update(challenge.as_bytes());
+    md5.update(product_key.as_bytes());
+    let hash: [u8; 16] = md5.finalize().into();
+
+    let mut hash_parts = [0u32; 4];
+    LittleEndian::read_u32_into(&hash, &mut hash_parts);
+    let md5_parts = hash_parts.map(|part| (part & 0x7FFFFFFF) as u64);
+
+    let mut chl = format!("{}{}", challenge, product_id).into_bytes();
+    chl.resize(chl.len().div_ceil(8) * 8, b'0');
+
+    let mut chl_parts = vec![0u32; chl.len() / 4];
+    LittleEndian::read_u32_into(&chl, &mut chl_parts);
+
+    let mut high: u64 = 0;
+    let mut low: u64 = 0;
+
+    for pair in chl_parts.chunks_exact(2) {
+        let mut temp = (MAGIC * pair[0] as u64) % MODULO;
+        temp = (md5_parts[0] * (temp + high) + md5_parts[1]) % MODULO;
+
+        high = (pair[1] as u64 + temp) % MODULO;
+        high = (md5_parts[2] * high + md5_parts[3]) % MODULO;
+
+        low = low + high + temp;
+    }
+
+    high = (high + md5_parts[1]) % MODULO;
+    low = (low + md5_parts[3]) % MODULO;
+
+    hash_parts[0] ^= high as u32;
+    hash_parts[1] ^= low as u32;
+    hash_parts[2] ^= high as u32;
+    hash_parts[3] ^= low as u32;
+
+    let mut out = [0u8; 16];
+    LittleEndian::write_u32_into(&hash_parts, &mut out);
+    hex::encode(out)
+}
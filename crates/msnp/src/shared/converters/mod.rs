@@ -1,2 +1,3 @@
 pub mod rfc2047;
-pub mod filetime;
\ No newline at end of file
+pub mod filetime;
+pub mod challenge;
\ No newline at end of file
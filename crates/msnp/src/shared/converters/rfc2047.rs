@@ -1,4 +1,5 @@
 use std::fmt::format;
+use base64::Engine;
 use email_encoding::headers::writer::EmailWriter;
 
 const ENCODING_START_PREFIX: &str = "=?utf-8?b?";
@@ -21,9 +22,80 @@ pub fn encode(data: &str) -> String {
     encoded
 }
 
+/* Decodes every =?charset?encoding?data?= word, only UTF-8 compatible charsets are supported.
+Whitespace between two encoded words is dropped as per the RFC */
+pub fn decode(data: &str) -> String {
+    let mut out = String::new();
+    let mut rest = data;
+    let mut previous_was_encoded = false;
+
+    while let Some(start) = rest.find("=?") {
+        let (before, word) = rest.split_at(start);
+
+        let Some((decoded, consumed)) = decode_word(word) else {
+            out.push_str(&rest[..start + 2]);
+            rest = &rest[start + 2..];
+            previous_was_encoded = false;
+            continue;
+        };
+
+        if !(previous_was_encoded && before.trim().is_empty()) {
+            out.push_str(before);
+        }
+
+        out.push_str(&decoded);
+        rest = &word[consumed..];
+        previous_was_encoded = true;
+    }
+
+    out.push_str(rest);
+    out
+}
+
+fn decode_word(word: &str) -> Option<(String, usize)> {
+    let mut parts = word[2..].splitn(3, '?');
+    let _charset = parts.next()?;
+    let encoding = parts.next()?;
+    let remaining = parts.next()?;
+    let data_length = remaining.find(ENCODING_END_SUFFIX)?;
+    let data = &remaining[..data_length];
+
+    let bytes = match encoding {
+        "B" | "b" => base64::engine::general_purpose::STANDARD.decode(data).ok()?,
+        "Q" | "q" => decode_quoted_printable(data)?,
+        _ => {
+            return None;
+        }
+    };
+
+    let consumed = word.len() - remaining.len() + data_length + ENCODING_END_SUFFIX.len();
+    Some((String::from_utf8_lossy(&bytes).to_string(), consumed))
+}
+
+fn decode_quoted_printable(data: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(data.len());
+    let bytes = data.as_bytes();
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'_' => out.push(b' '),
+            b'=' => {
+                let hex = data.get(i + 1..i + 3)?;
+                out.push(u8::from_str_radix(hex, 16).ok()?);
+                i += 2;
+            },
+            other => out.push(other)
+        }
+        i += 1;
+    }
+
+    Some(out)
+}
+
 #[cfg(test)]
 mod tests {
-    use super::encode;
+    use super::{decode, encode};
     #[test]
     fn test_mail_encoding_ascii() {
         let name = "Inky";
@@ -40,4 +112,16 @@ mod tests {
         assert_eq!("=?utf-8?B?QWxpbsOpYQ==?=", &encoded);
     }
 
-}
\ No newline at end of file
+    #[test]
+    fn test_mail_decoding() {
+        assert_eq!("Alinéa", &decode("=?utf-8?B?QWxpbsOpYQ==?="));
+        assert_eq!("Inky", &decode("Inky"));
+    }
+
+    #[test]
+    fn test_mail_decoding_mixed() {
+        assert_eq!("Hey AlinéaAlinéa !", &decode("Hey =?utf-8?B?QWxpbsOpYQ==?= =?UTF-8?Q?Alin=C3=A9a?= !"));
+        assert_eq!("=?broken", &decode("=?broken"));
+    }
+
+}
@@ -0,0 +1,218 @@
+use anyhow::anyhow;
+use byteorder::{ByteOrder, LittleEndian};
+use flate2::{Decompress, FlushDecompress};
+
+use crate::msnp::error::PayloadError;
+
+// Documentation source: https://learn.microsoft.com/en-us/previous-versions/bb417343(v=msdn.10)
+
+#[cfg(test)]
+mod tests {
+    use lazy_static_include::lazy_static_include_bytes;
+
+    use super::Cabinet;
+
+    lazy_static_include_bytes! {
+        WINK_CAB => "assets/wink/TFR375.cab",
+        WINK_MCO => "assets/wink/wink.mco"
+    }
+
+    #[test]
+    fn parse_uncompressed_cabinet() {
+        let cabinet = Cabinet::try_from(WINK_CAB.as_ref()).unwrap();
+
+        assert_eq!(3, cabinet.files.len());
+        assert_eq!("content.xml", &cabinet.files[0].name);
+        assert_eq!(479, cabinet.files[0].data.len());
+        assert!(cabinet.files[0].data.starts_with(b"<?xml"));
+
+        let thumbnail = cabinet.get_file("3065243m.png").unwrap();
+        assert_eq!(4327, thumbnail.data.len());
+        assert!(thumbnail.data.starts_with(b"\x89PNG"));
+
+        assert_eq!(23435, cabinet.get_file("3065243F.SWF").unwrap().data.len());
+    }
+
+    #[test]
+    fn parse_mszip_cabinet() {
+        let cabinet = Cabinet::try_from(WINK_MCO.as_ref()).unwrap();
+
+        assert_eq!(2, cabinet.files.len());
+        assert!(cabinet.get_file("content.xml").unwrap().data.starts_with(b"<package"));
+
+        let inner_cab = cabinet.get_file("TFR375.cab").unwrap();
+        assert_eq!(WINK_CAB.as_ref(), inner_cab.data.as_slice());
+    }
+
+    #[test]
+    fn parse_invalid_cabinet() {
+        assert!(Cabinet::try_from(b"MSCF".as_slice()).is_err());
+        assert!(Cabinet::try_from(b"not a cabinet at all, not at all, not at all".as_slice()).is_err());
+    }
+}
+
+const CAB_SIGNATURE: &[u8; 4] = b"MSCF";
+const CAB_HEADER_LENGTH: usize = 36;
+const CAB_FOLDER_LENGTH: usize = 8;
+const CAB_FILE_LENGTH: usize = 16;
+const CAB_DATA_LENGTH: usize = 8;
+
+const FLAG_PREV_CABINET: u16 = 0x0001;
+const FLAG_NEXT_CABINET: u16 = 0x0002;
+const FLAG_RESERVE_PRESENT: u16 = 0x0004;
+
+const MSZIP_SIGNATURE: &[u8; 2] = b"CK";
+const MSZIP_WINDOW_SIZE: usize = 32768;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum CabinetCompression {
+    None,
+    MsZip
+}
+
+#[derive(Clone, Debug)]
+pub struct CabinetFile {
+    pub name: String,
+    pub data: Vec<u8>
+}
+
+/* Microsoft Cabinet archive, used to package Winks & Messenger content. Only single cabinet sets compressed with NONE or MSZIP are supported */
+#[derive(Clone, Debug)]
+pub struct Cabinet {
+    pub files: Vec<CabinetFile>
+}
+
+impl Cabinet {
+    /* File names in cabinets are case insensitive */
+    pub fn get_file(&self, name: &str) -> Option<&CabinetFile> {
+        self.files.iter().find(|f| f.name.eq_ignore_ascii_case(name))
+    }
+}
+
+impl TryFrom<&[u8]> for Cabinet {
+    type Error = PayloadError;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        if bytes.len() < CAB_HEADER_LENGTH || &bytes[0..4] != CAB_SIGNATURE {
+            return Err(PayloadError::AnyError(anyhow!("Not a cabinet file")));
+        }
+
+        let files_offset = LittleEndian::read_u32(&bytes[16..20]) as usize;
+        let folder_count = LittleEndian::read_u16(&bytes[26..28]) as usize;
+        let file_count = LittleEndian::read_u16(&bytes[28..30]) as usize;
+        let flags = LittleEndian::read_u16(&bytes[30..32]);
+
+        if flags & (FLAG_PREV_CABINET | FLAG_NEXT_CABINET) != 0 {
+            return Err(PayloadError::AnyError(anyhow!("Multi cabinet sets are not supported")));
+        }
+
+        let mut cursor = CAB_HEADER_LENGTH;
+        let mut folder_reserve_length = 0;
+        let mut data_reserve_length = 0;
+
+        if flags & FLAG_RESERVE_PRESENT != 0 {
+            let reserve = read_slice(bytes, cursor, 4)?;
+            let header_reserve_length = LittleEndian::read_u16(&reserve[0..2]) as usize;
+            folder_reserve_length = reserve[2] as usize;
+            data_reserve_length = reserve[3] as usize;
+            cursor += 4 + header_reserve_length;
+        }
+
+        let mut folders = Vec::with_capacity(folder_count);
+        for _ in 0..folder_count {
+            let folder = read_slice(bytes, cursor, CAB_FOLDER_LENGTH)?;
+            let data_offset = LittleEndian::read_u32(&folder[0..4]) as usize;
+            let data_count = LittleEndian::read_u16(&folder[4..6]) as usize;
+            let compression = match LittleEndian::read_u16(&folder[6..8]) & 0x000F {
+                0 => CabinetCompression::None,
+                1 => CabinetCompression::MsZip,
+                other => {
+                    return Err(PayloadError::AnyError(anyhow!("Unsupported cabinet compression: {}", other)));
+                }
+            };
+
+            folders.push(read_folder_data(bytes, data_offset, data_count, data_reserve_length, compression)?);
+            cursor += CAB_FOLDER_LENGTH + folder_reserve_length;
+        }
+
+        let mut files = Vec::with_capacity(file_count);
+        cursor = files_offset;
+        for _ in 0..file_count {
+            let file = read_slice(bytes, cursor, CAB_FILE_LENGTH)?;
+            let size = LittleEndian::read_u32(&file[0..4]) as usize;
+            let folder_offset = LittleEndian::read_u32(&file[4..8]) as usize;
+            let folder_index = LittleEndian::read_u16(&file[8..10]) as usize;
+            cursor += CAB_FILE_LENGTH;
+
+            let name_length = bytes.get(cursor..).and_then(|name| name.iter().position(|b| *b == 0)).ok_or(PayloadError::AnyError(anyhow!("Unterminated cabinet file name")))?;
+            let name = String::from_utf8_lossy(&bytes[cursor..cursor + name_length]).to_string();
+            cursor += name_length + 1;
+
+            let folder = folders.get(folder_index).ok_or(PayloadError::AnyError(anyhow!("Cabinet file {} is in an unknown folder: {}", &name, folder_index)))?;
+            let data = read_slice(folder, folder_offset, size)?.to_vec();
+
+            files.push(CabinetFile { name, data });
+        }
+
+        Ok(Cabinet { files })
+    }
+}
+
+fn read_slice(bytes: &[u8], offset: usize, length: usize) -> Result<&[u8], PayloadError> {
+    bytes.get(offset..offset + length).ok_or(PayloadError::AnyError(anyhow!("Cabinet is truncated: wanted {} bytes at offset {}, size is {}", length, offset, bytes.len())))
+}
+
+fn read_folder_data(bytes: &[u8], mut cursor: usize, data_count: usize, data_reserve_length: usize, compression: CabinetCompression) -> Result<Vec<u8>, PayloadError> {
+    let mut out = Vec::new();
+
+    for _ in 0..data_count {
+        let header = read_slice(bytes, cursor, CAB_DATA_LENGTH)?;
+        let compressed_size = LittleEndian::read_u16(&header[4..6]) as usize;
+        let uncompressed_size = LittleEndian::read_u16(&header[6..8]) as usize;
+        cursor += CAB_DATA_LENGTH + data_reserve_length;
+
+        let block = read_slice(bytes, cursor, compressed_size)?;
+        cursor += compressed_size;
+
+        match compression {
+            CabinetCompression::None => {
+                out.extend_from_slice(block);
+            },
+            CabinetCompression::MsZip => {
+                let inflated = inflate_mszip_block(block, &out, uncompressed_size)?;
+                out.extend_from_slice(&inflated);
+            }
+        }
+    }
+
+    Ok(out)
+}
+
+/* Each MSZIP block is a complete deflate stream which can reference the output of the previous block.
+We replay the previous output as a stored deflate block so the inflater has it in its window. */
+fn inflate_mszip_block(block: &[u8], previous_output: &[u8], uncompressed_size: usize) -> Result<Vec<u8>, PayloadError> {
+    if block.len() < 2 || &block[0..2] != MSZIP_SIGNATURE {
+        return Err(PayloadError::AnyError(anyhow!("MSZIP block is missing its signature")));
+    }
+
+    let history = &previous_output[previous_output.len().saturating_sub(MSZIP_WINDOW_SIZE)..];
+
+    let mut stream = Vec::with_capacity(history.len() + block.len() + 5);
+    if !history.is_empty() {
+        let history_length = history.len() as u16;
+        stream.push(0x00);
+        stream.extend_from_slice(&history_length.to_le_bytes());
+        stream.extend_from_slice(&(!history_length).to_le_bytes());
+        stream.extend_from_slice(history);
+    }
+    stream.extend_from_slice(&block[2..]);
+
+    let mut out = Vec::with_capacity(history.len() + uncompressed_size);
+    Decompress::new(false).decompress_vec(&stream, &mut out, FlushDecompress::Finish).map_err(|e| PayloadError::AnyError(anyhow!("Couldn't inflate MSZIP block: {}", e)))?;
+
+    if out.len() != history.len() + uncompressed_size {
+        return Err(PayloadError::AnyError(anyhow!("MSZIP block inflated to {} bytes instead of {}", out.len().saturating_sub(history.len()), uncompressed_size)));
+    }
+
+    Ok(out.split_off(history.len()))
+}
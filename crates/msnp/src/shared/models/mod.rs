@@ -11,3 +11,5 @@ pub mod email_address;
 pub mod network_id;
 pub mod network_id_email;
 pub mod b64_string;
+pub mod cabinet;
+pub mod wink;
@@ -257,7 +257,7 @@ impl MsnObject {
         return Self{ creator, size, obj_type, location, friendly, sha1d, contenttype, contentid: None, partnerid: None, stamp: None, avatarid: None, avatarcontentid: None, sha1c: String::default(), compute_sha1c };
     }
 
-    fn get_sha1c(&self) -> String {
+    pub fn get_sha1c(&self) -> String {
         if !self.sha1c.is_empty() {
             return self.sha1c.clone();
         }
@@ -500,6 +500,11 @@ impl MSNObjectFactory {
         return MsnObject::new(creator_msn_addr.to_string(), MsnObjectType::DisplayPicture, location, sha1d, image.len(), friendly, Some(MsnObjectContentType::D), false);
     }
 
+    /* Rebuilds a display picture from a cached SHA1D without having the image at hand */
+    pub fn get_display_picture_from_metadata(size: usize, sha1d: String, creator_msn_addr: &EmailAddress, location: String, friendly: FriendlyName) -> MsnObject {
+        return MsnObject::new(creator_msn_addr.to_string(), MsnObjectType::DisplayPicture, location, sha1d, size, friendly, Some(MsnObjectContentType::D), false);
+    }
+
     pub fn get_me_display_picture(image: &[u8], creator_msn_addr: String, friendly: FriendlyName) -> MsnObject {
         let sha1d = compute_sha1(&image);
         return MsnObject::new(creator_msn_addr, MsnObjectType::DisplayPicture, "0".into(), sha1d, image.len(), friendly, None, false);
@@ -510,6 +515,20 @@ impl MSNObjectFactory {
         return MsnObject::new(creator_msn_addr, MsnObjectType::VoiceClip,"0".into(), sha1d, data.len(),  friendly, None, false);
     }
 
+    pub fn get_custom_emoticon(image: &[u8], creator_msn_addr: &EmailAddress, location: String, friendly: FriendlyName) -> MsnObject {
+        let sha1d = compute_sha1(&image);
+        return MsnObject::new(creator_msn_addr.to_string(), MsnObjectType::CustomEmoticon, location, sha1d, image.len(), friendly, None, false);
+    }
+
+    /* The stamp is the signature of the cabinet by the MSN Content Authority, it has to be kept for the wink to be played */
+    pub fn get_wink(cab: &[u8], creator_msn_addr: &EmailAddress, location: String, friendly: FriendlyName, contentid: Option<String>, stamp: Option<String>) -> MsnObject {
+        let sha1d = compute_sha1(&cab);
+        let mut out = MsnObject::new(creator_msn_addr.to_string(), MsnObjectType::Wink, location, sha1d, cab.len(), friendly, Some(MsnObjectContentType::P), false);
+        out.contentid = contentid;
+        out.stamp = stamp;
+        return out;
+    }
+
     pub fn get_contact_display_picture(image: &[u8], creator_msn_addr: String, location: String, friendly: FriendlyName) -> MsnObject {
         let sha1d = compute_sha1(&image);
 
@@ -536,6 +555,7 @@ mod tests {
     use lazy_static_include::lazy_static_include_bytes;
     use crate::{p2p::v2::slp_context::SlpContext, shared::models::msn_object::{compute_sha1, MsnObject, MsnObjectContentType, MSNObjectFactory, MsnObjectType}};
     use crate::shared::models::msn_object::FriendlyName;
+    use crate::shared::models::email_address::EmailAddress;
 
 
     lazy_static_include_bytes! {
@@ -556,7 +576,7 @@ mod tests {
     #[test]
     fn get_display_picture() {
 
-        let obj = MSNObjectFactory::get_display_picture(&AVATAR_BYTES, String::from("aeoncl1@shlasouf.local"), String::from("0"), FriendlyName::new("Flare"));
+        let obj = MSNObjectFactory::get_display_picture(&AVATAR_BYTES, &EmailAddress::from_str("aeoncl1@shlasouf.local").unwrap(), String::from("0"), FriendlyName::new("Flare"));
         let friendly_b64 = obj.friendly.to_string();
         
         let obj_serialized = obj.to_string();
@@ -565,10 +585,20 @@ mod tests {
         assert_eq!(obj_serialized, str);
     }
 
+    #[test]
+    fn get_display_picture_from_metadata() {
+        let creator = EmailAddress::from_str("aeoncl1@shlasouf.local").unwrap();
+        let obj = MSNObjectFactory::get_display_picture(&AVATAR_BYTES, &creator, String::from("TFR2C.tmp"), FriendlyName::default());
+        let from_metadata = MSNObjectFactory::get_display_picture_from_metadata(obj.size, obj.sha1d.clone(), &creator, String::from("TFR2C.tmp"), FriendlyName::default());
+
+        assert_eq!(obj.get_sha1c(), from_metadata.get_sha1c());
+        assert_eq!(obj.to_string(), from_metadata.to_string());
+    }
+
     #[test]
     fn friendly_not_empty() {
 
-        let obj = MSNObjectFactory::get_display_picture(&AVATAR_BYTES, String::from("aeoncl1@shlasouf.local"), String::from("0"), FriendlyName::new("Flare"));
+        let obj = MSNObjectFactory::get_display_picture(&AVATAR_BYTES, &EmailAddress::from_str("aeoncl1@shlasouf.local").unwrap(), String::from("0"), FriendlyName::new("Flare"));
         let friendly_b64 = obj.friendly.to_string();
         
         assert_eq!(&friendly_b64, "RgBsAGEAcgBlAAAA");
@@ -578,7 +608,7 @@ mod tests {
 
     #[test]
     fn friendly_empty() {
-        let obj = MSNObjectFactory::get_display_picture(&AVATAR_BYTES, String::from("aeoncl1@shlasouf.local"), String::from("0"), FriendlyName::default());
+        let obj = MSNObjectFactory::get_display_picture(&AVATAR_BYTES, &EmailAddress::from_str("aeoncl1@shlasouf.local").unwrap(), String::from("0"), FriendlyName::default());
         let friendly_b64 = obj.friendly.to_string();
         
         assert_eq!(&friendly_b64, "AAA=");
@@ -587,7 +617,7 @@ mod tests {
     #[test]
     fn friendly_none() {
 
-        let obj = MSNObjectFactory::get_display_picture(&AVATAR_BYTES, String::from("aeoncl1@shlasouf.local"), String::from("0"), FriendlyName::default());
+        let obj = MSNObjectFactory::get_display_picture(&AVATAR_BYTES, &EmailAddress::from_str("aeoncl1@shlasouf.local").unwrap(), String::from("0"), FriendlyName::default());
         let friendly_b64 = obj.friendly.to_string();
         
         assert_eq!(&friendly_b64, "AAA=");
@@ -18,6 +18,8 @@ pub struct MsnUser {
     pub capabilities: ClientCapabilities,
     pub status: PresenceStatus,
     pub display_name: Option<String>,
+    /* The name the user gave this contact in their address book, wins over the display name */
+    pub nickname: Option<String>,
     pub psm: String,
     pub display_picture: Option<MsnObject>
 }
@@ -31,6 +33,7 @@ impl MsnUser {
             network_id: NetworkId::WindowsLive,
             uuid,
             display_name: None,
+            nickname: None,
             capabilities: ClientCapabilities::default(), 
             status: PresenceStatus::default(), 
             psm: String::default(),
@@ -47,7 +50,9 @@ impl MsnUser {
     }
 
     pub fn compute_display_name(&self) -> &str {
-        if self.display_name.is_some() {
+        if self.nickname.is_some() {
+            self.nickname.as_ref().expect("yes")
+        } else if self.display_name.is_some() {
             self.display_name.as_ref().expect("yes")
         } else {
             &self.endpoint_id.email_addr.0
@@ -62,4 +67,24 @@ impl MsnUser {
         NetworkIdEmail::new(self.network_id.clone(), self.get_email_address().clone())
     }
     
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use crate::shared::models::email_address::EmailAddress;
+    use crate::shared::models::msn_user::MsnUser;
+
+    #[test]
+    fn compute_display_name_prefers_nickname() {
+        let mut user = MsnUser::with_email_addr(EmailAddress::from_str("aeon@test.com").unwrap());
+        assert_eq!("aeon@test.com", user.compute_display_name());
+
+        user.display_name = Some("Aeon".to_string());
+        assert_eq!("Aeon", user.compute_display_name());
+
+        user.nickname = Some("My buddy".to_string());
+        assert_eq!("My buddy", user.compute_display_name());
+    }
+}
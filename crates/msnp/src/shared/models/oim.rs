@@ -2,6 +2,8 @@ use std::fmt::{Display, Formatter};
 use std::io::{Read, Write};
 use std::str::{from_utf8, FromStr};
 
+use anyhow::anyhow;
+use base64::Engine;
 use chrono::{DateTime, Local, Utc};
 use log::Metadata;
 use mime::Mime;
@@ -14,10 +16,11 @@ use yaserde_derive::{YaDeserialize, YaSerialize};
 
 use crate::msnp::error::PayloadError;
 use crate::shared::config::yaserde::CONFIG_NO_DECL;
+use crate::shared::converters::rfc2047;
 use crate::shared::models::email_address::EmailAddress;
 use crate::shared::models::uuid::Uuid;
 use crate::shared::payload::msg::raw_msg_payload::factories::RawMsgPayloadFactory;
-use crate::shared::payload::msg::raw_msg_payload::MsgContentType;
+use crate::shared::payload::msg::raw_msg_payload::{MsgContentType, RawMsgPayload};
 use crate::shared::traits::MSNPPayload;
 use crate::soap::error::SoapMarshallError;
 use crate::soap::traits::xml::ToXml;
@@ -184,12 +187,76 @@ pub struct OIM {
 impl FromStr for OIM {
     type Err = PayloadError;
 
+    /* OIMs travel inside XML, where CRLFs get normalized to LF. From & To are missing from the ones sent to the Store service */
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-       // let msg_payload : RawMsgPayload = RawMsgPayload::from_str(s)?;
-        todo!("Deserializing an OIM is not yet implemented")
+        let normalized = s.trim_start().replace("\r\n", "\n").replace('\n', "\r\n");
+        let msg_payload = RawMsgPayload::try_from_bytes(normalized.into_bytes())?;
+        let content_type = msg_payload.get_content_type()?;
+
+        let (sender_display_name, sender) = match msg_payload.get_header("From") {
+            None => (None, EmailAddress::default()),
+            Some(from) => parse_mailbox(from)?
+        };
+
+        let receiver = match msg_payload.get_header("To") {
+            None => EmailAddress::default(),
+            Some(to) => parse_mailbox(to)?.1
+        };
+
+        let run_id = match msg_payload.get_header("X-OIM-Run-Id") {
+            None => Uuid::new(),
+            Some(run_id) => Uuid::from_str(run_id).map_err(|e| PayloadError::PayloadPropertyParseError { property_name: "X-OIM-Run-Id".into(), raw_value: run_id.into(), payload_type: "OIM".into(), source: anyhow!(e) })?
+        };
+
+        let seq_number = match msg_payload.get_header("X-OIM-Sequence-Num") {
+            None => 1,
+            Some(seq_num) => u32::from_str(seq_num).map_err(|e| PayloadError::PayloadPropertyParseError { property_name: "X-OIM-Sequence-Num".into(), raw_value: seq_num.into(), payload_type: "OIM".into(), source: anyhow!(e) })?
+        };
+
+        let recv_datetime = msg_payload.get_header("Date")
+            .and_then(|date| DateTime::parse_from_str(date, "%d %b %Y %H:%M:%S %z").ok())
+            .map(|date| date.to_utc())
+            .unwrap_or(Utc::now());
+
+        let body = msg_payload.get_body_as_str()?.trim();
+        let content = match msg_payload.get_header("Content-Transfer-Encoding") {
+            Some(encoding) if encoding.eq_ignore_ascii_case("base64") => {
+                let stripped: String = body.split_whitespace().collect();
+                let decoded = base64::engine::general_purpose::STANDARD.decode(&stripped).map_err(|e| PayloadError::StringPayloadParsingError { payload: s.to_string(), source: anyhow!(e) })?;
+                String::from_utf8(decoded)?
+            },
+            _ => body.to_string()
+        };
+
+        Ok(OIM {
+            recv_datetime,
+            sender,
+            sender_display_name,
+            receiver,
+            run_id,
+            seq_number,
+            message_id: msg_payload.get_header("Message-ID").unwrap_or_default().to_string(),
+            content,
+            content_type,
+            read: false,
+        })
     }
 }
 
+/* "=?utf-8?B?QWxpbsOpYQ==?= <alinea@shlasouf.local>" or a bare address */
+fn parse_mailbox(mailbox: &str) -> Result<(Option<String>, EmailAddress), PayloadError> {
+    let (display_name, address) = match mailbox.rsplit_once('<') {
+        None => (None, mailbox),
+        Some((display_name, address)) => {
+            let display_name = rfc2047::decode(display_name.trim());
+            (Some(display_name).filter(|d| !d.is_empty()), address.trim_end_matches('>'))
+        }
+    };
+
+    let address = EmailAddress::from_str(address.trim()).map_err(|e| PayloadError::PayloadPropertyParseError { property_name: "mailbox".into(), raw_value: mailbox.into(), payload_type: "OIM".into(), source: anyhow!(e) })?;
+    Ok((display_name, address))
+}
+
 impl Display for OIM {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         let msg_payload = RawMsgPayloadFactory::get_oim(self.recv_datetime, self.sender.as_str(), self.sender_display_name.as_ref().map(|e| e.as_str()).unwrap_or(""), self.receiver.as_str(), self.run_id.to_string().as_str(), self.seq_number, self.message_id.as_str(), self.content.as_str(), self.content_type.clone());
@@ -243,7 +310,9 @@ mod tests {
     use chrono::Local;
 
     use crate::shared::models::email_address::EmailAddress;
-    use crate::shared::models::oim::{MetaData, MetadataMessage};
+    use crate::shared::models::oim::{MetaData, MetadataMessage, OIM};
+    use crate::shared::models::uuid::Uuid;
+    use crate::shared::payload::msg::raw_msg_payload::MsgContentType;
     use crate::soap::traits::xml::ToXml;
 
     #[test]
@@ -270,4 +339,44 @@ mod tests {
 
     }
 
-}
\ No newline at end of file
+    #[test]
+    fn oim_roundtrip() {
+        let oim = OIM {
+            recv_datetime: Local::now().to_utc(),
+            sender: EmailAddress::from_str("aeon@test.com").unwrap(),
+            sender_display_name: Some("Alinéa".into()),
+            receiver: EmailAddress::from_str("inky@test.com").unwrap(),
+            run_id: Uuid::new(),
+            seq_number: 3,
+            message_id: "msgid".into(),
+            content: "Hello world".into(),
+            content_type: MsgContentType::TextPlain,
+            read: false,
+        };
+
+        let deser = OIM::from_str(&oim.to_string()).unwrap();
+
+        assert_eq!(oim.sender, deser.sender);
+        assert_eq!(Some("Alinéa".to_string()), deser.sender_display_name);
+        assert_eq!(oim.receiver, deser.receiver);
+        assert_eq!(oim.run_id, deser.run_id);
+        assert_eq!(3, deser.seq_number);
+        assert_eq!("msgid", &deser.message_id);
+        assert_eq!("Hello world", &deser.content);
+        assert_eq!(oim.recv_datetime.timestamp(), deser.recv_datetime.timestamp());
+    }
+
+    #[test]
+    fn deser_store2_content() {
+        let content = "MIME-Version: 1.0\nContent-Type: text/plain; charset=UTF-8\nContent-Transfer-Encoding: base64\nX-OIM-Message-Type: OfflineMessage\nX-OIM-Run-Id: {3A3BE82F-CC2D-4CC3-9D4D-7F0E9B2F3D4D}\nX-OIM-Sequence-Num: 2\n\nSGVsbG8gd29ybGQg\nw6AgdG91cw==";
+
+        let oim = OIM::from_str(content).unwrap();
+
+        assert_eq!("Hello world à tous", &oim.content);
+        assert_eq!(MsgContentType::TextPlain, oim.content_type);
+        assert_eq!(Uuid::from_str("3A3BE82F-CC2D-4CC3-9D4D-7F0E9B2F3D4D").unwrap(), oim.run_id);
+        assert_eq!(2, oim.seq_number);
+        assert_eq!(EmailAddress::default(), oim.sender);
+    }
+
+}
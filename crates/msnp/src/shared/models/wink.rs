@@ -0,0 +1,168 @@
+use std::str::FromStr;
+
+use anyhow::anyhow;
+use xml::reader::{EventReader, XmlEvent};
+
+use crate::msnp::error::PayloadError;
+use crate::shared::models::cabinet::{Cabinet, CabinetFile};
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use lazy_static_include::lazy_static_include_bytes;
+
+    use super::{Wink, WinkContent};
+
+    lazy_static_include_bytes! {
+        WINK_CAB => "assets/wink/TFR375.cab"
+    }
+
+    #[test]
+    fn parse_wink_content() {
+        let raw = "<?xml version=\"1.0\" encoding=\"UTF-8\" ?>\r\n<package xmlns=\"http://messenger.msn.com/messengercontent/1.0\" version=\"1.0\" type=\"wink\" xmlns:wink=\"http://messenger.msn.com/winks/1.0\" wink:version=\"1.0\" wink:name=\"Back Off!\" partnerid=\"AG\">\r\n <item contentid=\"AGW3065243\" contenttype=\"P\" type=\"animation\" mimetype=\"application/x-shockwave-flash\" file=\"3065243f.swf\" wink:sizex=\"410\" wink:sizey=\"275\" />\r\n <item type=\"thumbnail\" mimetype=\"image/png\" file=\"3065243m.png\" />\r\n</package>";
+        let content = WinkContent::from_str(raw).unwrap();
+
+        assert_eq!("wink", &content.package_type);
+        assert_eq!(Some("Back Off!".to_string()), content.name);
+        assert_eq!(2, content.items.len());
+
+        let animation = content.get_animation().unwrap();
+        assert_eq!("application/x-shockwave-flash", &animation.mimetype);
+        assert_eq!("3065243f.swf", &animation.file);
+        assert_eq!(Some("AGW3065243".to_string()), animation.contentid);
+
+        let thumbnail = content.get_thumbnail().unwrap();
+        assert_eq!("image/png", &thumbnail.mimetype);
+        assert_eq!("3065243m.png", &thumbnail.file);
+    }
+
+    #[test]
+    fn parse_wink_cab() {
+        let wink = Wink::try_from(WINK_CAB.as_ref()).unwrap();
+
+        assert_eq!(Some("Back Off!".to_string()), wink.content.name);
+
+        let (thumbnail, thumbnail_file) = wink.get_thumbnail().unwrap();
+        assert_eq!("image/png", &thumbnail.mimetype);
+        assert_eq!(4327, thumbnail_file.data.len());
+
+        let (animation, animation_file) = wink.get_animation().unwrap();
+        assert_eq!("application/x-shockwave-flash", &animation.mimetype);
+        assert_eq!(23435, animation_file.data.len());
+    }
+
+    #[test]
+    fn parse_not_a_wink() {
+        assert!(WinkContent::from_str("<package version=\"1.0\" type=\"theme\"><item type=\"wink\" file=\"TFR375.cab\"/></package>").is_err());
+    }
+}
+
+pub const WINK_CONTENT_FILE: &str = "content.xml";
+
+#[derive(Clone, Debug)]
+pub struct WinkItem {
+    /* animation or thumbnail */
+    pub item_type: String,
+    pub mimetype: String,
+    pub file: String,
+    pub contentid: Option<String>
+}
+
+/* The content.xml at the root of a wink cabinet */
+#[derive(Clone, Debug)]
+pub struct WinkContent {
+    pub package_type: String,
+    pub name: Option<String>,
+    pub items: Vec<WinkItem>
+}
+
+impl WinkContent {
+    pub fn get_animation(&self) -> Option<&WinkItem> {
+        self.items.iter().find(|i| i.item_type == "animation")
+    }
+
+    pub fn get_thumbnail(&self) -> Option<&WinkItem> {
+        self.items.iter().find(|i| i.item_type == "thumbnail")
+    }
+}
+
+impl FromStr for WinkContent {
+    type Err = PayloadError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut package_type = None;
+        let mut name = None;
+        let mut items = Vec::new();
+
+        for event in EventReader::from_str(s) {
+            let event = event.map_err(|e| PayloadError::StringPayloadParsingError { payload: s.to_string(), source: anyhow!("Invalid wink content.xml: {}", e) })?;
+
+            if let XmlEvent::StartElement { name: element_name, attributes, .. } = event {
+                match element_name.local_name.as_str() {
+                    "package" => {
+                        for attribute in attributes {
+                            match (attribute.name.prefix.as_deref(), attribute.name.local_name.as_str()) {
+                                (None, "type") => package_type = Some(attribute.value),
+                                (Some("wink"), "name") => name = Some(attribute.value),
+                                _ => {}
+                            }
+                        }
+                    },
+                    "item" => {
+                        let mut item = WinkItem { item_type: String::new(), mimetype: String::new(), file: String::new(), contentid: None };
+                        for attribute in attributes.into_iter().filter(|a| a.name.prefix.is_none()) {
+                            match attribute.name.local_name.as_str() {
+                                "type" => item.item_type = attribute.value,
+                                "mimetype" => item.mimetype = attribute.value,
+                                "file" => item.file = attribute.value,
+                                "contentid" => item.contentid = Some(attribute.value),
+                                _ => {}
+                            }
+                        }
+                        items.push(item);
+                    },
+                    _ => {}
+                }
+            }
+        }
+
+        let package_type = package_type.ok_or(PayloadError::MandatoryPartNotFound { name: "package type".to_string(), payload: s.to_string() })?;
+        if package_type != "wink" {
+            return Err(PayloadError::StringPayloadParsingError { payload: s.to_string(), source: anyhow!("Package is not a wink: {}", package_type) });
+        }
+
+        Ok(WinkContent { package_type, name, items })
+    }
+}
+
+/* MsnObjectType::Wink, transferred over P2P as a cabinet holding the content.xml, the animation & its thumbnail */
+#[derive(Clone, Debug)]
+pub struct Wink {
+    pub content: WinkContent,
+    cabinet: Cabinet
+}
+
+impl Wink {
+    pub fn get_animation(&self) -> Option<(&WinkItem, &CabinetFile)> {
+        let animation = self.content.get_animation()?;
+        self.cabinet.get_file(&animation.file).map(|file| (animation, file))
+    }
+
+    pub fn get_thumbnail(&self) -> Option<(&WinkItem, &CabinetFile)> {
+        let thumbnail = self.content.get_thumbnail()?;
+        self.cabinet.get_file(&thumbnail.file).map(|file| (thumbnail, file))
+    }
+}
+
+impl TryFrom<&[u8]> for Wink {
+    type Error = PayloadError;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        let cabinet = Cabinet::try_from(bytes)?;
+        let content_file = cabinet.get_file(WINK_CONTENT_FILE).ok_or(PayloadError::MandatoryPartNotFound { name: WINK_CONTENT_FILE.to_string(), payload: "".to_string() })?;
+        let content = WinkContent::from_str(std::str::from_utf8(&content_file.data)?)?;
+
+        Ok(Wink { content, cabinet })
+    }
+}
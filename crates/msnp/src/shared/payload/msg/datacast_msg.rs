@@ -8,14 +8,57 @@ use crate::shared::payload::msg::raw_msg_payload::factories::RawMsgPayloadFactor
 use crate::shared::payload::msg::raw_msg_payload::{MsgContentType, RawMsgPayload};
 use crate::shared::traits::{MSGPayload, MSNPPayload};
 
+#[cfg(test)]
+mod tests {
+    use crate::shared::models::msn_object::MsnObjectType;
+    use crate::shared::payload::msg::raw_msg_payload::RawMsgPayload;
+    use crate::shared::traits::{MSGPayload, MSNPPayload};
+
+    use super::{DatacastMessageContent, DatacastType};
+
+    #[test]
+    fn wink_datacast_deser() {
+        let raw = "MIME-Version: 1.0\r\nContent-Type: text/x-msnmsgr-datacast\r\n\r\nID: 2\r\nData: <msnobj Creator=\"aeoncl@shlasouf.local\" Type=\"8\" SHA1D=\"ZrBqPeI0+PvpbXgDPnTr8Xiywz8=\" Size=\"28379\" Location=\"TFR375.cab\" Friendly=\"QgBhAGMAawAgAE8AZgBmACEAAAA=\" contenttype=\"P\" stamp=\"MIIIngYJKoZIhvcNAQcCoIIIjzCCCIsCAQExCzAJBgUrDgMCGgUA\"/>\r\n\r\n";
+        let raw_msg = RawMsgPayload::try_from_bytes(raw.as_bytes().to_vec()).unwrap();
+        let content = DatacastMessageContent::try_from_raw(raw_msg).unwrap();
+
+        assert!(content.get_type() == DatacastType::Wink);
+        let msn_object = content.get_msn_object().unwrap();
+        assert_eq!(MsnObjectType::Wink, msn_object.obj_type);
+        assert_eq!("TFR375.cab", &msn_object.location);
+        assert_eq!(Some("MIIIngYJKoZIhvcNAQcCoIIIjzCCCIsCAQExCzAJBgUrDgMCGgUA".to_string()), msn_object.stamp);
+    }
+
+    #[test]
+    fn nudge_datacast_deser() {
+        let raw = "MIME-Version: 1.0\r\nContent-Type: text/x-msnmsgr-datacast\r\n\r\nID: 1\r\n\r\n";
+        let raw_msg = RawMsgPayload::try_from_bytes(raw.as_bytes().to_vec()).unwrap();
+        let content = DatacastMessageContent::try_from_raw(raw_msg).unwrap();
+
+        assert!(content.get_type() == DatacastType::Nudge);
+        assert!(content.get_msn_object().is_none());
+    }
+}
+
 pub struct DatacastMessageContent {
     data: Datacast
 }
 
 impl DatacastMessageContent {
+    pub fn new(data: Datacast) -> Self {
+        Self { data }
+    }
+
     pub fn get_type(&self) -> DatacastType {
         self.data.get_type()
     }
+
+    pub fn get_msn_object(&self) -> Option<&MsnObject> {
+        match &self.data {
+            Datacast::Wink(obj) | Datacast::MsnObject(obj) => Some(obj),
+            _ => None
+        }
+    }
 }
 
 impl MSGPayload for DatacastMessageContent {
@@ -31,7 +74,7 @@ impl MSGPayload for DatacastMessageContent {
             });
         }
 
-        let raw_datacast_type = u8::from_str(&raw_msg_payload.headers.remove("ID").ok_or(PayloadError::MandatoryPartNotFound{ name: "ID".to_string(), payload: "".to_string() })?)?;
+        let raw_datacast_type = u8::from_str(&raw_msg_payload.headers.remove("ID").or_else(|| get_body_field(&raw_msg_payload, "ID")).ok_or(PayloadError::MandatoryPartNotFound{ name: "ID".to_string(), payload: "".to_string() })?)?;
         let datacast_type =  DatacastType::from_u8(raw_datacast_type).ok_or(anyhow!("Unknown datacast type: {}", raw_datacast_type))?;
 
         let content = match datacast_type {
@@ -40,6 +83,11 @@ impl MSGPayload for DatacastMessageContent {
                     data: Datacast::Nudge,
                 }
             }
+            DatacastType::Wink => {
+                DatacastMessageContent {
+                    data: Datacast::Wink(MsnObject::from_str(&get_body_field(&raw_msg_payload, "Data").ok_or(PayloadError::MandatoryPartNotFound{ name: "Data".to_string(), payload: "".to_string() })?)?),
+                }
+            }
             DatacastType::MsnObject => {
                 DatacastMessageContent {
                     data: Datacast::MsnObject(MsnObject::from_str(raw_msg_payload.get_body_as_str()?)?),
@@ -60,6 +108,9 @@ impl MSGPayload for DatacastMessageContent {
             Datacast::Nudge => {
                 RawMsgPayloadFactory::get_nudge().into_bytes()
             }
+            Datacast::Wink(obj) => {
+                RawMsgPayloadFactory::get_wink_datacast(&obj).into_bytes()
+            }
             Datacast::MsnObject(obj) => {
                 RawMsgPayloadFactory::get_msnobj_datacast(&obj).into_bytes()
             }
@@ -70,8 +121,18 @@ impl MSGPayload for DatacastMessageContent {
     }
 }
 
+/* The datacast fields are in the body: ID: 2\r\nData: <msnobj/>\r\n */
+fn get_body_field(raw_msg_payload: &RawMsgPayload, name: &str) -> Option<String> {
+    let body = raw_msg_payload.get_body_as_str().ok()?;
+    body.lines().find_map(|line| {
+        let (field_name, value) = line.split_once(':')?;
+        if field_name.trim() == name { Some(value.trim().to_string()) } else { None }
+    })
+}
+
 pub enum Datacast {
     Nudge,
+    Wink(MsnObject),
     MsnObject(MsnObject),
     ActionMsg(String)
 }
@@ -79,6 +140,7 @@ pub enum Datacast {
 #[derive(FromPrimitive, PartialEq, Eq)]
 pub enum DatacastType {
     Nudge = 1,
+    Wink = 2,
     MsnObject = 3,
     ActionMsg = 4,
 }
@@ -90,6 +152,9 @@ impl Datacast {
             Datacast::Nudge => {
                 DatacastType::Nudge
             }
+            Datacast::Wink(_) => {
+                DatacastType::Wink
+            }
             Datacast::MsnObject(_) => {
                 DatacastType::MsnObject
             }
@@ -0,0 +1,151 @@
+use std::str::FromStr;
+
+use anyhow::anyhow;
+
+use crate::msnp::error::PayloadError;
+use crate::shared::models::msn_object::MsnObject;
+use crate::shared::payload::msg::raw_msg_payload::factories::RawMsgPayloadFactory;
+use crate::shared::payload::msg::raw_msg_payload::{MsgContentType, RawMsgPayload};
+use crate::shared::traits::{MSGPayload, MSNPPayload};
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use crate::shared::models::msn_object::MsnObjectType;
+    use crate::shared::payload::msg::raw_msg_payload::{MsgContentType, RawMsgPayload};
+    use crate::shared::traits::{MSGPayload, MSNPPayload};
+
+    use super::EmoticonMessageContent;
+
+    #[test]
+    fn emoticon_msg_deser() {
+        let raw = "MIME-Version: 1.0\r\nContent-Type: text/x-mms-emoticon\r\n\r\n(dog)\t<msnobj Creator=\"aeoncl@shlasouf.local\" Size=\"1337\" Type=\"2\" Location=\"TFR2C.tmp\" Friendly=\"AAA=\" SHA1D=\"trC8SlFx2sWQxZMIBAWSEnXc8oQ=\" SHA1C=\"U6lcjTw+y5cnzUdFDGpP6tOT6JA=\"/>\t:lol:\t<msnobj Creator=\"aeoncl@shlasouf.local\" Size=\"42\" Type=\"2\" Location=\"TFR2D.tmp\" Friendly=\"AAA=\" SHA1D=\"6f1y8TyOY/ZrKe9I3uPWwXtS+AE=\" SHA1C=\"1UvV95Wzc5qvVdoc8GWMyMzTHOE=\"/>\t";
+        let raw_msg = RawMsgPayload::try_from_bytes(raw.as_bytes().to_vec()).unwrap();
+        let content = EmoticonMessageContent::try_from_raw(raw_msg).unwrap();
+
+        assert!(!content.animated);
+        assert_eq!(2, content.emoticons.len());
+
+        assert_eq!("(dog)", &content.emoticons[0].shortcut);
+        assert_eq!(MsnObjectType::CustomEmoticon, content.emoticons[0].msn_object.obj_type);
+        assert_eq!("trC8SlFx2sWQxZMIBAWSEnXc8oQ=", &content.emoticons[0].msn_object.sha1d);
+        assert_eq!(1337, content.emoticons[0].msn_object.size);
+
+        assert_eq!(":lol:", &content.emoticons[1].shortcut);
+        assert_eq!("TFR2D.tmp", &content.emoticons[1].msn_object.location);
+    }
+
+    #[test]
+    fn anim_emoticon_msg_deser() {
+        let raw = "MIME-Version: 1.0\r\nContent-Type: text/x-mms-animemoticon\r\n\r\n(cat)\t<msnobj Creator=\"aeoncl@shlasouf.local\" Size=\"1337\" Type=\"2\" Location=\"TFR2C.tmp\" Friendly=\"AAA=\" SHA1D=\"trC8SlFx2sWQxZMIBAWSEnXc8oQ=\" SHA1C=\"U6lcjTw+y5cnzUdFDGpP6tOT6JA=\"/>\t";
+        let raw_msg = RawMsgPayload::try_from_bytes(raw.as_bytes().to_vec()).unwrap();
+        let content = EmoticonMessageContent::try_from_raw(raw_msg).unwrap();
+
+        assert!(content.animated);
+        assert_eq!(1, content.emoticons.len());
+        assert_eq!("(cat)", &content.emoticons[0].shortcut);
+    }
+
+    #[test]
+    fn emoticon_msg_ser() {
+        let body = "(dog)\t<msnobj Creator=\"aeoncl@shlasouf.local\" Size=\"1337\" Type=\"2\" Location=\"TFR2C.tmp\" Friendly=\"AAA=\" SHA1D=\"trC8SlFx2sWQxZMIBAWSEnXc8oQ=\" SHA1C=\"U6lcjTw+y5cnzUdFDGpP6tOT6JA=\"/>\t";
+        let content = EmoticonMessageContent::from_str(body).unwrap();
+
+        let raw = RawMsgPayload::try_from_bytes(content.into_bytes()).unwrap();
+        assert_eq!(MsgContentType::Emoticon, raw.get_content_type().unwrap());
+
+        let content = EmoticonMessageContent::try_from_raw(raw).unwrap();
+        assert_eq!(1, content.emoticons.len());
+        assert_eq!("(dog)", &content.emoticons[0].shortcut);
+        assert_eq!("trC8SlFx2sWQxZMIBAWSEnXc8oQ=", &content.emoticons[0].msn_object.sha1d);
+    }
+
+    #[test]
+    fn emoticon_msg_deser_malformed() {
+        assert!(EmoticonMessageContent::from_str("(dog)\t").is_err());
+        assert!(EmoticonMessageContent::from_str("(dog)\tnot an msnobj\t").is_err());
+    }
+}
+
+/* Sent before a text message containing custom emoticons, the body is: shortcut\t<msnobj />\t repeated for each emoticon */
+pub struct EmoticonMessageContent {
+    pub animated: bool,
+    pub emoticons: Vec<EmoticonDeclaration>
+}
+
+#[derive(Clone, Debug)]
+pub struct EmoticonDeclaration {
+    pub shortcut: String,
+    pub msn_object: MsnObject
+}
+
+impl EmoticonMessageContent {
+    pub fn new(emoticons: Vec<EmoticonDeclaration>) -> Self {
+        Self {
+            animated: false,
+            emoticons,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.emoticons.is_empty()
+    }
+
+    pub fn serialize_body(&self) -> String {
+        let mut out = String::new();
+        for emoticon in &self.emoticons {
+            out.push_str(&format!("{}\t{}\t", emoticon.shortcut, emoticon.msn_object.to_string_not_encoded()));
+        }
+        out
+    }
+}
+
+impl FromStr for EmoticonMessageContent {
+    type Err = PayloadError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = s.split('\t').filter(|part| !part.trim().is_empty()).collect();
+
+        if parts.len() % 2 != 0 {
+            return Err(PayloadError::StringPayloadParsingError { payload: s.to_string(), source: anyhow!("Emoticon declaration is missing an MSNObject") });
+        }
+
+        let mut emoticons = Vec::with_capacity(parts.len() / 2);
+        for declaration in parts.chunks(2) {
+            emoticons.push(EmoticonDeclaration {
+                shortcut: declaration[0].to_string(),
+                msn_object: MsnObject::from_str(declaration[1].trim())?,
+            });
+        }
+
+        Ok(Self::new(emoticons))
+    }
+}
+
+impl MSGPayload for EmoticonMessageContent {
+    type Err = PayloadError;
+
+    fn try_from_raw(raw_msg_payload: RawMsgPayload) -> Result<Self, Self::Err> where Self: Sized {
+        let animated = match raw_msg_payload.get_content_type()? {
+            MsgContentType::Emoticon => false,
+            MsgContentType::AnimEmoticon => true,
+            _ => {
+                return Err(PayloadError::PayloadPropertyParseError {
+                    property_name: "Content-Type".to_string(),
+                    raw_value: format!("{:?}", raw_msg_payload),
+                    payload_type: "MSG".to_string(),
+                    source: anyhow!("Content Type doesnt match expectation for this type of message"),
+                });
+            }
+        };
+
+        let mut out = Self::from_str(raw_msg_payload.get_body_as_str()?)?;
+        out.animated = animated;
+        Ok(out)
+    }
+
+    fn into_bytes(self) -> Vec<u8> {
+        RawMsgPayloadFactory::get_emoticon_declaration(&self).into_bytes()
+    }
+}
@@ -2,3 +2,4 @@ pub mod raw_msg_payload;
 pub mod text_msg;
 pub mod typing_user_msg;
 pub mod datacast_msg;
+pub mod emoticon_msg;
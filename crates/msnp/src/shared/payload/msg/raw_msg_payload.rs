@@ -38,6 +38,12 @@ pub enum MsgContentType {
     #[strum(serialize = "application/x-msnmsgrp2p", ascii_case_insensitive)]
     P2P,
 
+    #[strum(serialize = "text/x-mms-emoticon", ascii_case_insensitive)]
+    Emoticon,
+
+    #[strum(serialize = "text/x-mms-animemoticon", ascii_case_insensitive)]
+    AnimEmoticon,
+
     None
 }
 
@@ -194,6 +200,7 @@ pub mod factories {
     use crate::shared::models::email_address::EmailAddress;
     use crate::shared::models::oim::MetaData;
     use crate::shared::models::ticket_token::TicketToken;
+    use crate::shared::payload::msg::emoticon_msg::EmoticonMessageContent;
     use crate::soap::traits::xml::ToXml;
 
     use super::{MsgContentType, RawMsgPayload};
@@ -285,12 +292,25 @@ pub mod factories {
             return out;
         }
 
+        pub fn get_wink_datacast(msn_object: &MsnObject) -> RawMsgPayload {
+            let mut out = RawMsgPayload::new(MsgContentType::Datacast, true);
+            out.body = format!("ID: 2\r\nData: {}\r\n", msn_object.to_string_not_encoded()).into_bytes();
+            out
+        }
+
         pub fn get_msnobj_datacast(msn_object: &MsnObject) -> RawMsgPayload {
             let mut out = RawMsgPayload::new(MsgContentType::Datacast, true);
             out.body = format!("ID: 3\r\nData: {}\r\n", msn_object.to_string_not_encoded()).into_bytes();
             out
         }
 
+        pub fn get_emoticon_declaration(content: &EmoticonMessageContent) -> RawMsgPayload {
+            let content_type = if content.animated { MsgContentType::AnimEmoticon } else { MsgContentType::Emoticon };
+            let mut out = RawMsgPayload::new(content_type, false);
+            out.set_body_string(content.serialize_body());
+            out
+        }
+
         pub fn get_p2p(source: &MsnUser, destination: &MsnUser, payload: &P2PTransportPacket) -> RawMsgPayload {
             let mut out = RawMsgPayload::new(MsgContentType::P2P, false);
             out.add_header("P2P-Dest", &destination.endpoint_id.to_string());
@@ -16,7 +16,7 @@ use crate::shared::traits::{MSGPayload, MSNPPayload};
 
 #[cfg(test)]
 mod tests {
-    use super::{FontColor, OvercomplicatedFontColor};
+    use super::{FontColor, OvercomplicatedFontColor, TextMessageContent};
     #[test]
     pub fn complicated_font_color_tests_rgb() {
         let some_purple = OvercomplicatedFontColor::parse_from_rgb("762EE1").expect("to be kinda purple");
@@ -136,6 +136,15 @@ mod tests {
         assert_eq!("e12e76", &some_purple_str);
     }
 
+    #[test]
+    pub fn me_action_tests() {
+        assert_eq!(Some("waves"), TextMessageContent::new_with_default_style("/me waves").get_me_action());
+        assert_eq!(Some("waves"), TextMessageContent::new_with_default_style("/ME   waves").get_me_action());
+        assert_eq!(None, TextMessageContent::new_with_default_style("/me").get_me_action());
+        assert_eq!(None, TextMessageContent::new_with_default_style("/meow").get_me_action());
+        assert_eq!(None, TextMessageContent::new_with_default_style("hi /me waves").get_me_action());
+    }
+
 }
 
 pub struct TextMessageContent {
@@ -223,6 +232,17 @@ impl TextMessageContent {
         }
     }
 
+    /* WLM has no emote messages, "/me waves" is sent as is */
+    pub fn get_me_action(&self) -> Option<&str> {
+        let command = self.body.get(..4)?;
+        if !command.eq_ignore_ascii_case("/me ") {
+            return None;
+        }
+
+        let action = self.body[4..].trim_start();
+        if action.is_empty() { None } else { Some(action) }
+    }
+
     pub fn is_styling_default(&self) -> bool {
         self.is_default_font_color() && self.is_default_font_styles() && self.is_default_font()
     }
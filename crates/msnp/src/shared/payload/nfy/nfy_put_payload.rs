@@ -10,16 +10,25 @@ use strum_macros::{Display, EnumString};
 
 use crate::msnp::error::PayloadError;
 use crate::msnp::switchboard::command::msg::MsgPayload;
+use crate::msnp::notification::models::endpoint_guid::EndpointGuid;
+use crate::p2p::v2::p2p_transport_packet::P2PTransportPacket;
+use crate::shared::models::endpoint_id::EndpointId;
+use crate::shared::models::msn_object::MsnObject;
+use crate::shared::models::msn_user::MsnUser;
 use crate::shared::models::network_id_email::NetworkIdEmail;
 use crate::shared::payload::msg::raw_msg_payload::{MsgContentType, RawMsgPayload};
+use crate::shared::payload::msg::raw_msg_payload::factories::RawMsgPayloadFactory;
+use crate::shared::payload::msg::emoticon_msg::EmoticonMessageContent;
 use crate::shared::payload::msg::text_msg::TextMessageContent;
-use crate::shared::traits::MSNPPayload;
+use crate::shared::traits::{MSGPayload, MSNPPayload};
 
 #[cfg(test)]
 mod tests {
-    use std::str::FromStr;
+    use std::str::{from_utf8, FromStr};
 
+    use crate::shared::models::msn_object::MsnObject;
     use crate::shared::models::network_id_email::NetworkIdEmail;
+    use crate::shared::payload::msg::emoticon_msg::EmoticonMessageContent;
     use super::{NfyContentType, NfyEnvelope, RawNfyPayload};
     use crate::shared::traits::MSNPPayload;
 
@@ -33,6 +42,10 @@ mod tests {
             stream: 0,
             segment: None,
             flags: None,
+            from_epid: None,
+            to_epid: None,
+            to_path: None,
+            from_path: None,
         };
 
         let ser = put_env.to_string();
@@ -50,6 +63,10 @@ mod tests {
             stream: 0,
             segment: Some(2),
             flags: None,
+            from_epid: None,
+            to_epid: None,
+            to_path: None,
+            from_path: None,
         };
 
         let ser = put_env.to_string();
@@ -103,6 +120,10 @@ mod tests {
             stream: 0,
             segment: None,
             flags: None,
+            from_epid: None,
+            to_epid: None,
+            to_path: None,
+            from_path: None,
         };
 
 
@@ -120,6 +141,128 @@ mod tests {
         assert_eq!(expectation, &ser);
     }
 
+    #[test]
+    fn test_deser_sdg_p2p_payload() {
+        let mut raw = b"Routing: 1.0\r\nTo: 1:bob@lukewarmmail.com;epid={4c2ad1b8-5f1e-4ad7-9d4b-7a8b9c0d1e2f}\r\nFrom: 1:aeon@lukewarmmail.com;epid={f52973b6-c926-4bad-9ba8-7c1e840e4ab0}\r\n\r\nReliability: 1.0\r\n\r\nMessaging: 2.0\r\nMessage-Type: Data\r\nContent-Transfer-Encoding: binary\r\nBridging-Offsets: 0\r\nContent-Type: application/x-msnmsgrp2p\r\nContent-Length: 4\r\n\r\n".to_vec();
+        raw.extend_from_slice(&[0x08, 0x00, 0x00, 0xff]);
+
+        let deser = RawNfyPayload::try_from_bytes(raw).unwrap();
+
+        assert!(matches!(deser.content_type, NfyContentType::P2P));
+        assert_eq!(0, deser.envelope.stream);
+        assert_eq!("1:bob@lukewarmmail.com", &deser.envelope.to.to_string());
+        assert_eq!("bob@lukewarmmail.com;{4C2AD1B8-5F1E-4AD7-9D4B-7A8B9C0D1E2F}", &deser.envelope.get_to_endpoint_id().to_string());
+        assert_eq!("aeon@lukewarmmail.com;{F52973B6-C926-4BAD-9BA8-7C1E840E4AB0}", &deser.envelope.get_from_endpoint_id().to_string());
+        assert_eq!(vec![0x08, 0x00, 0x00, 0xff], deser.body);
+    }
+
+    #[test]
+    fn test_put_envelope_swap_sides_with_epid() {
+        let raw = "Routing: 1.0\r\nTo: 1:bob@lukewarmmail.com\r\nFrom: 1:aeon@lukewarmmail.com;epid={f52973b6-c926-4bad-9ba8-7c1e840e4ab0}\r\n\r\nReliability: 1.0\r\nStream: 0\r\n\r\n";
+        let mut deser = NfyEnvelope::from_str(raw).unwrap();
+        deser.swap_sides();
+
+        assert_eq!("Routing: 1.0\r\nTo: 1:aeon@lukewarmmail.com;epid={F52973B6-C926-4BAD-9BA8-7C1E840E4AB0}\r\nFrom: 1:bob@lukewarmmail.com\r\n\r\nReliability: 1.0\r\nStream: 0\r\n\r\n", &deser.to_string());
+    }
+
+    #[test]
+    fn test_ser_emoticon_payload() {
+        let emoticons = EmoticonMessageContent::from_str("(dog)\t<msnobj Creator=\"bob@lukewarmmail.com\" Size=\"1337\" Type=\"2\" Location=\"TFR2C.tmp\" Friendly=\"AAA=\" SHA1D=\"trC8SlFx2sWQxZMIBAWSEnXc8oQ=\" SHA1C=\"U6lcjTw+y5cnzUdFDGpP6tOT6JA=\"/>\t").unwrap();
+
+        let payload = RawNfyPayload::new_emoticon_message(NetworkIdEmail::from_str("1:bob@lukewarmmail.com").unwrap(), NetworkIdEmail::from_str("1:aeon@lukewarmmail.com").unwrap(), &emoticons);
+        let deser = RawNfyPayload::try_from_bytes(payload.into_bytes()).unwrap();
+
+        assert!(matches!(deser.content_type, NfyContentType::Emoticon));
+        assert_eq!("CustomEmoticon", deser.get_header("Message-Type").unwrap());
+
+        let emoticons = EmoticonMessageContent::from_str(from_utf8(&deser.body).unwrap()).unwrap();
+        assert_eq!("(dog)", &emoticons.emoticons[0].shortcut);
+    }
+
+    #[test]
+    fn test_ser_wink_payload() {
+        let wink = MsnObject::from_str("<msnobj Creator=\"bob@lukewarmmail.com\" Type=\"8\" SHA1D=\"ZrBqPeI0+PvpbXgDPnTr8Xiywz8=\" Size=\"28379\" Location=\"TFR375.cab\" Friendly=\"AAA=\" contenttype=\"P\"/>").unwrap();
+
+        let payload = RawNfyPayload::new_wink_message(NetworkIdEmail::from_str("1:bob@lukewarmmail.com").unwrap(), NetworkIdEmail::from_str("1:aeon@lukewarmmail.com").unwrap(), &wink);
+        let deser = RawNfyPayload::try_from_bytes(payload.into_bytes()).unwrap();
+
+        assert!(matches!(deser.content_type, NfyContentType::Datacast));
+        assert_eq!("Wink", deser.get_header("Message-Type").unwrap());
+        assert!(from_utf8(&deser.body).unwrap().starts_with("ID: 2\r\nData: <msnobj"));
+    }
+
+    #[test]
+    fn test_ser_circle_roster_payload() {
+        let circle = NetworkIdEmail::from_str("9:00000000-0000-0000-0009-000000000001@hotmail.com").unwrap();
+        let me = NetworkIdEmail::from_str("1:aeon@lukewarmmail.com").unwrap();
+        let roster = vec![me.clone(), NetworkIdEmail::from_str("1:bob@lukewarmmail.com").unwrap()];
+
+        let payload = RawNfyPayload::new_circle_roster(circle, me, &roster);
+        let serialized = String::from_utf8(payload.into_bytes()).unwrap();
+
+        let expected_body = "<circle><roster><id>IM</id><user><id>1:aeon@lukewarmmail.com</id></user><user><id>1:bob@lukewarmmail.com</id></user></roster></circle>";
+        assert_eq!(format!("Routing: 1.0\r\nTo: 1:aeon@lukewarmmail.com\r\nFrom: 9:00000000-0000-0000-0009-000000000001@hotmail.com\r\n\r\nReliability: 1.0\r\nStream: 0\r\n\r\nNotification: 1.0\r\nNotifNum: 0\r\nUri: /circle\r\nNotifType: Full\r\nContent-Type: application/circles+xml\r\nContent-Length: {}\r\n\r\n{}", expected_body.len(), expected_body), serialized);
+    }
+
+    #[test]
+    fn test_ser_circle_roster_partial_payloads() {
+        let circle = NetworkIdEmail::from_str("9:00000000-0000-0000-0009-000000000001@hotmail.com").unwrap();
+        let me = NetworkIdEmail::from_str("1:aeon@lukewarmmail.com").unwrap();
+        let bob = NetworkIdEmail::from_str("1:bob@lukewarmmail.com").unwrap();
+
+        let joined = RawNfyPayload::new_circle_roster_joined(circle.clone(), me.clone(), &[bob.clone()]);
+        assert_eq!("Partial", joined.get_header("NotifType").unwrap());
+        assert_eq!("/circle", joined.get_header("Uri").unwrap());
+        assert_eq!("<circle><roster><id>IM</id><user><id>1:bob@lukewarmmail.com</id></user></roster></circle>", joined.get_body_as_str().unwrap());
+
+        let left = RawNfyPayload::new_circle_roster_left(circle, me, &bob);
+        let serialized = String::from_utf8(left.into_bytes()).unwrap();
+        assert!(serialized.contains("Uri: /circle/roster(IM)/user(1:bob@lukewarmmail.com)\r\nNotifType: Partial\r\n"));
+        assert!(serialized.ends_with("Content-Length: 0\r\n\r\n"));
+    }
+
+    #[test]
+    fn test_deser_circle_text_message() {
+        let raw = b"Routing: 1.0\r\nTo: 9:00000000-0000-0000-0009-000000000001@hotmail.com;path=IM\r\nFrom: 1:aeon@lukewarmmail.com;epid={f52973b6-c926-4bad-9ba8-7c1e840e4ab0}\r\n\r\nReliability: 1.0\r\n\r\nMessaging: 1.0\r\nMessage-Type: Text\r\nContent-Type: text/plain; charset=UTF-8\r\nContent-Length: 5\r\nX-MMS-IM-Format: FN=Segoe%20UI; EF=B; CO=0; CS=1; PF=0\r\n\r\nhello";
+        let deser = RawNfyPayload::try_from_bytes(raw.to_vec()).unwrap();
+
+        assert_eq!(Some("IM"), deser.envelope.to_path.as_deref());
+
+        let text = deser.get_text_message().unwrap();
+        assert_eq!("hello", &text.body);
+        assert_eq!("Segoe UI", &text.font_family);
+
+        let mut reply = RawNfyPayload::new_text_message(NetworkIdEmail::from_str("1:bob@lukewarmmail.com").unwrap(), deser.envelope.to.clone(), text);
+        reply.envelope.to_path = Some("IM".to_string());
+        assert!(String::from_utf8(reply.into_bytes()).unwrap().starts_with("Routing: 1.0\r\nTo: 9:00000000-0000-0000-0009-000000000001@hotmail.com;path=IM\r\nFrom: 1:bob@lukewarmmail.com\r\n"));
+    }
+
+    #[test]
+    fn test_ser_action_payload() {
+        let payload = RawNfyPayload::new_action_message(NetworkIdEmail::from_str("1:bob@lukewarmmail.com").unwrap(), NetworkIdEmail::from_str("1:aeon@lukewarmmail.com").unwrap(), "reacted 👍 to \"hello\"".to_string());
+        let deser = RawNfyPayload::try_from_bytes(payload.into_bytes()).unwrap();
+
+        assert!(matches!(deser.content_type, NfyContentType::Datacast));
+        assert_eq!("ID: 4\r\nData: reacted 👍 to \"hello\"\r\n", from_utf8(&deser.body).unwrap());
+    }
+
+    #[test]
+    fn test_circle_notice_from_path() {
+        let circle = NetworkIdEmail::from_str("9:00000000-0000-0000-0009-000000000001@hotmail.com").unwrap();
+        let mut notice = RawNfyPayload::new_action_message(circle, NetworkIdEmail::from_str("1:aeon@lukewarmmail.com").unwrap(), "hello".to_string());
+        notice.envelope.from_path = Some("IM".to_string());
+
+        let serialized = String::from_utf8(notice.into_bytes()).unwrap();
+        assert!(serialized.starts_with("Routing: 1.0\r\nTo: 1:aeon@lukewarmmail.com\r\nFrom: 9:00000000-0000-0000-0009-000000000001@hotmail.com;path=IM\r\n"));
+
+        let mut deser = RawNfyPayload::try_from_bytes(serialized.into_bytes()).unwrap();
+        assert_eq!(Some("IM"), deser.envelope.from_path.as_deref());
+
+        deser.envelope.swap_sides();
+        assert_eq!(Some("IM"), deser.envelope.to_path.as_deref());
+        assert_eq!(None, deser.envelope.from_path);
+    }
+
 }
 
 
@@ -131,6 +274,14 @@ pub enum NfyContentType {
     PlainText,
     #[strum(serialize = "text/x-msmsgscontrol", ascii_case_insensitive)]
     Control,
+    #[strum(serialize = "application/x-msnmsgrp2p", ascii_case_insensitive)]
+    P2P,
+    #[strum(serialize = "text/x-msnmsgr-datacast", ascii_case_insensitive)]
+    Datacast,
+    #[strum(serialize = "text/x-mms-emoticon", ascii_case_insensitive)]
+    Emoticon,
+    #[strum(serialize = "text/x-mms-animemoticon", ascii_case_insensitive)]
+    AnimEmoticon,
     None,
 }
 
@@ -144,16 +295,61 @@ pub struct NfyEnvelope {
     pub stream: u32,
     pub segment: Option<u32>,
     pub flags: Option<String>,
+
+    pub from_epid: Option<EndpointGuid>,
+    pub to_epid: Option<EndpointGuid>,
+    /* Circle conversations are addressed as 9:circle@domain;path=IM */
+    pub to_path: Option<String>,
+    pub from_path: Option<String>,
 }
 
 impl NfyEnvelope {
     pub fn swap_sides(&mut self){
         mem::swap(&mut self.from,&mut self.to);
+        mem::swap(&mut self.from_epid,&mut self.to_epid);
+        mem::swap(&mut self.from_path,&mut self.to_path);
     }
+
+    pub fn get_from_endpoint_id(&self) -> EndpointId {
+        EndpointId::new(self.from.email.clone(), self.from_epid.clone())
+    }
+
+    pub fn get_to_endpoint_id(&self) -> EndpointId {
+        EndpointId::new(self.to.email.clone(), self.to_epid.clone())
+    }
+
+    fn parse_address(value: &str) -> Result<(NetworkIdEmail, Option<EndpointGuid>, Option<String>), PayloadError> {
+        let mut split = value.split(';');
+        let address = split.next().ok_or(anyhow!("Empty address in PUT header"))?;
+        let network_id_email = NetworkIdEmail::from_str(address.trim()).map_err(|e| anyhow!(e))?;
+
+        let mut epid = None;
+        let mut path = None;
+        for param in split {
+            match param.trim().split_once('=') {
+                Some(("epid", guid)) => {
+                    epid = Some(EndpointGuid::from_str(guid).map_err(|e| anyhow!(e))?);
+                },
+                Some(("path", value)) => {
+                    path = Some(value.to_string());
+                },
+                _ => {
+                    warn!("Unknown address parameter in PUT header: {}", param);
+                }
+            }
+        }
+
+        Ok((network_id_email, epid, path))
+    }
+
     pub fn from_parts(routing_info: Vec<&str>, reliability_info: Vec<&str>) -> Result<Self, PayloadError> {
         let mut routing = None;
         let mut to = None;
         let mut from = None;
+        let mut to_epid = None;
+        let mut from_epid = None;
+        let mut to_path = None;
+        let mut from_path = None;
 
         for current in routing_info {
             let (key, value) = current.split_once(":").ok_or(anyhow!("Malformed PUT header"))?;
@@ -162,10 +358,16 @@ impl NfyEnvelope {
                     routing = Some(value.trim().to_string());
                 }
                 "to" => {
-                    to = Some(NetworkIdEmail::from_str(value.trim()).unwrap());
+                    let (address, epid, path) = Self::parse_address(value)?;
+                    to = Some(address);
+                    to_epid = epid;
+                    to_path = path;
                 }
                 "from" => {
-                    from = Some(NetworkIdEmail::from_str(value.trim()).unwrap());
+                    let (address, epid, path) = Self::parse_address(value)?;
+                    from = Some(address);
+                    from_epid = epid;
+                    from_path = path;
                 }
                 _ => {
                     warn!("Unknown routing info PUT header: {} {}", key, value)
@@ -204,9 +406,14 @@ impl NfyEnvelope {
             from: from.ok_or(anyhow!("Missing from field from PUT"))?,
             to: to.ok_or(anyhow!("Missing to field from PUT"))?,
             reliability: reliability.ok_or(anyhow!("Missing reliability field from PUT"))?,
-            stream: stream.ok_or(anyhow!("Missing stream field from PUT"))?,
+            /* SDG carried messages don't always come with a stream */
+            stream: stream.unwrap_or(0),
             segment,
             flags,
+            from_epid,
+            to_epid,
+            to_path,
+            from_path,
         }
         )
     }
@@ -231,23 +438,39 @@ impl FromStr for NfyEnvelope {
 
 impl Display for NfyEnvelope {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "Routing: {}\r\nTo: {}\r\nFrom: {}\r\n\r\nReliability: {}\r\nStream: {}\r\n", self.routing, self.to, self.from, self.reliability, self.stream);
+        write!(f, "Routing: {}\r\nTo: {}", self.routing, self.to)?;
+        if let Some(to_epid) = self.to_epid.as_ref() {
+            write!(f, ";epid={}", to_epid)?;
+        }
+        if let Some(to_path) = self.to_path.as_ref() {
+            write!(f, ";path={}", to_path)?;
+        }
+
+        write!(f, "\r\nFrom: {}", self.from)?;
+        if let Some(from_epid) = self.from_epid.as_ref() {
+            write!(f, ";epid={}", from_epid)?;
+        }
+        if let Some(from_path) = self.from_path.as_ref() {
+            write!(f, ";path={}", from_path)?;
+        }
+
+        write!(f, "\r\n\r\nReliability: {}\r\nStream: {}\r\n", self.reliability, self.stream)?;
 
         match self.segment {
             None => {}
             Some(segment) => {
-                write!(f, "Segment: {}\r\n", segment);
+                write!(f, "Segment: {}\r\n", segment)?;
             }
         };
 
         match self.flags.as_ref() {
             None => {}
             Some(flags) => {
-                write!(f, "Flags: {}\r\n", flags);
+                write!(f, "Flags: {}\r\n", flags)?;
             }
         };
 
-        write!(f, "\r\n");
+        write!(f, "\r\n")?;
 
         Ok(())
     }
@@ -285,6 +508,10 @@ impl RawNfyPayload {
             stream: 0,
             segment: None,
             flags: None,
+            from_epid: None,
+            to_epid: None,
+            to_path: None,
+            from_path: None,
         };
 
         let mut out = Self::new(envelope, NfyContentType::Circle, false);
@@ -305,6 +532,10 @@ impl RawNfyPayload {
             stream: 0,
             segment: None,
             flags: None,
+            from_epid: None,
+            to_epid: None,
+            to_path: None,
+            from_path: None,
         };
 
         let mut out = Self::new(envelope, NfyContentType::Circle, false);
@@ -317,6 +548,33 @@ impl RawNfyPayload {
 
     }
 
+    /* Everyone currently in the circle conversation, answers the client joining it */
+    pub fn new_circle_roster(circle: NetworkIdEmail, me: NetworkIdEmail, roster: &[NetworkIdEmail]) -> Self {
+        let mut out = Self::new_circle(circle, me);
+        out.set_body_string(Self::get_circle_roster_body(roster));
+        out
+    }
+
+    pub fn new_circle_roster_joined(circle: NetworkIdEmail, me: NetworkIdEmail, joined: &[NetworkIdEmail]) -> Self {
+        let mut out = Self::new_circle_partial(circle, me);
+        out.set_body_string(Self::get_circle_roster_body(joined));
+        out
+    }
+
+    /* Sent with NFY DEL, the user to remove is in the Uri */
+    pub fn new_circle_roster_left(circle: NetworkIdEmail, me: NetworkIdEmail, left: &NetworkIdEmail) -> Self {
+        let mut out = Self::new_circle_partial(circle, me);
+        if let Some(uri) = out.headers.get_mut("Uri") {
+            *uri = format!("/circle/roster(IM)/user({})", left);
+        }
+        out
+    }
+
+    fn get_circle_roster_body(users: &[NetworkIdEmail]) -> String {
+        let users: String = users.iter().map(|user| format!("<user><id>{}</id></user>", user)).collect();
+        format!("<circle><roster><id>IM</id>{}</roster></circle>", users)
+    }
+
     pub fn new_text_message(from: NetworkIdEmail, to: NetworkIdEmail, payload: TextMessageContent) -> Self {
         let envelope = NfyEnvelope{
             routing: "1.0".to_string(),
@@ -326,6 +584,10 @@ impl RawNfyPayload {
             stream: 0,
             segment: None,
             flags: None,
+            from_epid: None,
+            to_epid: None,
+            to_path: None,
+            from_path: None,
         };
 
         let mut out = Self::new(envelope, NfyContentType::PlainText, false);
@@ -337,6 +599,104 @@ impl RawNfyPayload {
         out
     }
 
+    /* Has to be sent right before the text message using the emoticons */
+    pub fn new_emoticon_message(from: NetworkIdEmail, to: NetworkIdEmail, payload: &EmoticonMessageContent) -> Self {
+        let envelope = NfyEnvelope{
+            routing: "1.0".to_string(),
+            from,
+            to,
+            reliability: "1.0".to_string(),
+            stream: 0,
+            segment: None,
+            flags: None,
+            from_epid: None,
+            to_epid: None,
+            to_path: None,
+            from_path: None,
+        };
+
+        let content_type = if payload.animated { NfyContentType::AnimEmoticon } else { NfyContentType::Emoticon };
+
+        let mut out = Self::new(envelope, content_type, false);
+        out.add_header("Messaging", "1.0");
+        out.add_header("Message-Type", "CustomEmoticon");
+        out.set_body_string(payload.serialize_body());
+
+        out
+    }
+
+    /* The client requests the wink cabinet over P2P once it received this */
+    pub fn new_wink_message(from: NetworkIdEmail, to: NetworkIdEmail, msn_object: &MsnObject) -> Self {
+        let envelope = NfyEnvelope{
+            routing: "1.0".to_string(),
+            from,
+            to,
+            reliability: "1.0".to_string(),
+            stream: 0,
+            segment: None,
+            flags: None,
+            from_epid: None,
+            to_epid: None,
+            to_path: None,
+            from_path: None,
+        };
+
+        let mut out = Self::new(envelope, NfyContentType::Datacast, false);
+        out.add_header("Messaging", "1.0");
+        out.add_header("Message-Type", "Wink");
+        out.set_body_string(format!("ID: 2\r\nData: {}\r\n", msn_object.to_string_not_encoded()));
+
+        out
+    }
+
+    /* Action messages are displayed in grey without the "says:" line, like /me in IRC */
+    pub fn new_action_message(from: NetworkIdEmail, to: NetworkIdEmail, text: String) -> Self {
+        let envelope = NfyEnvelope{
+            routing: "1.0".to_string(),
+            from,
+            to,
+            reliability: "1.0".to_string(),
+            stream: 0,
+            segment: None,
+            flags: None,
+            from_epid: None,
+            to_epid: None,
+            to_path: None,
+            from_path: None,
+        };
+
+        let mut out = Self::new(envelope, NfyContentType::Datacast, false);
+        out.add_header("Messaging", "1.0");
+        out.add_header("Message-Type", "Data");
+        out.set_body(RawMsgPayloadFactory::get_action_msg(text, false).body);
+
+        out
+    }
+
+    pub fn new_p2p(source: &MsnUser, destination: &MsnUser, packet: &P2PTransportPacket) -> Self {
+        let envelope = NfyEnvelope{
+            routing: "1.0".to_string(),
+            from: source.get_network_id_email(),
+            to: destination.get_network_id_email(),
+            reliability: "1.0".to_string(),
+            stream: 0,
+            segment: None,
+            flags: None,
+            from_epid: source.endpoint_id.endpoint_guid.clone(),
+            to_epid: destination.endpoint_id.endpoint_guid.clone(),
+            to_path: None,
+            from_path: None,
+        };
+
+        let mut out = Self::new(envelope, NfyContentType::P2P, false);
+        out.add_header("Messaging", "2.0");
+        out.add_header("Message-Type", "Data");
+        out.add_header("Content-Transfer-Encoding", "binary");
+        out.add_header("Bridging-Offsets", "0");
+        out.set_body(packet.to_vec());
+
+        out
+    }
 
     pub fn add_header(&mut self, name: &str, value: &str) {
         self.headers.insert(name.to_string(), value.to_string());
@@ -361,6 +721,21 @@ impl RawNfyPayload {
     pub fn get_body_as_str(&self) -> Result<&str, Utf8Error> {
         from_utf8(&self.body)
     }
+
+    /* Text messages sent with SDG carry the MSG text headers, e.g. to a circle */
+    pub fn get_text_message(&self) -> Result<TextMessageContent, PayloadError> {
+        if self.content_type != NfyContentType::PlainText || self.get_header("Message-Type") != Some("Text") {
+            return Err(PayloadError::AnyError(anyhow!("NFY payload is not a text message: {} {:?}", self.content_type, self.get_header("Message-Type"))));
+        }
+
+        let mut raw = RawMsgPayload::new(MsgContentType::TextPlain, false);
+        if let Some(format) = self.get_header("X-MMS-IM-Format") {
+            raw.add_header("X-MMS-IM-Format", format);
+        }
+        raw.set_body(self.body.clone());
+
+        TextMessageContent::try_from_raw(raw)
+    }
 }
 
 impl MSNPPayload for RawNfyPayload {
@@ -136,11 +136,33 @@ pub mod request {
 }
 
 pub mod response {
+    use yaserde::ser::to_string;
     use yaserde_derive::{YaDeserialize, YaSerialize};
     use crate::shared::models::uuid::Uuid;
     use crate::soap::abch::msnab_datatypes::Guid;
     use crate::soap::abch::msnab_faults::SoapFault;
     use crate::soap::abch::service_header::ServiceHeaderContainer;
+    use crate::soap::error::SoapMarshallError;
+    use crate::soap::traits::xml::ToXml;
+
+    #[cfg(test)]
+    mod tests {
+        use crate::shared::models::uuid::Uuid;
+        use crate::soap::abch::ab_service::ab_contact_add::response::AbcontactAddResponseMessageSoapEnvelope;
+        use crate::soap::traits::xml::ToXml;
+
+        #[test]
+        fn test_ab_contact_add_response() {
+            let contact_uuid = Uuid::from_seed("test@shlasouf.local");
+            let response = AbcontactAddResponseMessageSoapEnvelope::get_response(&contact_uuid, "cachekey");
+
+            let response_serialized = response.to_xml().expect("to work");
+
+            assert!(response_serialized.contains("<CacheKey>cachekey</CacheKey>"));
+            assert!(response_serialized.contains(&format!("<guid>{}</guid>", contact_uuid)));
+        }
+
+    }
 
     #[derive(Debug, Default, YaSerialize, YaDeserialize)]
     pub struct SoapAbcontactAddResponseMessage {
@@ -198,4 +220,12 @@ pub mod response {
             Self { body, header: Some(ServiceHeaderContainer::new(cache_key)) }
         }
     }
+
+    impl ToXml for AbcontactAddResponseMessageSoapEnvelope {
+        type Error = SoapMarshallError;
+        fn to_xml(&self) -> Result<String, Self::Error>  {
+            to_string(self).map_err(|e| SoapMarshallError::SerializationError { message: e})
+        }
+
+    }
 }
\ No newline at end of file
@@ -122,12 +122,13 @@ pub mod request {
 
 }
 
-#[cfg(test)]
 pub mod response {
 
+    #[cfg(test)]
     mod tests {
         use yaserde::ser::to_string;
         use crate::soap::abch::ab_service::ab_contact_update::response::AbcontactUpdateResponseMessageSoapEnvelope;
+        use crate::soap::traits::xml::ToXml;
 
 
         #[test]
@@ -137,15 +138,25 @@ pub mod response {
             let response_serialized = to_string(&response).unwrap();
             println!("{}", response_serialized);
 
-            //TODO assertions
+            assert!(response_serialized.contains("ABContactUpdateResponse"));
+            assert!(response_serialized.contains("<CacheKey>cachekey</CacheKey>"));
+        }
 
+        #[test]
+        fn test_contact_update_response_to_xml() {
+            let response = AbcontactUpdateResponseMessageSoapEnvelope::get_response("cachekey");
+            let response_serialized = response.to_xml().expect("to serialize");
+            assert!(response_serialized.contains("ABContactUpdateResponse"));
         }
 
     }
 
+    use yaserde::ser::to_string;
     use yaserde_derive::{YaDeserialize, YaSerialize};
     use crate::soap::abch::msnab_faults::SoapFault;
     use crate::soap::abch::service_header::ServiceHeaderContainer;
+    use crate::soap::error::SoapMarshallError;
+    use crate::soap::traits::xml::ToXml;
 
     #[derive(Debug, Default, YaSerialize, YaDeserialize)]
     pub struct SoapAbcontactUpdateResponseMessage {
@@ -159,7 +170,7 @@ pub mod response {
     #[yaserde(
     rename = "ABContactUpdateResponse",
     namespace = "nsi1: http://www.msn.com/webservices/AddressBook",
-    prefix = "nsi1"
+    prefix = "nsi1",
     default_namespace="nsi1"
     )]
     pub struct AbcontactUpdateResponse {}
@@ -192,6 +203,13 @@ pub mod response {
         }
     }
 
+    impl ToXml for AbcontactUpdateResponseMessageSoapEnvelope {
+        type Error = SoapMarshallError;
+        fn to_xml(&self) -> Result<String, Self::Error>  {
+            to_string(self).map_err(|e| SoapMarshallError::SerializationError { message: e})
+        }
+    }
+
 
 
 
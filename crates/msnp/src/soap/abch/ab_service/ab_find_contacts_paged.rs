@@ -130,6 +130,7 @@ pub mod response {
         use crate::soap::abch::ab_service::ab_find_contacts_paged::response::{Ab, AbfindContactsPagedResponse, AbfindContactsPagedResponseMessageSoapEnvelope, AbfindContactsPagedResultType, Groups, SoapAbfindContactsPagedResponseMessage};
         use crate::soap::abch::msnab_datatypes::{AbInfoType, AddressBookType, ArrayOfContactType, CircleResultType, ContactType, GroupType};
         use crate::soap::abch::service_header::{ServiceHeader, ServiceHeaderContainer};
+        use crate::shared::models::uuid::Uuid;
 
         #[test]
         fn test_find_contacts_paged_response() {
@@ -155,6 +156,15 @@ pub mod response {
             let serialized = to_string(&r).unwrap();
             println!("{}", serialized);
         }
+
+        #[test]
+        fn test_individual_response_me_blp() {
+            let response = AbfindContactsPagedResponseMessageSoapEnvelope::new_individual(Uuid::from_seed("aeon@test.com"), "cache_key", "aeon@test.com", "Aeon", Vec::new(), Vec::new(), Vec::new(), false, true);
+            let me = response.body.body.ab_find_contacts_paged_result.contacts.expect("contacts to be here").contact.remove(0);
+            let blp = me.contact_info.expect("contact info to be here").annotations.expect("annotations to be here").annotation.into_iter().find(|annotation| annotation.name == "MSN.IM.BLP").expect("BLP annotation to be here");
+
+            assert_eq!(Some("0".to_string()), blp.value);
+        }
     }
 
     use std::str::FromStr;
@@ -284,7 +294,7 @@ pub mod response {
             Self{header: Some(ServiceHeaderContainer::new(cache_key)), body }
         }
 
-        pub fn new_individual(uuid: Uuid, cache_key: &str, msn_addr: &str, display_name: &str, mut contacts: Vec<ContactType>, mut circles: Vec<CircleData>, profile_update: bool) -> Self {
+        pub fn new_individual(uuid: Uuid, cache_key: &str, msn_addr: &str, display_name: &str, mut contacts: Vec<ContactType>, groups: Vec<GroupType>, mut circles: Vec<CircleData>, profile_update: bool, allow_list_only: bool) -> Self {
             let now = Local::now();
 
             let create_date = String::from("2014-10-31T00:00:00Z");
@@ -292,17 +302,9 @@ pub mod response {
             let ab_info_type = AbInfoType{ migrated_to: None, beta_status: None, name: None, owner_puid: 0, owner_cid: uuid.to_decimal_cid(), owner_email:Some(msn_addr.to_string()), f_default: true, joined_namespace: false, is_bot: false, is_parent_managed: false, account_tier: None, account_tier_last_changed: String::from("0001-01-01T00:00:00"), profile_version: 0, subscribe_external_partner: false, notify_external_partner: false, address_book_type: AddressBookType::Individual, messenger_application_service_created: None, is_beta_migrated: None, last_relevance_update: None };
             let ab = Ab{ ab_id: Uuid::nil().to_string(), ab_info: ab_info_type, last_change: now.format("%Y-%m-%dT%H:%M:%SZ").to_string(), dynamic_item_last_changed: String::from("0001-01-01T00:00:00"), recent_activity_item_last_changed: None, create_date: create_date.clone(), properties_changed: String::new() };
 
-            contacts.push(ContactType::new_me(&uuid, &msn_addr, &display_name, profile_update));
+            contacts.push(ContactType::new_me(&uuid, &msn_addr, &display_name, profile_update, allow_list_only));
 
-            let mut favorite_annotation_arary : Vec<Annotation> = Vec::new();
-            favorite_annotation_arary.push(Annotation::new_display(Some(true)));
-            let favorite_array_of_annotations = ArrayOfAnnotation{ annotation: favorite_annotation_arary };
-
-
-            let favorite_group = GroupType{ group_id: String::from("1ae28c79-c963-4fe6-8339-d72a0f7c8bd2"), group_info: GroupInfoType{ annotations: Some(favorite_array_of_annotations), group_type: Some(String::from("c8529ce2-6ead-434d-881f-341e17db3ff8")), name: Some(String::from("Favorites")), is_not_mobile_visible: Some(false), is_private: Some(false), is_favorite: Some(true), f_messenger: None }, properties_changed: String::new(), f_deleted: Some(false), last_change: Some(create_date.clone())};
-            let mut group_array : Vec<GroupType> = Vec::new();
-            group_array.push(favorite_group);
-            let groups = Groups{ group: group_array };
+            let groups = if groups.is_empty() { None } else { Some(Groups{ group: groups }) };
 
             let circles = {
               if circles.is_empty() {
@@ -324,7 +326,7 @@ pub mod response {
             let circle_result = CircleResultType{ circles, circle_ticket: String::from("&lt;?xml version=\"1.0\" encoding=\"utf-16\"?&gt;&lt;SignedTicket xmlns:xsi=\"http://www.w3.org/2001/XMLSchema-instance\" xmlns:xsd=\"http://www.w3.org/2001/XMLSchema\" ver=\"1\" keyVer=\"1\"&gt;&lt;Data&gt;PD94bWwgdmVyc2lvbj0iMS4wIiBlbmNvZGluZz0idXRmLTE2Ij8+DQo8VGlja2V0IHhtbG5zOnhzaT0iaHR0cDovL3d3dy53My5vcmcvMjAwMS9YTUxTY2hlbWEtaW5zdGFuY2UiIHhtbG5zOnhzZD0iaHR0cDovL3d3dy53My5vcmcvMjAwMS9YTUxTY2hlbWEiPg0KICA8VFM+MDAwMC0wMS0wMVQwMDowMDowMDwvVFM+DQogIDxDSUQ+LTc3NzY5ODI1NzkyNzI5Mzc1NzI8L0NJRD4NCjwvVGlja2V0Pg==&lt;/Data&gt;&lt;Sig&gt;SLE8LXFmBW/2nMY9t+lG/7w4APZt3Z5U4nsu3G7KSWSdTEvTt9mt2kdssQaxxjEhy8udrLlC2dFSQXtHI/6mmbHhtaf7wx2WvRb4F1ayv5kZmrp5lJPkEXhdSwzJHlYPZM530Gsr7Md9MW4w67F7ct7i2MhsQyBLXr5nEDLlILHjTNUkbIa31IZJ5Qpwnr7Cj4XLPYOl8Phl6mHSjWdLo/CvohxRnAb/akABRyIhdd4rIvZREYsYhjSyZ/RLc6j0eLF7zkn8jjLKVGkIIFNvcGGnv/9ZtQ4zO5a/OkNB18Pvj6excNHt8zeCXiPomIikZrUOEZ4sshYRAJ7/5k/PAA==&lt;/Sig&gt;&lt;/SignedTicket&gt;") };
 
             let array_of_contact = ArrayOfContactType{ contact: contacts };
-            let result = AbfindContactsPagedResultType{ groups: Some(groups), contacts: Some(array_of_contact), circle_result: Some(circle_result), ab };
+            let result = AbfindContactsPagedResultType{ groups, contacts: Some(array_of_contact), circle_result: Some(circle_result), ab };
             let body_body = AbfindContactsPagedResponse{ ab_find_contacts_paged_result: result };
             let body = SoapAbfindContactsPagedResponseMessage{ body: body_body, fault: None };
 
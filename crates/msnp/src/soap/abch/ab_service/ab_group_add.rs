@@ -180,9 +180,12 @@ pub mod response {
 
     }
 
+    use yaserde::ser::to_string;
     use yaserde_derive::{YaDeserialize, YaSerialize};
     use crate::soap::abch::msnab_faults::SoapFault;
     use crate::soap::abch::service_header::ServiceHeaderContainer;
+    use crate::soap::error::SoapMarshallError;
+    use crate::soap::traits::xml::ToXml;
 
     #[derive(Debug, Default, YaSerialize, YaDeserialize)]
     pub struct SoapAbgroupAddResponseMessage {
@@ -231,7 +234,7 @@ pub mod response {
     }
 
     impl AbgroupAddResponseMessageSoapEnvelope {
-        pub fn new_favorite_group_added_response(guid: &str, cache_key: &str) -> Self {
+        pub fn get_response(guid: &str, cache_key: &str) -> Self {
             let result = AbgroupAddResultType { guid: guid.to_string() };
             let group_add_response = AbgroupAddResponse{ ab_group_add_result: Some(result) };
 
@@ -239,4 +242,11 @@ pub mod response {
             Self{ header: Some(ServiceHeaderContainer::new(cache_key)), body  }
         }
     }
+
+    impl ToXml for AbgroupAddResponseMessageSoapEnvelope {
+        type Error = SoapMarshallError;
+        fn to_xml(&self) -> Result<String, Self::Error>  {
+            to_string(self).map_err(|e| SoapMarshallError::SerializationError { message: e})
+        }
+    }
 }
\ No newline at end of file
@@ -1,4 +1,6 @@
 pub mod request {
+    use crate::soap::error::SoapMarshallError;
+    use crate::soap::traits::xml::TryFromXml;
     use yaserde_derive::{YaDeserialize, YaSerialize};
     use crate::soap::abch::msnab_datatypes::{ArrayOfContactType, GroupFilterType, Guid};
     use crate::soap::abch::request_header::RequestHeaderContainer;
@@ -132,9 +134,21 @@ pub mod request {
         }
     }
 
+    impl TryFromXml for AbgroupContactAddMessageSoapEnvelope {
+
+        type Error = SoapMarshallError;
+
+        fn try_from_xml(xml_str: &str) -> Result<Self, Self::Error> {
+            yaserde::de::from_str::<Self>(&xml_str).map_err(|e| Self::Error::DeserializationError { message: e})
+        }
+    }
+
 }
 
 pub mod response {
+    use yaserde::ser::to_string;
+    use crate::soap::error::SoapMarshallError;
+    use crate::soap::traits::xml::ToXml;
     use yaserde_derive::{YaDeserialize, YaSerialize};
     use crate::soap::abch::msnab_datatypes::Guid;
     use crate::soap::abch::msnab_faults::SoapFault;
@@ -175,6 +189,16 @@ pub mod response {
 
         }
 
+
+        #[test]
+        fn test_ab_group_contact_add_response() {
+            let response = AbgroupContactAddResponseMessageSoapEnvelope::get_response("a267edb0-a29a-4257-8fbe-73468c4c0845", "cachekey");
+            let response_serialized = yaserde::ser::to_string(&response).unwrap();
+
+            let deser = from_str::<AbgroupContactAddResponseMessageSoapEnvelope>(&response_serialized).expect("things to work");
+            assert_eq!("a267edb0-a29a-4257-8fbe-73468c4c0845", &deser.body.body.ab_group_contact_add_result.expect("to be here").guid.body);
+        }
+
     }
 
     #[derive(Debug, Default, YaSerialize, YaDeserialize)]
@@ -224,6 +248,19 @@ pub mod response {
                 header: None,
             }
         }
+
+        pub fn get_response(contact_id: &str, cache_key: &str) -> Self {
+            let result = AbgroupContactAddResultType{ guid: Guid{ body: contact_id.to_string() } };
+            let body = SoapAbgroupContactAddResponseMessage{ body: AbgroupContactAddResponse{ ab_group_contact_add_result: Some(result) }, fault: None };
+            Self{ header: Some(ServiceHeaderContainer::new(cache_key)), body }
+        }
+    }
+
+    impl ToXml for AbgroupContactAddResponseMessageSoapEnvelope {
+        type Error = SoapMarshallError;
+        fn to_xml(&self) -> Result<String, Self::Error>  {
+            to_string(self).map_err(|e| SoapMarshallError::SerializationError { message: e})
+        }
     }
 
 
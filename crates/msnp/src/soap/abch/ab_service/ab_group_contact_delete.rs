@@ -1,4 +1,6 @@
 pub mod requets {
+    use crate::soap::error::SoapMarshallError;
+    use crate::soap::traits::xml::TryFromXml;
     use yaserde_derive::{YaDeserialize, YaSerialize};
     use crate::soap::abch::msnab_datatypes::{ArrayOfContactType, GroupFilterType, Guid};
     use crate::soap::abch::request_header::RequestHeaderContainer;
@@ -95,12 +97,24 @@ pub mod requets {
         }
     }
 
+    impl TryFromXml for AbgroupContactDeleteMessageSoapEnvelope {
+
+        type Error = SoapMarshallError;
+
+        fn try_from_xml(xml_str: &str) -> Result<Self, Self::Error> {
+            yaserde::de::from_str::<Self>(&xml_str).map_err(|e| Self::Error::DeserializationError { message: e})
+        }
+    }
+
 
 
 
 }
 
 pub mod response {
+    use yaserde::ser::to_string;
+    use crate::soap::error::SoapMarshallError;
+    use crate::soap::traits::xml::ToXml;
     use yaserde_derive::{YaDeserialize, YaSerialize};
     use crate::soap::abch::msnab_faults::SoapFault;
     use crate::soap::abch::request_header::RequestHeaderContainer;
@@ -136,6 +150,15 @@ pub mod response {
         }
 
 
+
+        #[test]
+        fn test_ab_group_contact_delete_response() {
+            let response = AbgroupContactDeleteResponseMessageSoapEnvelope::get_response("cachekey");
+            let response_serialized = yaserde::ser::to_string(&response).unwrap();
+            assert!(response_serialized.contains("ABGroupContactDeleteResponse"));
+            assert!(response_serialized.contains("cachekey"));
+        }
+
     }
 
     #[derive(Debug, Default, YaSerialize, YaDeserialize)]
@@ -177,6 +200,18 @@ pub mod response {
                 header: None,
             }
         }
+
+        pub fn get_response(cache_key: &str) -> Self {
+            let body = SoapAbgroupContactDeleteResponseMessage{ body: AbgroupContactDeleteResponse{}, fault: None };
+            Self{ header: Some(ServiceHeaderContainer::new(cache_key)), body }
+        }
+    }
+
+    impl ToXml for AbgroupContactDeleteResponseMessageSoapEnvelope {
+        type Error = SoapMarshallError;
+        fn to_xml(&self) -> Result<String, Self::Error>  {
+            to_string(self).map_err(|e| SoapMarshallError::SerializationError { message: e})
+        }
     }
 
 
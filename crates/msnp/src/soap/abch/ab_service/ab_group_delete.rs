@@ -1,4 +1,6 @@
 pub mod request {
+    use crate::soap::error::SoapMarshallError;
+    use crate::soap::traits::xml::TryFromXml;
     use yaserde_derive::{YaDeserialize, YaSerialize};
     use crate::soap::abch::msnab_datatypes::{GroupFilterType, Guid};
     use crate::soap::abch::request_header::RequestHeaderContainer;
@@ -89,10 +91,22 @@ pub mod request {
         }
     }
 
+    impl TryFromXml for AbgroupDeleteMessageSoapEnvelope {
+
+        type Error = SoapMarshallError;
+
+        fn try_from_xml(xml_str: &str) -> Result<Self, Self::Error> {
+            yaserde::de::from_str::<Self>(&xml_str).map_err(|e| Self::Error::DeserializationError { message: e})
+        }
+    }
+
 
 }
 
 pub mod response {
+    use yaserde::ser::to_string;
+    use crate::soap::error::SoapMarshallError;
+    use crate::soap::traits::xml::ToXml;
     use yaserde_derive::{YaDeserialize, YaSerialize};
     use crate::soap::abch::msnab_faults::SoapFault;
     use crate::soap::abch::request_header::RequestHeaderContainer;
@@ -132,6 +146,15 @@ pub mod response {
         }
 
 
+
+        #[test]
+        fn test_ab_group_delete_response() {
+            let response = AbgroupDeleteResponseMessageSoapEnvelope::get_response("cachekey");
+            let response_serialized = yaserde::ser::to_string(&response).unwrap();
+            assert!(response_serialized.contains("ABGroupDeleteResponse"));
+            assert!(response_serialized.contains("cachekey"));
+        }
+
     }
 
     #[derive(Debug, Default, YaSerialize, YaDeserialize)]
@@ -172,6 +195,18 @@ pub mod response {
                 header: None
             }
         }
+
+        pub fn get_response(cache_key: &str) -> Self {
+            let body = SoapAbgroupDeleteResponseMessage{ body: AbgroupDeleteResponse{} };
+            Self{ header: Some(ServiceHeaderContainer::new(cache_key)), body }
+        }
+    }
+
+    impl ToXml for AbgroupDeleteResponseMessageSoapEnvelope {
+        type Error = SoapMarshallError;
+        fn to_xml(&self) -> Result<String, Self::Error>  {
+            to_string(self).map_err(|e| SoapMarshallError::SerializationError { message: e})
+        }
     }
 
 
@@ -1,4 +1,6 @@
 pub mod request {
+    use crate::soap::error::SoapMarshallError;
+    use crate::soap::traits::xml::TryFromXml;
     use yaserde_derive::{YaDeserialize, YaSerialize};
 
     use crate::soap::abch::ab_service::ab_find_contacts_paged::response::Groups;
@@ -94,10 +96,22 @@ pub mod request {
         }
     }
 
+    impl TryFromXml for AbgroupUpdateMessageSoapEnvelope {
+
+        type Error = SoapMarshallError;
+
+        fn try_from_xml(xml_str: &str) -> Result<Self, Self::Error> {
+            yaserde::de::from_str::<Self>(&xml_str).map_err(|e| Self::Error::DeserializationError { message: e})
+        }
+    }
+
 
 }
 
 pub mod response {
+    use yaserde::ser::to_string;
+    use crate::soap::error::SoapMarshallError;
+    use crate::soap::traits::xml::ToXml;
     use yaserde_derive::{YaDeserialize, YaSerialize};
 
     use crate::soap::abch::msnab_faults::SoapFault;
@@ -134,6 +148,15 @@ pub mod response {
             let deser = from_str::<AbgroupUpdateResponseMessageSoapEnvelope>(raw).expect("things to work");
 
         }
+
+        #[test]
+        fn test_ab_group_update_response() {
+            let response = AbgroupUpdateResponseMessageSoapEnvelope::get_response("cachekey");
+            let response_serialized = yaserde::ser::to_string(&response).unwrap();
+            assert!(response_serialized.contains("ABGroupUpdateResponse"));
+            assert!(response_serialized.contains("cachekey"));
+        }
+
     }
 
     #[derive(Debug, Default, YaSerialize, YaDeserialize)]
@@ -175,6 +198,18 @@ pub mod response {
                 header: None,
             }
         }
+
+        pub fn get_response(cache_key: &str) -> Self {
+            let body = SoapAbgroupUpdateResponseMessage{ response: AbgroupUpdateResponse{}, fault: None };
+            Self{ header: Some(ServiceHeaderContainer::new(cache_key)), body }
+        }
+    }
+
+    impl ToXml for AbgroupUpdateResponseMessageSoapEnvelope {
+        type Error = SoapMarshallError;
+        fn to_xml(&self) -> Result<String, Self::Error>  {
+            to_string(self).map_err(|e| SoapMarshallError::SerializationError { message: e})
+        }
     }
 
 
@@ -688,7 +688,7 @@ pub struct ContactType {
 
 impl ContactType {
 
-	pub fn new_me(uuid: &Uuid, msn_addr: &str, display_name: &str, profile_update: bool) -> ContactType {
+	pub fn new_me(uuid: &Uuid, msn_addr: &str, display_name: &str, profile_update: bool, allow_list_only: bool) -> ContactType {
 
 		let now = Local::now();
 
@@ -697,7 +697,7 @@ impl ContactType {
 		annotation_array.push(Annotation::new_roam_live_properties(Some(true)));
 		annotation_array.push(Annotation::new_mbea(Some(false)));
 		annotation_array.push(Annotation::new_gtc(Some(true)));
-		annotation_array.push(Annotation::new_blp(Some(true)));
+		annotation_array.push(Annotation::new_blp(Some(!allow_list_only)));
 
 		if profile_update {
 			annotation_array.push(Annotation::new_live_profile_expression_last_changed(Local::now()));
@@ -1088,6 +1088,19 @@ pub struct GroupType {
 	#[yaserde(rename = "lastChange", prefix = "nsi1")]
 	pub last_change: Option<String>, 
 }
+
+pub const MESSENGER_GROUP_TYPE: &str = "c8529ce2-6ead-434d-881f-341e17db3ff8";
+
+impl GroupType {
+
+	pub fn new(group_id: &str, name: &str, is_favorite: bool, deleted: bool, last_change: &str) -> GroupType {
+		let array_of_annotations = ArrayOfAnnotation{ annotation: vec![Annotation::new_display(Some(true))] };
+		let group_info = GroupInfoType{ annotations: Some(array_of_annotations), group_type: Some(MESSENGER_GROUP_TYPE.to_string()), name: Some(name.to_string()), is_not_mobile_visible: Some(false), is_private: Some(false), is_favorite: Some(is_favorite), f_messenger: None };
+		return GroupType{ group_id: group_id.to_string(), group_info, properties_changed: String::new(), f_deleted: Some(deleted), last_change: Some(last_change.to_string()) };
+	}
+
+}
+
 #[derive(Debug, Default, YaSerialize, YaDeserialize, Clone)]
 #[yaserde(
 	rename = "groupInfoType", namespace = "nsi1: http://www.msn.com/webservices/AddressBook",
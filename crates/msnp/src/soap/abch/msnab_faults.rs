@@ -208,6 +208,37 @@ impl SoapFaultResponseEnvelope {
 
 	}
 
+	pub fn new_group_doesnt_exist(soap_action: String, confict_object_id: &Uuid) -> Self {
+
+		let additional_details = FaultAdditionalDetails{
+			original_exception_error_message: None,
+			conflict_object_id: Some(confict_object_id.to_string())
+		};
+
+
+		let fault_detail = FaultDetail{
+			error_code: Some(FaultErrorCode::ABGroupDoesNotExist),
+			error_string: Some("Group Does Not Exist".into()),
+			machine_name: Some("TACHEPSILON3".into()),
+			parameter_fault: None,
+			additional_details: Some(additional_details),
+		};
+
+		let soap_fault = SoapFault{
+			fault_code: Some("soap:Client".into()),
+			fault_string: Some("Group Does Not Exist".into()),
+			fault_actor: Some(soap_action),
+			detail: Some(fault_detail),
+		};
+
+		SoapFaultResponseEnvelope {
+			body: SoapFaultBody {
+				fault: soap_fault,
+			}
+		}
+
+	}
+
 	pub fn new_group_name_too_long(soap_action: String) -> Self {
 		let additional_details = FaultAdditionalDetails{
 			original_exception_error_message: Some("Argument Exceeded Allowed Length GroupName exceeded length".into()),
@@ -455,6 +486,7 @@ pub enum FaultErrorCode {
 	BadArgumentLength,
 	BadEmailArgument,
 	GroupAlreadyExists,
+	ABGroupDoesNotExist,
 	FullSyncRequired,
 	ContactDoesNotExist,
 	ContactAlreadyExists,
@@ -514,4 +546,13 @@ mod tests {
 
 	}
 
+	#[test]
+	fn test_serialize_group_doesnt_exist() {
+		let response = SoapFaultResponseEnvelope::new_group_doesnt_exist("http://www.msn.com/webservices/AddressBook/ABGroupContactAdd".into(), &Uuid::from_seed("group"));
+		let response_serialized = to_string(&response).unwrap();
+
+		assert!(response_serialized.contains(">ABGroupDoesNotExist</nsi1:errorcode>"));
+		assert!(response_serialized.contains(&Uuid::from_seed("group").to_string()));
+	}
+
 }
@@ -5,4 +5,5 @@ pub mod storage_service;
 pub mod traits;
 pub mod error;
 pub mod rsi;
+pub mod oim;
 pub mod space;
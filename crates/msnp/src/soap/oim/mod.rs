@@ -0,0 +1 @@
+pub mod store2;
\ No newline at end of file
@@ -0,0 +1,290 @@
+pub mod request {
+    use std::str::FromStr;
+
+    use yaserde_derive::{YaDeserialize, YaSerialize};
+
+    use crate::msnp::error::PayloadError;
+    use crate::shared::converters::rfc2047;
+    use crate::shared::models::oim::OIM;
+    use crate::soap::error::SoapMarshallError;
+    use crate::soap::traits::xml::TryFromXml;
+
+    #[cfg(test)]
+    mod tests {
+        use crate::soap::oim::store2::request::Store2MessageSoapEnvelope;
+        use crate::soap::traits::xml::TryFromXml;
+
+        #[test]
+        fn deser_test() {
+            let req = "<?xml version=\"1.0\" encoding=\"utf-8\"?><soap:Envelope xmlns:xsi=\"http://www.w3.org/2001/XMLSchema-instance\" xmlns:xsd=\"http://www.w3.org/2001/XMLSchema\" xmlns:soap=\"http://schemas.xmlsoap.org/soap/envelope/\"><soap:Header><From memberName=\"aeoncl@shlasouf.local\" friendlyName=\"=?utf-8?B?QWxpbsOpYQ==?=\" xml:lang=\"fr-FR\" proxy=\"MSNMSGR\" xmlns=\"http://messenger.msn.com/ws/2004/09/oim/\" msnpVer=\"MSNP18\" buildVer=\"14.0.8117.0416\"/><To memberName=\"inky@shlasouf.local\" xmlns=\"http://messenger.msn.com/ws/2004/09/oim/\"/><Ticket passport=\"t=t0ken&amp;p=\" appid=\"PROD0120PW!CCV9@\" lockkey=\"\" xmlns=\"http://messenger.msn.com/ws/2004/09/oim/\"/><Sequence xmlns=\"http://schemas.xmlsoap.org/ws/2003/03/rm\"><Identifier xmlns=\"http://schemas.xmlsoap.org/ws/2002/07/utility\">http://messenger.msn.com</Identifier><MessageNumber>1</MessageNumber></Sequence></soap:Header><soap:Body><MessageType xmlns=\"http://messenger.msn.com/ws/2004/09/oim/\">text</MessageType><Content xmlns=\"http://messenger.msn.com/ws/2004/09/oim/\">MIME-Version: 1.0\r\nContent-Type: text/plain; charset=UTF-8\r\nContent-Transfer-Encoding: base64\r\nX-OIM-Message-Type: OfflineMessage\r\nX-OIM-Run-Id: {3A3BE82F-CC2D-4CC3-9D4D-7F0E9B2F3D4D}\r\nX-OIM-Sequence-Num: 1\r\n\r\nSGVsbG8gd29ybGQ=</Content></soap:Body></soap:Envelope>";
+
+            let deser = Store2MessageSoapEnvelope::try_from_xml(req).unwrap();
+            let header = deser.header.expect("header to be here");
+
+            assert_eq!("aeoncl@shlasouf.local", &header.from.member_name);
+            assert_eq!(Some("Alinéa".to_string()), header.from.get_friendly_name());
+            assert_eq!("inky@shlasouf.local", &header.to.member_name);
+            assert_eq!("t=t0ken&p=", &header.ticket.passport);
+            assert_eq!(Some("t0ken"), header.ticket.get_token());
+            assert_eq!("PROD0120PW!CCV9@", &header.ticket.app_id);
+            assert_eq!("", &header.ticket.lock_key);
+            assert_eq!(1, header.sequence.message_number);
+            assert_eq!("text", &deser.body.message_type);
+
+            let oim = deser.body.get_oim().unwrap();
+            assert_eq!("Hello world", &oim.content);
+            assert_eq!(1, oim.seq_number);
+        }
+    }
+
+    #[derive(Debug, Default, YaSerialize, YaDeserialize, Clone)]
+    #[yaserde(
+    rename = "From",
+    namespace = "nsi1: http://messenger.msn.com/ws/2004/09/oim/",
+    default_namespace = "nsi1"
+    )]
+    pub struct StoreFrom {
+        #[yaserde(attribute, rename = "memberName")]
+        pub member_name: String,
+        /* RFC2047 encoded */
+        #[yaserde(attribute, rename = "friendlyName")]
+        pub friendly_name: Option<String>,
+        #[yaserde(attribute, rename = "proxy")]
+        pub proxy: Option<String>,
+        #[yaserde(attribute, rename = "msnpVer")]
+        pub msnp_ver: Option<String>,
+        #[yaserde(attribute, rename = "buildVer")]
+        pub build_ver: Option<String>,
+    }
+
+    impl StoreFrom {
+        pub fn get_friendly_name(&self) -> Option<String> {
+            self.friendly_name.as_ref().map(|friendly_name| rfc2047::decode(friendly_name))
+        }
+    }
+
+    #[derive(Debug, Default, YaSerialize, YaDeserialize, Clone)]
+    #[yaserde(
+    rename = "To",
+    namespace = "nsi1: http://messenger.msn.com/ws/2004/09/oim/",
+    default_namespace = "nsi1"
+    )]
+    pub struct StoreTo {
+        #[yaserde(attribute, rename = "memberName")]
+        pub member_name: String,
+    }
+
+    #[derive(Debug, Default, YaSerialize, YaDeserialize, Clone)]
+    #[yaserde(
+    rename = "Ticket",
+    namespace = "nsi1: http://messenger.msn.com/ws/2004/09/oim/",
+    default_namespace = "nsi1"
+    )]
+    pub struct Ticket {
+        /* t=token&p= */
+        #[yaserde(attribute, rename = "passport")]
+        pub passport: String,
+        /* The product id, its product key is used for the lock key challenge */
+        #[yaserde(attribute, rename = "appid")]
+        pub app_id: String,
+        /* Empty until the server answered with a LockKeyChallenge */
+        #[yaserde(attribute, rename = "lockkey")]
+        pub lock_key: String,
+    }
+
+    impl Ticket {
+        pub fn get_token(&self) -> Option<&str> {
+            self.passport.split('&').find_map(|part| part.strip_prefix("t="))
+        }
+    }
+
+    #[derive(Debug, Default, YaSerialize, YaDeserialize, Clone)]
+    #[yaserde(
+    rename = "Sequence",
+    namespace = "nsi2: http://schemas.xmlsoap.org/ws/2003/03/rm",
+    namespace = "nsi3: http://schemas.xmlsoap.org/ws/2002/07/utility",
+    default_namespace = "nsi2"
+    )]
+    pub struct Sequence {
+        #[yaserde(rename = "Identifier", prefix = "nsi3")]
+        pub identifier: String,
+        #[yaserde(rename = "MessageNumber", prefix = "nsi2")]
+        pub message_number: u32,
+    }
+
+    #[derive(Debug, Default, YaSerialize, YaDeserialize, Clone)]
+    #[yaserde(
+    rename = "Header",
+    namespace = "soapenv: http://schemas.xmlsoap.org/soap/envelope/",
+    prefix = "soapenv"
+    )]
+    pub struct Store2Header {
+        #[yaserde(rename = "From", default)]
+        pub from: StoreFrom,
+        #[yaserde(rename = "To", default)]
+        pub to: StoreTo,
+        #[yaserde(rename = "Ticket", default)]
+        pub ticket: Ticket,
+        #[yaserde(rename = "Sequence", default)]
+        pub sequence: Sequence,
+    }
+
+    #[derive(Debug, Default, YaSerialize, YaDeserialize, Clone)]
+    #[yaserde(
+    namespace = "nsi1: http://messenger.msn.com/ws/2004/09/oim/",
+    )]
+    pub struct SoapStore2Message {
+        /* Only text has been observed */
+        #[yaserde(rename = "MessageType", prefix = "nsi1", default)]
+        pub message_type: String,
+        /* The OIM MIME message, without From & To */
+        #[yaserde(rename = "Content", prefix = "nsi1", default)]
+        pub content: String,
+    }
+
+    impl SoapStore2Message {
+        pub fn get_oim(&self) -> Result<OIM, PayloadError> {
+            OIM::from_str(&self.content)
+        }
+    }
+
+    #[derive(Debug, Default, YaSerialize, YaDeserialize)]
+    #[yaserde(
+    rename = "Envelope",
+    namespace = "soapenv: http://schemas.xmlsoap.org/soap/envelope/",
+    namespace = "xsi: http://www.w3.org/2001/XMLSchema-instance",
+    namespace = "xsd: http://www.w3.org/2001/XMLSchema",
+    prefix = "soapenv"
+    )]
+    pub struct Store2MessageSoapEnvelope {
+        #[yaserde(rename = "Header", prefix = "soapenv")]
+        pub header: Option<Store2Header>,
+        #[yaserde(rename = "Body", prefix = "soapenv")]
+        pub body: SoapStore2Message,
+    }
+
+    impl TryFromXml for Store2MessageSoapEnvelope {
+
+        type Error = SoapMarshallError;
+
+        fn try_from_xml(xml_str: &str) -> Result<Self, Self::Error> {
+            yaserde::de::from_str::<Self>(&xml_str).map_err(|e| Self::Error::DeserializationError { message: e})
+        }
+    }
+
+}
+
+pub mod response {
+    use yaserde::ser::to_string;
+    use yaserde_derive::{YaDeserialize, YaSerialize};
+
+    use crate::soap::error::SoapMarshallError;
+    use crate::soap::traits::xml::ToXml;
+
+    #[cfg(test)]
+    mod tests {
+        use crate::soap::oim::store2::response::Store2ResponseSoapEnvelope;
+        use crate::soap::traits::xml::ToXml;
+
+        #[test]
+        fn ser_test() {
+            let ser = Store2ResponseSoapEnvelope::new(3).to_xml().unwrap();
+
+            assert!(ser.contains("Upper=\"3\""));
+            assert!(ser.contains("Lower=\"3\""));
+            assert!(ser.contains("Store2Response"));
+        }
+    }
+
+    #[derive(Debug, Default, YaSerialize, YaDeserialize, Clone)]
+    #[yaserde(
+    rename = "AcknowledgmentRange",
+    namespace = "nsi2: http://schemas.xmlsoap.org/ws/2003/03/rm",
+    prefix = "nsi2"
+    )]
+    pub struct AcknowledgmentRange {
+        #[yaserde(attribute, rename = "Upper")]
+        pub upper: u32,
+        #[yaserde(attribute, rename = "Lower")]
+        pub lower: u32,
+    }
+
+    #[derive(Debug, Default, YaSerialize, YaDeserialize, Clone)]
+    #[yaserde(
+    rename = "SequenceAcknowledgment",
+    namespace = "nsi2: http://schemas.xmlsoap.org/ws/2003/03/rm",
+    namespace = "nsi3: http://schemas.xmlsoap.org/ws/2002/07/utility",
+    prefix = "nsi2"
+    )]
+    pub struct SequenceAcknowledgment {
+        #[yaserde(rename = "Identifier", prefix = "nsi3")]
+        pub identifier: String,
+        #[yaserde(rename = "AcknowledgmentRange", prefix = "nsi2")]
+        pub acknowledgment_range: AcknowledgmentRange,
+    }
+
+    #[derive(Debug, Default, YaSerialize, YaDeserialize, Clone)]
+    #[yaserde(
+    rename = "Header",
+    namespace = "soapenv: http://schemas.xmlsoap.org/soap/envelope/",
+    prefix = "soapenv"
+    )]
+    pub struct Store2ResponseHeader {
+        #[yaserde(rename = "SequenceAcknowledgment", prefix = "nsi2")]
+        pub sequence_acknowledgment: SequenceAcknowledgment,
+    }
+
+    #[derive(Debug, Default, YaSerialize, YaDeserialize)]
+    pub struct SoapStore2ResponseMessage {
+        #[yaserde(rename = "Store2Response", default)]
+        pub body: Store2ResponseType
+    }
+
+    #[derive(Debug, Default, YaSerialize, YaDeserialize, Clone)]
+    #[yaserde(
+    rename = "Store2Response",
+    namespace = "nsi1: http://messenger.msn.com/ws/2004/09/oim/",
+    default_namespace = "nsi1"
+    )]
+    pub struct Store2ResponseType {}
+
+    #[derive(Debug, Default, YaSerialize, YaDeserialize)]
+    #[yaserde(
+    rename = "Envelope",
+    namespace = "soapenv: http://schemas.xmlsoap.org/soap/envelope/",
+    namespace = "xsi: http://www.w3.org/2001/XMLSchema-instance",
+    namespace = "xsd: http://www.w3.org/2001/XMLSchema",
+    prefix = "soapenv"
+    )]
+    pub struct Store2ResponseSoapEnvelope {
+        #[yaserde(rename = "Header", prefix = "soapenv")]
+        pub header: Option<Store2ResponseHeader>,
+        #[yaserde(rename = "Body", prefix = "soapenv")]
+        pub body: SoapStore2ResponseMessage,
+    }
+
+    impl ToXml for Store2ResponseSoapEnvelope {
+        type Error = SoapMarshallError;
+
+        fn to_xml(&self) -> Result<String, Self::Error>  {
+            to_string(self).map_err(|e| SoapMarshallError::SerializationError { message: e})
+        }
+    }
+
+    impl Store2ResponseSoapEnvelope {
+        /* Acknowledges the MessageNumber of the request */
+        pub fn new(message_number: u32) -> Self {
+            Store2ResponseSoapEnvelope {
+                header: Some(Store2ResponseHeader {
+                    sequence_acknowledgment: SequenceAcknowledgment {
+                        identifier: "http://messenger.msn.com".to_string(),
+                        acknowledgment_range: AcknowledgmentRange { upper: message_number, lower: message_number },
+                    },
+                }),
+                body: SoapStore2ResponseMessage {
+                    body: Store2ResponseType {},
+                },
+            }
+        }
+    }
+}
@@ -32,7 +32,7 @@ async fn main() {
 
     let (master_kill_signal,  kill_recv) = broadcast::channel::<()>(1);
 
-    let client_store_facade = ClientStoreFacade::default();
+    let client_store_facade = ClientStoreFacade::new(&config);
 
     let notification_server = NotificationServer::listen("127.0.0.1", 1863, kill_recv.resubscribe(), client_store_facade.clone());
     let switchboard_server = SwitchboardServer::listen("127.0.0.1", 1864, kill_recv.resubscribe(), client_store_facade.clone());
@@ -0,0 +1,106 @@
+use std::str::FromStr;
+
+use log::{debug, warn};
+use matrix_sdk::{Client, Room, RoomMemberships};
+use matrix_sdk::ruma::{OwnedUserId, RoomId, UserId};
+use matrix_sdk::ruma::events::room::member::{MembershipChange, SyncRoomMemberEvent};
+
+use msnp::msnp::notification::command::nfy::{NfyOperation, NfyServer};
+use msnp::shared::models::email_address::EmailAddress;
+use msnp::shared::models::network_id::NetworkId;
+use msnp::shared::models::network_id_email::NetworkIdEmail;
+use msnp::shared::models::uuid::Uuid;
+use msnp::shared::payload::nfy::nfy_put_payload::RawNfyPayload;
+
+use crate::notification::client_store::ClientData;
+use crate::shared::identifiers::MatrixIdCompatible;
+
+/* Same passport as the circle contact served by ABFindContactsPaged */
+pub fn get_circle_address(room_id: &RoomId) -> NetworkIdEmail {
+    let circle_id = Uuid::from_seed(room_id.as_str());
+    NetworkIdEmail::new(NetworkId::Circle, EmailAddress::from_str(&format!("{}@hotmail.com", circle_id)).expect("Circle address to be valid"))
+}
+
+pub fn find_circle_room(client: &Client, circle: &EmailAddress) -> Option<Room> {
+    let (circle_id, _domain) = circle.crack();
+    let circle_id = Uuid::from_str(circle_id).ok()?;
+
+    client.joined_rooms().into_iter().find(|room| Uuid::from_seed(room.room_id().as_str()) == circle_id)
+}
+
+fn get_roster_address(user_id: &UserId) -> NetworkIdEmail {
+    NetworkIdEmail::new(NetworkId::WindowsLive, EmailAddress::from_user_id(user_id))
+}
+
+/* The client joins the circle conversation with a PUT, the whole roster is sent back */
+pub async fn join_circle_conversation(payload: &RawNfyPayload, client_data: &ClientData) -> Result<Option<NfyServer>, anyhow::Error> {
+    let client = client_data.get_matrix_client();
+
+    let Some(room) = find_circle_room(&client, &payload.envelope.to.email) else {
+        warn!("MSNP|NS|CIRCLES: No room found for circle: {}", &payload.envelope.to);
+        return Ok(None);
+    };
+
+    let roster: Vec<OwnedUserId> = room.members_no_sync(RoomMemberships::JOIN).await?.iter().map(|member| member.user_id().to_owned()).collect();
+    let roster_addresses: Vec<NetworkIdEmail> = roster.iter().map(|user_id| get_roster_address(user_id)).collect();
+
+    debug!("MSNP|NS|CIRCLES: Joined circle conversation {} with {} members", room.room_id(), roster.len());
+    client_data.inner.circle_store.join(&Uuid::from_seed(room.room_id().as_str()), roster);
+
+    let me = client_data.get_user()?.get_network_id_email();
+    Ok(Some(NfyServer {
+        operation: NfyOperation::Put,
+        payload: RawNfyPayload::new_circle_roster(get_circle_address(room.room_id()), me, &roster_addresses)
+    }))
+}
+
+/* Joins are pushed as a partial roster PUT, leaves as a DEL of the roster user */
+pub fn get_circle_roster_updates(event: &SyncRoomMemberEvent, room: &Room, me: &UserId, client_data: &ClientData) -> Result<Vec<NfyServer>, anyhow::Error> {
+    let SyncRoomMemberEvent::Original(og_rm_event) = event else {
+        return Ok(Vec::new());
+    };
+
+    let circle_store = &client_data.inner.circle_store;
+    let circle_id = Uuid::from_seed(room.room_id().as_str());
+
+    if !circle_store.is_joined(&circle_id) {
+        return Ok(Vec::new());
+    }
+
+    let membership_change = og_rm_event.membership_change();
+    let has_left = matches!(membership_change, MembershipChange::Left | MembershipChange::Banned | MembershipChange::Kicked | MembershipChange::KickedAndBanned);
+
+    if &og_rm_event.state_key == me {
+        if has_left {
+            debug!("SYNC|CIRCLES: Left circle conversation {}", room.room_id());
+            circle_store.leave(&circle_id);
+        }
+        return Ok(Vec::new());
+    }
+
+    let circle = get_circle_address(room.room_id());
+    let me_addr = client_data.get_user()?.get_network_id_email();
+
+    match membership_change {
+        MembershipChange::Joined | MembershipChange::InvitationAccepted | MembershipChange::KnockAccepted => {
+            let joined: Vec<NetworkIdEmail> = circle_store.add_to_roster(&circle_id, vec![og_rm_event.state_key.clone()]).iter().map(|user_id| get_roster_address(user_id)).collect();
+            if joined.is_empty() {
+                return Ok(Vec::new());
+            }
+
+            debug!("SYNC|CIRCLES: {} joined circle conversation {}", &og_rm_event.state_key, room.room_id());
+            Ok(vec![NfyServer { operation: NfyOperation::Put, payload: RawNfyPayload::new_circle_roster_joined(circle, me_addr, &joined) }])
+        },
+        _ if has_left => {
+            let left = circle_store.remove_from_roster(&circle_id, vec![og_rm_event.state_key.clone()]);
+
+            Ok(left.iter().map(|user_id| {
+                debug!("SYNC|CIRCLES: {} left circle conversation {}", user_id, room.room_id());
+                NfyServer { operation: NfyOperation::Del, payload: RawNfyPayload::new_circle_roster_left(circle.clone(), me_addr.clone(), &get_roster_address(user_id)) }
+            }).collect())
+        },
+        _ => {
+            Ok(Vec::new())
+        }
+    }
+}
@@ -2,6 +2,7 @@ use std::collections::HashSet;
 
 use log::{debug, error, info, warn};
 use matrix_sdk::{Client, Error, Room, RoomMemberships, StateStoreExt};
+use matrix_sdk::ruma::events::room::member::MembershipState;
 use matrix_sdk::deserialized_responses::AnySyncOrStrippedState::Sync;
 use matrix_sdk::deserialized_responses::{AnySyncOrStrippedState, RawMemberEvent, SyncOrStrippedState};
 use matrix_sdk::ruma::{OwnedRoomId, OwnedUserId, RoomId, UserId};
@@ -231,6 +232,111 @@ fn try_fetch_in_direct_targets(direct_targets: &HashSet<OwnedUserId>, me: &UserI
 }
 
 
+/* Reuses the m.direct room or a pending invite from the target if there's one, the SDK creates new DM rooms encrypted, with is_direct and m.direct set */
+pub async fn get_or_create_dm_room(client: &Client, target: &UserId) -> Result<Room, matrix_sdk::Error> {
+    match client.get_dm_room(target) {
+        None => {
+            if let Some(invited_room) = get_pending_dm_invites(client, target).await?.into_iter().next() {
+                accept_dm_invite(&invited_room).await?;
+                return Ok(invited_room);
+            }
+
+            info!("MATRIX|DIRECTS: Creating DM room with: {}", target);
+            client.create_dm(target).await
+        }
+        Some(room) => {
+            let membership = room.get_member_no_sync(target).await?.map(|member| member.membership().clone());
+            match membership {
+                Some(MembershipState::Join) | Some(MembershipState::Invite) => {},
+                _ => {
+                    info!("MATRIX|DIRECTS: Inviting {} back to DM room: {}", target, room.room_id());
+                    room.invite_user_by_id(target).await?;
+                }
+            }
+            Ok(room)
+        }
+    }
+}
+
+
+/* Leaving doesn't drop the room from m.direct, the contact would come back at the next fullsync */
+pub async fn leave_dm_room(room: &Room, forget: bool) -> Result<(), matrix_sdk::Error> {
+    room.set_is_direct(false).await?;
+    room.leave().await?;
+    info!("MATRIX|DIRECTS: Left DM room: {}", room.room_id());
+
+    if forget {
+        room.forget().await?;
+        info!("MATRIX|DIRECTS: Forgot DM room: {}", room.room_id());
+    }
+    Ok(())
+}
+
+/* A contact can be deleted first and blocked afterwards, the rooms we left with them are forgotten then */
+pub async fn forget_left_dm_rooms(client: &Client, target: &UserId) -> Result<(), matrix_sdk::Error> {
+    let me = client.user_id().expect("A user to be logged in when forgetting DM rooms");
+
+    for left_room in client.left_rooms() {
+        let members = left_room.members_no_sync(RoomMemberships::all()).await?;
+        let is_one_on_one_with_target = members.iter().any(|member| member.user_id() == target)
+            && members.iter().all(|member| member.user_id() == target || member.user_id() == me);
+
+        if is_one_on_one_with_target {
+            left_room.forget().await?;
+            info!("MATRIX|DIRECTS: Forgot left DM room: {} with: {}", left_room.room_id(), target);
+        }
+    }
+    Ok(())
+}
+
+/* DM invites the target sent us that we haven't answered yet */
+pub async fn get_pending_dm_invites(client: &Client, inviter: &UserId) -> Result<Vec<Room>, matrix_sdk::Error> {
+    let me = client.user_id().expect("A user to be logged in when looking for DM invites");
+    let mut out = Vec::new();
+
+    for invited_room in client.invited_rooms() {
+        let invite = invited_room.invite_details().await?;
+        let is_from_inviter = invite.inviter.as_ref().is_some_and(|member| member.user_id() == inviter);
+
+        if is_from_inviter && is_direct_invite(&invited_room, me).await? {
+            out.push(invited_room);
+        }
+    }
+    Ok(out)
+}
+
+/* Our member event in an invited room, the inviter puts is_direct and the invite message on it */
+pub async fn get_own_invite_content(room: &Room, me: &UserId) -> Result<Option<RoomMemberEventContent>, matrix_sdk::Error> {
+    let Some(raw_event) = room.get_state_event_static_for_key::<RoomMemberEventContent, _>(me).await? else {
+        return Ok(None);
+    };
+
+    match raw_event.deserialize()? {
+        SyncOrStrippedState::Stripped(event) => Ok(Some(event.content)),
+        SyncOrStrippedState::Sync(SyncStateEvent::Original(event)) => Ok(Some(event.content)),
+        SyncOrStrippedState::Sync(SyncStateEvent::Redacted(_)) => Ok(None)
+    }
+}
+
+/* Invites to group rooms can be small too, only is_direct tells a DM invite apart */
+pub async fn is_direct_invite(room: &Room, me: &UserId) -> Result<bool, matrix_sdk::Error> {
+    let content = get_own_invite_content(room, me).await?;
+    Ok(content.is_some_and(|content| content.membership == MembershipState::Invite && content.is_direct.unwrap_or(false)))
+}
+
+pub async fn accept_dm_invite(room: &Room) -> Result<(), matrix_sdk::Error> {
+    room.join().await?;
+    room.set_is_direct(true).await?;
+    info!("MATRIX|DIRECTS: Accepted DM invite: {}", room.room_id());
+    Ok(())
+}
+
+pub async fn reject_dm_invite(room: &Room) -> Result<(), matrix_sdk::Error> {
+    room.leave().await?;
+    info!("MATRIX|DIRECTS: Rejected DM invite: {}", room.room_id());
+    Ok(())
+}
+
 pub async fn force_update_rooms_with_fresh_m_direct(client: &Client) -> Result<(), matrix_sdk::Error> {
     if let Some(raw_content) = client.account().fetch_account_data(GlobalAccountDataEventType::Direct).await? {
         let mut e = raw_content.deserialize_as::<DirectEventContent>()?;
@@ -0,0 +1,278 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use anyhow::anyhow;
+use lazy_static::lazy_static;
+use log::{debug, warn};
+use matrix_sdk::{Client, Room};
+use matrix_sdk::crypto::vodozemac::base64_encode;
+use matrix_sdk::deserialized_responses::SyncOrStrippedState;
+use matrix_sdk::media::{MediaFormat, MediaRequest};
+use matrix_sdk::ruma::{MxcUri, OwnedMxcUri};
+use matrix_sdk::ruma::events::{GlobalAccountDataEventType, SyncStateEvent};
+use matrix_sdk::ruma::events::macros::EventContent;
+use matrix_sdk::ruma::events::room::{ImageInfo, MediaSource};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use msnp::shared::models::email_address::EmailAddress;
+use msnp::shared::models::msn_object::{FriendlyName, MSNObjectFactory, MsnObject};
+
+use crate::notification::emoticon_store::EmoticonStore;
+
+lazy_static! {
+    static ref IMG_TAG_REGEX: Regex = Regex::new(r"<img\s[^>]*>").unwrap();
+    static ref SRC_ATTR_REGEX: Regex = Regex::new(r#"src="([^"]*)""#).unwrap();
+    static ref ALT_ATTR_REGEX: Regex = Regex::new(r#"alt="([^"]*)""#).unwrap();
+    static ref TITLE_ATTR_REGEX: Regex = Regex::new(r#"title="([^"]*)""#).unwrap();
+}
+
+/* MSC2545 image packs: https://github.com/matrix-org/matrix-spec-proposals/pull/2545 */
+pub const USER_EMOTES_EVENT_TYPE: &str = "im.ponies.user_emotes";
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PackUsage {
+    Emoticon,
+    Sticker
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct PackInfo {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub display_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub avatar_url: Option<OwnedMxcUri>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub usage: Vec<PackUsage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attribution: Option<String>
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct PackImage {
+    pub url: OwnedMxcUri,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub body: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub info: Option<ImageInfo>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub usage: Vec<PackUsage>
+}
+
+impl PackImage {
+    /* Images without usage inherit the pack's usage, a pack without usage is both emoticons & stickers */
+    pub fn is_emoticon(&self, pack: Option<&PackInfo>) -> bool {
+        let usage = if !self.usage.is_empty() { &self.usage } else { pack.map(|p| &p.usage).unwrap_or(&self.usage) };
+        return usage.is_empty() || usage.contains(&PackUsage::Emoticon);
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, EventContent)]
+#[ruma_event(type = "im.ponies.user_emotes", kind = GlobalAccountData)]
+pub struct UserEmotesEventContent {
+    #[serde(default)]
+    pub images: BTreeMap<String, PackImage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pack: Option<PackInfo>
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, EventContent)]
+#[ruma_event(type = "im.ponies.room_emotes", kind = State, state_key_type = String)]
+pub struct RoomEmotesEventContent {
+    #[serde(default)]
+    pub images: BTreeMap<String, PackImage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pack: Option<PackInfo>
+}
+
+pub async fn fetch_user_emotes(client: &Client) -> Result<UserEmotesEventContent, anyhow::Error> {
+    match client.account().fetch_account_data(GlobalAccountDataEventType::from(USER_EMOTES_EVENT_TYPE)).await? {
+        None => Ok(UserEmotesEventContent::default()),
+        Some(raw_content) => Ok(raw_content.deserialize_as::<UserEmotesEventContent>()?)
+    }
+}
+
+async fn get_room_packs(room: &Room) -> Result<BTreeMap<String, RoomEmotesEventContent>, anyhow::Error> {
+    let mut out = BTreeMap::new();
+
+    for raw_event in room.get_state_events_static::<RoomEmotesEventContent>().await? {
+        match raw_event.deserialize() {
+            Ok(SyncOrStrippedState::Sync(SyncStateEvent::Original(event))) => {
+                out.insert(event.state_key, event.content);
+            },
+            Ok(_) => {},
+            Err(err) => {
+                warn!("EMOTICONS: Couldn't deserialize room pack in {}: {}", room.room_id(), err);
+            }
+        }
+    }
+
+    Ok(out)
+}
+
+/* Emoticons of the room's packs, keyed by shortcode. Those are the only packs every member can read:
+user packs and enabled emote rooms are private account data, so another user's personal emoticons
+can only be resolved from the inline images of their messages */
+pub async fn get_room_emoticons(room: &Room) -> Result<BTreeMap<String, PackImage>, anyhow::Error> {
+    let mut out = BTreeMap::new();
+
+    for pack in get_room_packs(room).await?.values() {
+        add_emoticons(&mut out, &pack.images, pack.pack.as_ref());
+    }
+
+    Ok(out)
+}
+
+fn add_emoticons(out: &mut BTreeMap<String, PackImage>, images: &BTreeMap<String, PackImage>, pack: Option<&PackInfo>) {
+    for (shortcode, image) in images {
+        if image.is_emoticon(pack) {
+            out.insert(shortcode.clone(), image.clone());
+        }
+    }
+}
+
+/* Matrix clients type :shortcode:, emoticons imported from WLM keep their original shortcut (e.g. "(dog)") */
+pub fn find_shortcuts_in_text(text: &str, emoticons: &BTreeMap<String, PackImage>) -> Vec<(String, PackImage)> {
+    let mut out = Vec::new();
+
+    for (shortcode, image) in emoticons {
+        let colon_shortcut = format!(":{}:", shortcode);
+        if text.contains(&colon_shortcut) {
+            out.push((colon_shortcut, image.clone()));
+        } else if !is_plain_word(shortcode) && text.contains(shortcode.as_str()) {
+            out.push((shortcode.clone(), image.clone()));
+        }
+    }
+
+    out
+}
+
+fn is_plain_word(shortcode: &str) -> bool {
+    shortcode.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+}
+
+/* Emoticons are offered with every message using them, the bytes are only downloaded once */
+pub async fn get_emoticon_bytes(client: &Client, emoticon_store: &EmoticonStore, emoticon_mxc: &MxcUri) -> Result<Arc<Vec<u8>>, anyhow::Error> {
+    if let Some(bytes) = emoticon_store.get_bytes(emoticon_mxc) {
+        return Ok(bytes);
+    }
+
+    let media_request = MediaRequest{ source: MediaSource::Plain(emoticon_mxc.to_owned()), format: MediaFormat::File };
+    let bytes = Arc::new(client.media().get_media_content(&media_request, true).await.map_err(|e| anyhow!(e))?);
+    emoticon_store.set_bytes(emoticon_mxc.to_owned(), bytes.clone());
+    Ok(bytes)
+}
+
+/* The location is the base64 encoded mxc so we can find the media back when the emoticon is requested over P2P */
+pub async fn emoticon_to_msn_object(client: &Client, emoticon_store: &EmoticonStore, creator: &EmailAddress, emoticon_mxc: &MxcUri) -> Result<MsnObject, anyhow::Error> {
+    let bytes = get_emoticon_bytes(client, emoticon_store, emoticon_mxc).await?;
+    let location = format!("{}.tmp", base64_encode(emoticon_mxc.to_string()));
+    Ok(MSNObjectFactory::get_custom_emoticon(&bytes, creator, location, FriendlyName::default()))
+}
+
+/* Inline emoticons in a formatted_body: <img data-mx-emoticon src="mxc://..." alt=":shortcode:">.
+The sender picked those from its own packs, the alt text is what ends up in the plain body */
+pub fn find_inline_emoticons(formatted_body: &str) -> Vec<(String, OwnedMxcUri)> {
+    let mut out = Vec::new();
+
+    for img_tag in IMG_TAG_REGEX.find_iter(formatted_body).map(|m| m.as_str()) {
+        if !img_tag.contains("data-mx-emoticon") {
+            continue;
+        }
+
+        let src = SRC_ATTR_REGEX.captures(img_tag).map(|c| c[1].to_string());
+        let shortcut = ALT_ATTR_REGEX.captures(img_tag).or_else(|| TITLE_ATTR_REGEX.captures(img_tag)).map(|c| unescape_html(&c[1]));
+
+        match (src, shortcut) {
+            (Some(src), Some(shortcut)) if src.starts_with("mxc://") && !shortcut.is_empty() => {
+                out.push((shortcut, OwnedMxcUri::from(src)));
+            },
+            _ => {
+                warn!("EMOTICONS: Ignoring malformed inline emoticon: {}", img_tag);
+            }
+        }
+    }
+
+    out
+}
+
+/* WLM shortcuts can contain any character, e.g. "<3" */
+pub fn to_inline_emoticon(shortcut: &str, emoticon_mxc: &MxcUri) -> String {
+    format!("<img data-mx-emoticon src=\"{mxc}\" alt=\"{shortcut}\" title=\"{shortcut}\" height=\"32\" />", mxc = emoticon_mxc, shortcut = escape_html(shortcut))
+}
+
+/* The body is walked once so a shortcut can't match inside an inserted <img> tag, the longest shortcut wins */
+pub fn render_inline_emoticons(text: &str, emoticons: &[(String, OwnedMxcUri)]) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut plain_start = 0;
+    let mut position = 0;
+
+    while position < text.len() {
+        let rest = &text[position..];
+        let matched = emoticons.iter()
+            .filter(|(shortcut, _)| !shortcut.is_empty() && rest.starts_with(shortcut.as_str()))
+            .max_by_key(|(shortcut, _)| shortcut.len());
+
+        match matched {
+            Some((shortcut, mxc)) => {
+                out.push_str(&escape_html(&text[plain_start..position]));
+                out.push_str(&to_inline_emoticon(shortcut, mxc));
+                position += shortcut.len();
+                plain_start = position;
+            },
+            None => {
+                position += rest.chars().next().map(|c| c.len_utf8()).unwrap_or(1);
+            }
+        }
+    }
+
+    out.push_str(&escape_html(&text[plain_start..]));
+    out
+}
+
+pub fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;").replace('\'', "&#39;")
+}
+
+fn unescape_html(text: &str) -> String {
+    text.replace("&lt;", "<").replace("&gt;", ">").replace("&quot;", "\"").replace("&#39;", "'").replace("&amp;", "&")
+}
+
+/* WLM emoticons are either PNG, GIF or JPEG */
+pub fn guess_image_mime(image: &[u8]) -> mime::Mime {
+    if image.starts_with(b"GIF8") {
+        mime::IMAGE_GIF
+    } else if image.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        mime::IMAGE_JPEG
+    } else {
+        mime::IMAGE_PNG
+    }
+}
+
+/* Stores a WLM custom emoticon in the user's pack so it roams with the Matrix account, returns its mxc */
+pub async fn add_to_user_emotes(client: &Client, shortcut: &str, image: &[u8], content_type: &mime::Mime) -> Result<OwnedMxcUri, anyhow::Error> {
+    let mut user_emotes = fetch_user_emotes(client).await?;
+
+    if let Some(existing) = user_emotes.images.get(shortcut) {
+        debug!("EMOTICONS: {} is already in the user pack", shortcut);
+        return Ok(existing.url.clone());
+    }
+
+    let response = client.media().upload(content_type, image.to_vec()).await?;
+
+    let mut info = ImageInfo::new();
+    info.mimetype = Some(content_type.to_string());
+    info.size = Some((image.len() as u32).into());
+
+    user_emotes.images.insert(shortcut.to_string(), PackImage {
+        url: response.content_uri.clone(),
+        body: Some(shortcut.to_string()),
+        info: Some(info),
+        usage: vec![PackUsage::Emoticon],
+    });
+
+    client.account().set_account_data(user_emotes).await?;
+
+    Ok(response.content_uri)
+}
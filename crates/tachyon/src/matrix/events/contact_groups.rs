@@ -0,0 +1,164 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use chrono::Utc;
+use matrix_sdk::Client;
+use matrix_sdk::ruma::events::GlobalAccountDataEventType;
+use matrix_sdk::ruma::events::macros::EventContent;
+use msnp::shared::models::uuid::Uuid;
+use msnp::soap::abch::msnab_datatypes::GroupType;
+use serde::{Deserialize, Serialize};
+
+use crate::notification::address_book_store::{format_last_change, TOMBSTONE_LIFETIME_SECS};
+
+pub const CONTACT_GROUPS_EVENT_TYPE: &str = "com.tachyon.contact.groups";
+
+/* The Favorites group WLM gets until it creates its own */
+pub const DEFAULT_FAVORITES_GROUP_ID: &str = "1ae28c79-c963-4fe6-8339-d72a0f7c8bd2";
+
+/* WLM contact groups, keyed by group id. Kept in account data so they roam with the Matrix account */
+#[derive(Clone, Debug, Default, Deserialize, Serialize, EventContent)]
+#[ruma_event(type = "com.tachyon.contact.groups", kind = GlobalAccountData)]
+pub struct ContactGroupsEventContent {
+    #[serde(default)]
+    pub groups: BTreeMap<String, ContactGroup>,
+    /* Group tombstones older than this were pruned, like AddressBookStore deltas from before it need a fullsync */
+    #[serde(default)]
+    pub oldest_change: i64
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ContactGroup {
+    pub name: String,
+    #[serde(default)]
+    pub is_favorite: bool,
+    /* Contacts email addresses */
+    #[serde(default)]
+    pub contacts: BTreeSet<String>,
+    #[serde(default)]
+    pub deleted: bool,
+    pub last_change: i64
+}
+
+impl ContactGroup {
+    fn new(name: &str, is_favorite: bool) -> Self {
+        Self { name: name.to_string(), is_favorite, contacts: BTreeSet::new(), deleted: false, last_change: Utc::now().timestamp() }
+    }
+
+    pub fn to_group_type(&self, group_id: &str) -> GroupType {
+        GroupType::new(group_id, &self.name, self.is_favorite, self.deleted, &format_last_change(self.last_change))
+    }
+}
+
+impl ContactGroupsEventContent {
+
+    pub fn find_by_name(&self, name: &str) -> Option<&str> {
+        self.groups.iter().find(|(_, group)| !group.deleted && group.name.eq_ignore_ascii_case(name)).map(|(group_id, _)| group_id.as_str())
+    }
+
+    pub fn get_favorites_group_id(&self) -> String {
+        self.groups.iter().find(|(_, group)| !group.deleted && group.is_favorite).map(|(group_id, _)| group_id.clone()).unwrap_or(DEFAULT_FAVORITES_GROUP_ID.to_string())
+    }
+
+    pub fn has_favorites_group(&self) -> bool {
+        self.groups.values().any(|group| !group.deleted && group.is_favorite)
+    }
+
+    pub fn can_serve_delta(&self, since: i64) -> bool {
+        since >= self.oldest_change
+    }
+
+    pub fn contains_group(&self, group_id: &str) -> bool {
+        self.groups.iter().any(|(id, group)| !group.deleted && id.eq_ignore_ascii_case(group_id))
+    }
+
+    pub fn add_group(&mut self, name: &str, is_favorite: bool) -> String {
+        let group_id = Uuid::new().to_string();
+        self.groups.insert(group_id.clone(), ContactGroup::new(name, is_favorite));
+        self.prune();
+        group_id
+    }
+
+    /* Keeps the default Favorites group id, the clients already have it in their cache */
+    pub fn add_favorites_group(&mut self, group_id: &str, name: &str) {
+        self.groups.insert(group_id.to_string(), ContactGroup::new(name, true));
+    }
+
+    pub fn rename_group(&mut self, group_id: &str, name: &str) -> bool {
+        let Some(group) = self.get_group_mut(group_id) else {
+            return false;
+        };
+
+        group.name = name.to_string();
+        group.last_change = Utc::now().timestamp();
+        true
+    }
+
+    /* Returns the contacts that were in the group, their groupIds changed too */
+    pub fn delete_group(&mut self, group_id: &str) -> Option<BTreeSet<String>> {
+        let group = self.get_group_mut(group_id)?;
+
+        group.deleted = true;
+        group.last_change = Utc::now().timestamp();
+        let contacts = std::mem::take(&mut group.contacts);
+        self.prune();
+        Some(contacts)
+    }
+
+    pub fn add_contact(&mut self, group_id: &str, email: &str) -> bool {
+        match self.get_group_mut(group_id) {
+            None => false,
+            Some(group) => {
+                group.contacts.insert(email.to_string());
+                true
+            }
+        }
+    }
+
+    pub fn remove_contact(&mut self, group_id: &str, email: &str) -> bool {
+        match self.get_group_mut(group_id) {
+            None => false,
+            Some(group) => group.contacts.remove(email)
+        }
+    }
+
+    pub fn get_group_ids_of(&self, email: &str) -> Vec<String> {
+        self.groups.iter().filter(|(_, group)| !group.deleted && group.contacts.contains(email)).map(|(group_id, _)| group_id.clone()).collect()
+    }
+
+    /* Same rules as the address book store: None returns live groups only, Some returns everything changed since */
+    pub fn get_groups(&self, since: Option<i64>) -> Vec<GroupType> {
+        self.groups.iter()
+            .filter(|(_, group)| match since {
+                None => !group.deleted,
+                Some(since) => group.last_change >= since
+            })
+            .map(|(group_id, group)| group.to_group_type(group_id))
+            .collect()
+    }
+
+    fn get_group_mut(&mut self, group_id: &str) -> Option<&mut ContactGroup> {
+        self.groups.iter_mut().find(|(id, group)| !group.deleted && id.eq_ignore_ascii_case(group_id)).map(|(_, group)| group)
+    }
+
+    fn prune(&mut self) {
+        let cutoff = Utc::now().timestamp() - TOMBSTONE_LIFETIME_SECS;
+        let before = self.groups.len();
+        self.groups.retain(|_, group| !group.deleted || group.last_change >= cutoff);
+
+        if self.groups.len() != before {
+            self.oldest_change = self.oldest_change.max(cutoff);
+        }
+    }
+}
+
+pub async fn fetch_contact_groups(client: &Client) -> Result<ContactGroupsEventContent, matrix_sdk::Error> {
+    match client.account().fetch_account_data(GlobalAccountDataEventType::from(CONTACT_GROUPS_EVENT_TYPE)).await? {
+        None => Ok(ContactGroupsEventContent::default()),
+        Some(raw_content) => Ok(raw_content.deserialize_as::<ContactGroupsEventContent>()?)
+    }
+}
+
+pub async fn save_contact_groups(client: &Client, contact_groups: ContactGroupsEventContent) -> Result<(), matrix_sdk::Error> {
+    client.account().set_account_data(contact_groups).await?;
+    Ok(())
+}
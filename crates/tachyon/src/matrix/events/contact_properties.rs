@@ -0,0 +1,126 @@
+use std::collections::BTreeMap;
+
+use matrix_sdk::Client;
+use matrix_sdk::ruma::events::GlobalAccountDataEventType;
+use matrix_sdk::ruma::events::macros::EventContent;
+use msnp::soap::abch::msnab_datatypes::{Annotation, ArrayOfAnnotation, ArrayOfcontactPhoneType, ContactInfoType, ContactPhoneType};
+use serde::{Deserialize, Serialize};
+
+pub const CONTACT_PROPERTIES_EVENT_TYPE: &str = "com.tachyon.contact.properties";
+
+/* What WLM lets the user edit on a contact, keyed by contact email address */
+#[derive(Clone, Debug, Default, Deserialize, Serialize, EventContent)]
+#[ruma_event(type = "com.tachyon.contact.properties", kind = GlobalAccountData)]
+pub struct ContactPropertiesEventContent {
+    #[serde(default)]
+    pub contacts: BTreeMap<String, ContactProperties>
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct ContactProperties {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nickname: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+    /* contactPhoneType -> number */
+    #[serde(default)]
+    pub phones: BTreeMap<String, String>,
+    /* Annotation name -> value */
+    #[serde(default)]
+    pub annotations: BTreeMap<String, String>
+}
+
+impl ContactProperties {
+
+    pub fn is_empty(&self) -> bool {
+        self.nickname.is_none() && self.comment.is_none() && self.phones.is_empty() && self.annotations.is_empty()
+    }
+
+    /* Only the properties listed in propertiesChanged are taken from the update */
+    pub fn update(&mut self, contact_info: &ContactInfoType, properties_changed: &str) {
+        for property in properties_changed.split_whitespace() {
+            match property {
+                "ContactQuickName" => {
+                    self.nickname = contact_info.quick_name.clone().filter(|nickname| !nickname.is_empty());
+                },
+                "Comment" => {
+                    self.comment = contact_info.comment.clone().filter(|comment| !comment.is_empty());
+                },
+                "ContactPhone" => {
+                    for phone in contact_info.phones.iter().flat_map(|phones| phones.contact_phone.iter()) {
+                        if phone.number.is_empty() {
+                            self.phones.remove(&phone.contact_phone_type);
+                        } else {
+                            self.phones.insert(phone.contact_phone_type.clone(), phone.number.clone());
+                        }
+                    }
+                },
+                "Annotation" => {
+                    for annotation in contact_info.annotations.iter().flat_map(|annotations| annotations.annotation.iter()) {
+                        match annotation.value.as_ref().filter(|value| !value.is_empty()) {
+                            None => {
+                                self.annotations.remove(&annotation.name);
+                            },
+                            Some(value) => {
+                                self.annotations.insert(annotation.name.clone(), value.clone());
+                            }
+                        }
+                    }
+                },
+                _ => {}
+            }
+        }
+    }
+
+    pub fn apply_to(&self, contact_info: &mut ContactInfoType) {
+        if let Some(nickname) = self.nickname.as_ref() {
+            contact_info.quick_name = Some(nickname.clone());
+        }
+
+        if let Some(comment) = self.comment.as_ref() {
+            contact_info.comment = Some(comment.clone());
+        }
+
+        if !self.phones.is_empty() {
+            let contact_phone = self.phones.iter().map(|(phone_type, number)| ContactPhoneType{ contact_phone_type: phone_type.clone(), number: number.clone(), is_messenger_enabled: false, properties_changed: String::new() }).collect();
+            contact_info.phones = Some(ArrayOfcontactPhoneType{ contact_phone });
+        }
+
+        if !self.annotations.is_empty() {
+            let annotation = self.annotations.iter().map(|(name, value)| Annotation{ name: name.clone(), value: Some(value.clone()) }).collect();
+            contact_info.annotations = Some(ArrayOfAnnotation{ annotation });
+        }
+    }
+}
+
+impl ContactPropertiesEventContent {
+
+    pub fn get(&self, email: &str) -> Option<&ContactProperties> {
+        self.contacts.get(email)
+    }
+
+    pub fn update(&mut self, email: &str, contact_info: &ContactInfoType, properties_changed: &str) {
+        let properties = self.contacts.entry(email.to_string()).or_default();
+        properties.update(contact_info, properties_changed);
+
+        if properties.is_empty() {
+            self.contacts.remove(email);
+        }
+    }
+
+    pub fn get_nicknames(&self) -> impl Iterator<Item = (&String, &String)> {
+        self.contacts.iter().filter_map(|(email, properties)| properties.nickname.as_ref().map(|nickname| (email, nickname)))
+    }
+}
+
+pub async fn fetch_contact_properties(client: &Client) -> Result<ContactPropertiesEventContent, matrix_sdk::Error> {
+    match client.account().fetch_account_data(GlobalAccountDataEventType::from(CONTACT_PROPERTIES_EVENT_TYPE)).await? {
+        None => Ok(ContactPropertiesEventContent::default()),
+        Some(raw_content) => Ok(raw_content.deserialize_as::<ContactPropertiesEventContent>()?)
+    }
+}
+
+pub async fn save_contact_properties(client: &Client, contact_properties: ContactPropertiesEventContent) -> Result<(), matrix_sdk::Error> {
+    client.account().set_account_data(contact_properties).await?;
+    Ok(())
+}
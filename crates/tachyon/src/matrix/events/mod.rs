@@ -1,2 +1,6 @@
+pub mod contact_groups;
+pub mod contact_properties;
+pub mod privacy_settings;
 pub mod room_mappings;
-pub mod room_roster;
\ No newline at end of file
+pub mod room_roster;
+pub mod wink_sticker;
@@ -0,0 +1,27 @@
+use matrix_sdk::Client;
+use matrix_sdk::ruma::events::GlobalAccountDataEventType;
+use matrix_sdk::ruma::events::macros::EventContent;
+use serde::{Deserialize, Serialize};
+
+pub const PRIVACY_SETTINGS_EVENT_TYPE: &str = "com.tachyon.privacy";
+
+/* WLM's BLP mode, kept in account data so every Tachyon device enforces the same one */
+#[derive(Clone, Debug, Default, Deserialize, Serialize, EventContent)]
+#[ruma_event(type = "com.tachyon.privacy", kind = GlobalAccountData)]
+pub struct PrivacySettingsEventContent {
+    /* BLP BL: only contacts on the Allow list can reach us */
+    #[serde(default)]
+    pub allow_list_only: bool
+}
+
+pub async fn fetch_privacy_settings(client: &Client) -> Result<PrivacySettingsEventContent, matrix_sdk::Error> {
+    match client.account().fetch_account_data(GlobalAccountDataEventType::from(PRIVACY_SETTINGS_EVENT_TYPE)).await? {
+        None => Ok(PrivacySettingsEventContent::default()),
+        Some(raw_content) => Ok(raw_content.deserialize_as::<PrivacySettingsEventContent>()?)
+    }
+}
+
+pub async fn save_privacy_settings(client: &Client, privacy_settings: PrivacySettingsEventContent) -> Result<(), matrix_sdk::Error> {
+    client.account().set_account_data(privacy_settings).await?;
+    Ok(())
+}
@@ -0,0 +1,29 @@
+use matrix_sdk::ruma::events::macros::EventContent;
+use matrix_sdk::ruma::events::room::ImageInfo;
+use matrix_sdk::ruma::OwnedMxcUri;
+use serde::{Deserialize, Serialize};
+
+/* A regular m.sticker showing the wink thumbnail, with the original cabinet attached so Tachyon can replay the wink */
+#[derive(Clone, Debug, Deserialize, Serialize, EventContent)]
+#[ruma_event(type = "m.sticker", kind = MessageLike)]
+pub struct WinkStickerEventContent {
+    pub body: String,
+    pub info: ImageInfo,
+    pub url: OwnedMxcUri,
+    #[serde(rename = "com.tachyon.wink", skip_serializing_if = "Option::is_none")]
+    pub wink: Option<WinkAttachment>
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct WinkAttachment {
+    pub url: OwnedMxcUri,
+    pub mimetype: String,
+    pub size: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub contentid: Option<String>,
+    /* Signature of the cabinet by the MSN Content Authority, WLM won't play a wink without it */
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stamp: Option<String>
+}
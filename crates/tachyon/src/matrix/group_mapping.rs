@@ -0,0 +1,222 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use log::{debug, info, warn};
+use matrix_sdk::{Client, Room};
+use matrix_sdk::deserialized_responses::SyncOrStrippedState;
+use matrix_sdk::ruma::{OwnedRoomId, RoomId, UserId};
+use matrix_sdk::ruma::events::{StateEventType, SyncStateEvent};
+use matrix_sdk::ruma::events::space::child::SpaceChildEventContent;
+use msnp::shared::models::email_address::EmailAddress;
+use msnp::shared::models::uuid::Uuid;
+use msnp::soap::abch::msnab_datatypes::GroupType;
+
+use crate::matrix::events::contact_groups::{ContactGroupsEventContent, DEFAULT_FAVORITES_GROUP_ID};
+use crate::notification::address_book_store::{format_last_change, AddressBookStore, StoredSpaceGroup};
+use crate::shared::identifiers::MatrixIdCompatible;
+
+/* Where the members of a WLM contact group are kept on the Matrix side */
+pub enum MappedGroup {
+    /* m.favourite tags of the DM rooms */
+    Favorites,
+    /* m.space.child links of a Space to the DM rooms */
+    Space(Room),
+    /* Our contact groups account data */
+    Stored,
+    Unknown
+}
+
+/* The contact groups as WLM sees them, with the groups of each contact */
+#[derive(Default)]
+pub struct GroupsSnapshot {
+    pub groups: Vec<GroupType>,
+    contact_group_ids: HashMap<String, Vec<String>>
+}
+
+impl GroupsSnapshot {
+    pub fn get_group_ids_of(&self, email: &str) -> Vec<String> {
+        self.contact_group_ids.get(email).cloned().unwrap_or_default()
+    }
+
+    fn add_contact(&mut self, email: &str, group_id: &str) {
+        self.contact_group_ids.entry(email.to_string()).or_default().push(group_id.to_string());
+    }
+}
+
+pub fn get_space_group_id(space_id: &RoomId) -> String {
+    Uuid::from_seed(space_id.as_str()).to_string()
+}
+
+/* Spaces and tags are reconciled with the address book store first, so deltas get what changed from other Matrix clients too */
+pub async fn get_groups_snapshot(client: &Client, contact_groups: &ContactGroupsEventContent, address_book_store: &AddressBookStore, spaces_as_groups: bool, since: Option<i64>) -> Result<GroupsSnapshot, matrix_sdk::Error> {
+    let me = client.user_id().expect("A user to be logged in when mapping contact groups");
+    let mut snapshot = GroupsSnapshot::default();
+
+    snapshot.groups = contact_groups.get_groups(since);
+    for (group_id, group) in contact_groups.groups.iter().filter(|(_, group)| !group.deleted && !group.is_favorite) {
+        for email in &group.contacts {
+            snapshot.add_contact(email, group_id);
+        }
+    }
+
+    if !contact_groups.has_favorites_group() && since.is_none() {
+        snapshot.groups.push(GroupType::new(DEFAULT_FAVORITES_GROUP_ID, "Favorites", true, false, &format_last_change(0)));
+    }
+
+    let favorites_group_id = contact_groups.get_favorites_group_id();
+    for room in client.joined_rooms().iter().filter(|room| room.is_favourite()) {
+        if let Some(email) = get_direct_target_email(room, me) {
+            snapshot.add_contact(&email, &favorites_group_id);
+        }
+    }
+
+    let mut space_groups = Vec::new();
+    if spaces_as_groups {
+        for space in client.joined_rooms().iter().filter(|room| room.is_space()) {
+            let group_id = get_space_group_id(space.room_id());
+            space_groups.push(StoredSpaceGroup::new(space.room_id().as_str(), &space.compute_display_name().await?.to_string()));
+
+            for child_id in get_space_children(space).await? {
+                let Some(child) = client.get_room(&child_id) else {
+                    continue;
+                };
+
+                if let Some(email) = get_direct_target_email(&child, me) {
+                    snapshot.add_contact(&email, &group_id);
+                }
+            }
+        }
+    }
+
+    /* Without spacesAsGroups, the Space groups WLM still has get tombstoned */
+    address_book_store.reconcile_groups(space_groups, &snapshot.contact_group_ids);
+    snapshot.groups.extend(address_book_store.get_space_groups(since).iter().map(|group| group.to_group_type()));
+
+    Ok(snapshot)
+}
+
+pub fn resolve_group(client: &Client, contact_groups: &ContactGroupsEventContent, spaces_as_groups: bool, group_id: &str) -> MappedGroup {
+    if contact_groups.get_favorites_group_id().eq_ignore_ascii_case(group_id) {
+        return MappedGroup::Favorites;
+    }
+
+    if contact_groups.contains_group(group_id) {
+        return MappedGroup::Stored;
+    }
+
+    if spaces_as_groups {
+        let space = client.joined_rooms().into_iter().find(|room| room.is_space() && get_space_group_id(room.room_id()).eq_ignore_ascii_case(group_id));
+        if let Some(space) = space {
+            return MappedGroup::Space(space);
+        }
+    }
+
+    MappedGroup::Unknown
+}
+
+/* Returns false when the contact couldn't be put in the group */
+pub async fn add_contact_to_group(client: &Client, contact_groups: &mut ContactGroupsEventContent, spaces_as_groups: bool, group_id: &str, email: &str) -> Result<bool, matrix_sdk::Error> {
+    match resolve_group(client, contact_groups, spaces_as_groups, group_id) {
+        MappedGroup::Favorites => {
+            let Some(dm_room) = get_dm_room_of(client, email) else {
+                return Ok(false);
+            };
+
+            dm_room.set_is_favourite(true, None).await?;
+            info!("MATRIX|GROUPS: Tagged DM room {} as favourite", dm_room.room_id());
+            Ok(true)
+        },
+        MappedGroup::Space(space) => {
+            let Some(dm_room) = get_dm_room_of(client, email) else {
+                return Ok(false);
+            };
+
+            let me = client.user_id().expect("A user to be logged in when editing a Space");
+            if !space.can_user_send_state(me, StateEventType::SpaceChild).await? {
+                warn!("MATRIX|GROUPS: Not allowed to add rooms to Space: {}", space.room_id());
+                return Ok(false);
+            }
+
+            space.send_state_event_for_key(&dm_room.room_id().to_owned(), SpaceChildEventContent::new(vec![me.server_name().to_owned()])).await?;
+            info!("MATRIX|GROUPS: Added DM room {} to Space: {}", dm_room.room_id(), space.room_id());
+            Ok(true)
+        },
+        MappedGroup::Stored => {
+            Ok(contact_groups.add_contact(group_id, email))
+        },
+        MappedGroup::Unknown => {
+            Ok(false)
+        }
+    }
+}
+
+pub async fn remove_contact_from_group(client: &Client, contact_groups: &mut ContactGroupsEventContent, spaces_as_groups: bool, group_id: &str, email: &str) -> Result<bool, matrix_sdk::Error> {
+    match resolve_group(client, contact_groups, spaces_as_groups, group_id) {
+        MappedGroup::Favorites => {
+            let Some(dm_room) = get_dm_room_of(client, email) else {
+                return Ok(false);
+            };
+
+            dm_room.set_is_favourite(false, None).await?;
+            info!("MATRIX|GROUPS: Untagged DM room {} as favourite", dm_room.room_id());
+            Ok(true)
+        },
+        MappedGroup::Space(space) => {
+            let Some(dm_room) = get_dm_room_of(client, email) else {
+                return Ok(false);
+            };
+
+            let me = client.user_id().expect("A user to be logged in when editing a Space");
+            if !space.can_user_send_state(me, StateEventType::SpaceChild).await? {
+                warn!("MATRIX|GROUPS: Not allowed to remove rooms from Space: {}", space.room_id());
+                return Ok(false);
+            }
+
+            /* An empty m.space.child content removes the link */
+            space.send_state_event_raw("m.space.child", dm_room.room_id().as_str(), serde_json::json!({})).await?;
+            info!("MATRIX|GROUPS: Removed DM room {} from Space: {}", dm_room.room_id(), space.room_id());
+            Ok(true)
+        },
+        MappedGroup::Stored => {
+            Ok(contact_groups.remove_contact(group_id, email))
+        },
+        MappedGroup::Unknown => {
+            Ok(false)
+        }
+    }
+}
+
+async fn get_space_children(space: &Room) -> Result<Vec<OwnedRoomId>, matrix_sdk::Error> {
+    let mut out = Vec::new();
+
+    for raw_event in space.get_state_events_static::<SpaceChildEventContent>().await? {
+        match raw_event.deserialize() {
+            Ok(SyncOrStrippedState::Sync(SyncStateEvent::Original(event))) => {
+                if !event.content.via.is_empty() {
+                    out.push(event.state_key);
+                }
+            },
+            Ok(_) => {},
+            Err(err) => {
+                /* Removed links have an empty content */
+                debug!("MATRIX|GROUPS: Skipping m.space.child in {}: {}", space.room_id(), err);
+            }
+        }
+    }
+
+    Ok(out)
+}
+
+fn get_direct_target_email(room: &Room, me: &UserId) -> Option<String> {
+    let direct_targets = room.direct_targets();
+    if direct_targets.len() != 1 {
+        return None;
+    }
+
+    direct_targets.iter().find(|target| *target != me).map(|target| EmailAddress::from_user_id(target).to_string())
+}
+
+fn get_dm_room_of(client: &Client, email: &str) -> Option<Room> {
+    let user_id = EmailAddress::from_str(email).ok()?.try_to_owned_user_id()?;
+    client.get_dm_room(&user_id)
+}
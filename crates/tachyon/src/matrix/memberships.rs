@@ -1,6 +1,7 @@
 use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 use std::convert::Infallible;
 use std::mem;
+use std::str::FromStr;
 use dashmap::mapref::one::RefMut;
 use log::{debug, error, warn};
 use matrix_sdk::{Client, Room, RoomMemberships};
@@ -21,24 +22,24 @@ use msnp::msnp::notification::command::not::NotServer;
 use msnp::shared::models::email_address::EmailAddress;
 use msnp::shared::models::endpoint_id::EndpointId;
 use msnp::shared::models::msn_user::MsnUser;
+use msnp::shared::models::network_id::NetworkId;
 use msnp::shared::models::role_list::RoleList;
 use msnp::shared::models::uuid::Uuid;
 use msnp::shared::payload::msg::raw_msg_payload::factories::RawMsgPayloadFactory;
 use msnp::soap::abch::ab_service::ab_find_contacts_paged::response::CircleData;
 use msnp::soap::abch::msnab_datatypes::{Annotation, ArrayOfAnnotation, BaseMember, ContactType, ContactTypeEnum, MemberState, MemberType, CircleRelationshipRole, RelationshipState, RoleId, NetworkInfoType, CircleInverseInfoType};
-use crate::matrix::directs::{force_update_rooms_with_fresh_m_direct, get_invite_room_mapping_info, get_joined_room_mapping_info, get_left_room_mapping_info, RoomMapping};
-use crate::notification::client_store::{ClientData, Contact};
+use crate::matrix::directs::{force_update_rooms_with_fresh_m_direct, get_pending_dm_invites, is_direct_invite, reject_dm_invite, get_invite_room_mapping_info, get_joined_room_mapping_info, get_left_room_mapping_info, RoomMapping};
+use crate::notification::address_book_store::{AddressBookChanges, MemberRole, StoredCircle, StoredCircleInvite, StoredCircleMember, StoredContact, StoredMember};
+use crate::notification::client_store::ClientData;
 use crate::shared::identifiers::MatrixIdCompatible;
 
 
-pub async fn handle_memberships(client: Client, response: SyncResponse) -> Result<(Vec<Contact>, VecDeque<BaseMember>, HashMap<String, Vec<ContactType>>), anyhow::Error> {
+pub async fn handle_memberships(client: Client, response: SyncResponse) -> Result<AddressBookChanges, anyhow::Error> {
     debug!("---Handle Memberships---");
     let me = client.user_id().expect("UserID to be here");
 
 
-    let mut contacts = Vec::new();
-    let mut memberships = VecDeque::new();
-    let mut circle_members: HashMap<String, Vec<ContactType>> = HashMap::new();
+    let mut changes = AddressBookChanges::default();
 
     // for account_data_event in response.account_data {
     //     match account_data_event.deserialize() {
@@ -94,7 +95,7 @@ pub async fn handle_memberships(client: Client, response: SyncResponse) -> Resul
                 Ok(AnySyncTimelineEvent::State(AnySyncStateEvent::RoomMember(room_member_event))) => {
                     if dedup.get(room_member_event.event_id().as_str()).is_none() {
                         dedup.insert(room_member_event.event_id().to_string());
-                        handle_joined_room_member_event(&room_member_event, &room, me, &client, &mut contacts, &mut memberships, &mut circle_members).await?;
+                        handle_joined_room_member_event(&room_member_event, &room, me, &client, &mut changes).await?;
                     }
                 },
                 Ok(other) => {
@@ -115,7 +116,7 @@ pub async fn handle_memberships(client: Client, response: SyncResponse) -> Resul
                         if dedup.get(room_member_event.event_id().as_str()).is_none() {
                             dedup.insert(room_member_event.event_id().to_string());
                         }
-                        handle_joined_room_member_event(&room_member_event, &room, me, &client, &mut contacts, &mut memberships, &mut circle_members).await?;
+                        handle_joined_room_member_event(&room_member_event, &room, me, &client, &mut changes).await?;
                     },
                     Ok(other) => {
                         error!("SYNC|MEMBERSHIPS|JOIN: Received non member event : {:?}", other);
@@ -137,7 +138,7 @@ pub async fn handle_memberships(client: Client, response: SyncResponse) -> Resul
             match state_event.deserialize() {
                 Ok(AnyStrippedStateEvent::RoomMember(stripped_rm_event)) => {
                     debug!("SYNC|MEMBERSHIPS|INVITE: Stripped RoomMemberEvent Received: {:?}", stripped_rm_event);
-                    handle_invite_room_member_event(&stripped_rm_event, &room_id, me, &client, &mut contacts, &mut memberships).await?;
+                    handle_invite_room_member_event(&stripped_rm_event, &room_id, me, &client, &mut changes).await?;
                 },
                 Ok(other) => {
                     error!("SYNC|MEMBERSHIPS|INVITE: Received non member event : {:?}", other);
@@ -162,7 +163,7 @@ pub async fn handle_memberships(client: Client, response: SyncResponse) -> Resul
 
             match state_event.deserialize() {
                 Ok(AnySyncStateEvent::RoomMember(room_member_event)) => {
-                    handle_leave_room_member_event(&room_member_event, &room, me, &client, &mut contacts, &mut memberships).await?;
+                    handle_leave_room_member_event(&room_member_event, &room, me, &client, &mut changes).await?;
                 },
                 Ok(other) => {
                     debug!("SYNC|MEMBERSHIPS|LEAVE: Received Non Member Event: {:?}", &other);
@@ -176,10 +177,10 @@ pub async fn handle_memberships(client: Client, response: SyncResponse) -> Resul
 
     }
 
-    Ok((contacts, memberships, circle_members))
+    Ok(changes)
 }
 
-pub async fn handle_joined_room_member_event(event: &SyncRoomMemberEvent, room: &Room, me: &UserId, client: &Client, contacts: &mut Vec<Contact>, memberships: &mut VecDeque<BaseMember>, circle_members: &mut HashMap<String, Vec<ContactType>>) -> Result<(), anyhow::Error> {
+pub async fn handle_joined_room_member_event(event: &SyncRoomMemberEvent, room: &Room, me: &UserId, client: &Client, changes: &mut AddressBookChanges) -> Result<(), anyhow::Error> {
 
             match event {
                 SyncRoomMemberEvent::Original(og_rm_event) => {
@@ -201,29 +202,20 @@ pub async fn handle_joined_room_member_event(event: &SyncRoomMemberEvent, room:
                                     MembershipChange::Joined | MembershipChange::InvitationAccepted | MembershipChange::KnockAccepted => {
                                         debug!("SYNC|MEMBERSHIPS|JOIN: Sent Invite was accepted by contact: {}", &target_msn_addr);
                                         //He accepted my invitation, ADD TO REVERSE LIST, CHANGE CONTACT TO NOT PENDING
-                                        let invited_contact = ContactType::new(&target_msn_user.uuid, target_msn_addr, &display_name, ContactTypeEnum::Live, false);
-                                        let invited_reverse_member = BaseMember::new_passport_member(&target_msn_user.uuid, target_msn_addr, MemberState::Accepted, RoleList::Reverse, false);
-
-                                        contacts.push(Contact::Contact(invited_contact));
-                                        memberships.push_back(invited_reverse_member);
+                                        changes.contacts.push(StoredContact::new(target_msn_addr, display_name, false, false));
+                                        changes.members.push(StoredMember::new(target_msn_addr, MemberRole::Reverse, false));
                                     }
                                     MembershipChange::Left | MembershipChange::Banned | MembershipChange::Kicked | MembershipChange::KickedAndBanned => {
                                         debug!("SYNC|MEMBERSHIPS|JOIN: Contact Left the room: {}", &target_msn_addr);
                                         //He left the room, Remove from Reverse List, Set to contact pending
-                                        let left_contact = ContactType::new(&target_msn_user.uuid, target_msn_addr, &display_name, ContactTypeEnum::Live, true);
-                                        let left_contact_pending = ContactType::new(&target_msn_user.uuid, target_msn_addr, &display_name, ContactTypeEnum::LivePending, false);
-                                        let left_reverse_member = BaseMember::new_passport_member(&target_msn_user.uuid, target_msn_addr, MemberState::Accepted, RoleList::Reverse, true);
-                                        contacts.push(Contact::Contact(left_contact));
-                                        contacts.push(Contact::Contact(left_contact_pending));
-                                        memberships.push_back(left_reverse_member);
+                                        changes.contacts.push(StoredContact::new(target_msn_addr, display_name, true, false));
+                                        changes.members.push(StoredMember::new(target_msn_addr, MemberRole::Reverse, true));
                                     }
                                     MembershipChange::Invited => {
                                         debug!("SYNC|MEMBERSHIPS|JOIN: Invited contact to join room: {}", &target_msn_addr);
                                         //I Invited him, Add to allow list, add to contact pending.
-                                        let invited_contact = ContactType::new(&target_msn_user.uuid, target_msn_addr, &display_name, ContactTypeEnum::LivePending, false);
-                                        let invited_allow_member = BaseMember::new_passport_member(&target_msn_user.uuid, target_msn_addr, MemberState::Accepted, RoleList::Allow, false);
-                                        contacts.push(Contact::Contact(invited_contact));
-                                        memberships.push_back(invited_allow_member);
+                                        changes.contacts.push(StoredContact::new(target_msn_addr, display_name, true, false));
+                                        changes.members.push(StoredMember::new(target_msn_addr, MemberRole::Allow, false));
                                     }
                                     _ => {}
                                 }
@@ -232,24 +224,16 @@ pub async fn handle_joined_room_member_event(event: &SyncRoomMemberEvent, room:
                                     MembershipChange::Left => {
                                         log::info!("SYNC|MEMBERSHIPS|JOIN: I Left, delete: {}", &target_msn_user.get_email_address());
                                         //I Left the room, remove member from PENDING_LIST, ALLOW_LIST, REVERSE_LIST. Remove Contact from Contact List
-                                        let current_contact = ContactType::new(&target_msn_user.uuid, target_msn_addr, target_msn_addr, ContactTypeEnum::Live, true);
-                                        let current_allow_member = BaseMember::new_passport_member(&target_msn_user.uuid, target_msn_addr, MemberState::Accepted, RoleList::Allow, true);
-                                        let current_reverse_member = BaseMember::new_passport_member(&target_msn_user.uuid, target_msn_addr, MemberState::Accepted, RoleList::Reverse, true);
-                                        let current_pending_member = BaseMember::new_passport_member(&target_msn_user.uuid, target_msn_addr, MemberState::Accepted, RoleList::Pending, true);
-
-                                        contacts.push(Contact::Contact(current_contact));
-                                        memberships.push_back(current_allow_member);
-                                        memberships.push_back(current_reverse_member);
-                                        memberships.push_back(current_pending_member);
+                                        changes.contacts.push(StoredContact::new(target_msn_addr, target_msn_addr, false, true));
+                                        changes.members.push(StoredMember::new(target_msn_addr, MemberRole::Allow, true));
+                                        changes.members.push(StoredMember::new(target_msn_addr, MemberRole::Reverse, true));
+                                        changes.members.push(StoredMember::new(target_msn_addr, MemberRole::Pending, true));
                                     }
                                     MembershipChange::InvitationAccepted | MembershipChange::Joined => {
                                         log::info!("SYNC|MEMBERSHIPS|JOIN: I Accepted an invite from: {} Do Nothing", &target_msn_user.get_email_address());
-                                        let inviter_contact = ContactType::new(&target_msn_user.uuid, target_msn_addr, target_msn_addr, ContactTypeEnum::Live, false);
-                                        let inviter_allow_member = BaseMember::new_passport_member(&target_msn_user.uuid, target_msn_addr, MemberState::Accepted, RoleList::Allow, false);
-                                        let inviter_pending_member = BaseMember::new_passport_member(&target_msn_user.uuid, target_msn_addr, MemberState::Accepted, RoleList::Pending, true);
-                                        contacts.push(Contact::Contact(inviter_contact));
-                                        memberships.push_back(inviter_allow_member);
-                                        memberships.push_back(inviter_pending_member);
+                                        changes.contacts.push(StoredContact::new(target_msn_addr, target_msn_addr, false, false));
+                                        changes.members.push(StoredMember::new(target_msn_addr, MemberRole::Allow, false));
+                                        changes.members.push(StoredMember::new(target_msn_addr, MemberRole::Pending, true));
                                     }
                                     _ => {}
                                 }
@@ -260,59 +244,27 @@ pub async fn handle_joined_room_member_event(event: &SyncRoomMemberEvent, room:
                         RoomMapping::Group => {
                             debug!("SYNC|MEMBERSHIPS|JOIN: Mapping is Group");
                             let room_id = room.room_id();
-                            let circle_uuid = Uuid::from_seed(room_id.as_str());
+                            let circle_id = Uuid::from_seed(room_id.as_str()).to_string();
 
                             if &og_rm_event.state_key == me {
                                 // Me action
                                 match og_rm_event.membership_change() {
                                     MembershipChange::Left => {
                                         //I Left the Circle
-                                        let mut circle = ContactType::new_circle(room_id.as_str(), &room.compute_display_name().await.unwrap().to_string(), true, RelationshipState::Accepted, CircleRelationshipRole::None);
-                                        let inverse_info = CircleInverseInfoType::new(circle.contact_id.clone().expect("to be here"), room.compute_display_name().await.expect("").to_string(), true, CircleRelationshipRole::Member, RelationshipState::Accepted);
-                                        contacts.push(Contact::Circle(CircleData {
-                                            contact: circle,
-                                            inverse_info,
-                                        }));
-
+                                        changes.circles.push(StoredCircle::new(room_id.as_str(), &room.compute_display_name().await?.to_string(), None, true));
                                     }
                                     MembershipChange::InvitationAccepted | MembershipChange::Joined => {
                                         //I accepted an invite to a circle
 
-                                        let mut circle = ContactType::new_circle(room_id.as_str(), &room.compute_display_name().await.unwrap().to_string(), false, RelationshipState::Accepted, CircleRelationshipRole::None);
-                                        let inverse_info = CircleInverseInfoType::new(circle.contact_id.clone().expect("to be here"), room.compute_display_name().await.expect("").to_string(), false, CircleRelationshipRole::Member, RelationshipState::Accepted);
-
-                                        contacts.push(Contact::Circle(CircleData {
-                                            contact: circle,
-                                            inverse_info,
-                                        }));
-
-                                        let circle_members = {
-                                            match circle_members.get_mut(&circle_uuid.to_string()) {
-                                                None => {
-                                                    circle_members.insert(circle_uuid.to_string(), Vec::new());
-                                                    circle_members.get_mut(&circle_uuid.to_string()).expect("to be here")
-                                                }
-                                                Some(circle_members) => {
-                                                    circle_members
-                                                }
-                                            }
-                                        };
+                                        changes.circles.push(StoredCircle::new(room_id.as_str(), &room.compute_display_name().await?.to_string(), None, false));
 
                                         let mut members = room.members_no_sync(RoomMemberships::ACTIVE).await?;
 
                                         //This method is the same as in GetContactsPaged TODO cleanup
                                         for current in members.drain(..){
-
-                                            match current.membership() {
-                                                MembershipState::Join => {
-                                                    let msn_user = MsnUser::with_email_addr(EmailAddress::from_user_id(current.user_id()));
-                                                    circle_members.push(ContactType::new_circle_member_contact(&msn_user.uuid, msn_user.get_email_address().as_str(), current.display_name().unwrap_or(msn_user.get_email_address().as_str()), ContactTypeEnum::Live, RelationshipState::Accepted , CircleRelationshipRole::Member , false));
-                                                }
-                                                _ => {
-                                                    let msn_user = MsnUser::with_email_addr(EmailAddress::from_user_id(current.user_id()));
-                                                    circle_members.push(ContactType::new_circle_member_contact(&msn_user.uuid, msn_user.get_email_address().as_str(), current.display_name().unwrap_or(msn_user.get_email_address().as_str()), ContactTypeEnum::LivePending, RelationshipState::WaitingResponse , CircleRelationshipRole::Member,false));
-                                                }
-                                            }
+                                            let msn_addr = EmailAddress::from_user_id(current.user_id());
+                                            let pending = current.membership() != &MembershipState::Join;
+                                            changes.add_circle_member(&circle_id, StoredCircleMember::new(msn_addr.as_str(), current.display_name().unwrap_or(msn_addr.as_str()), pending, false));
                                         }
 
                                     }
@@ -329,31 +281,18 @@ pub async fn handle_joined_room_member_event(event: &SyncRoomMemberEvent, room:
                                 let target_msn_addr = target_user.get_email_address().as_str();
                                 let display_name = display_name.unwrap_or(target_msn_addr.to_string());
 
-                                let circle_members = {
-                                    match circle_members.get_mut(&circle_uuid.to_string()) {
-                                        None => {
-                                            circle_members.insert(circle_uuid.to_string(), Vec::new());
-                                            circle_members.get_mut(&circle_uuid.to_string()).expect("to be here")
-                                        }
-                                        Some(circle_members) => {
-                                            circle_members
-                                        }
-                                    }
-                                };
-
-
                                 match og_rm_event.membership_change() {
                                     MembershipChange::Joined | MembershipChange::InvitationAccepted | MembershipChange::KnockAccepted => {
                                         debug!("SYNC|MEMBERSHIPS|JOIN: Sent Invite was accepted by contact in circle: {}", &target_msn_addr);
-                                        circle_members.push(ContactType::new_circle_member_contact(&target_user.uuid, target_msn_addr, &display_name, ContactTypeEnum::Live, RelationshipState::Accepted , CircleRelationshipRole::Member , false));
+                                        changes.add_circle_member(&circle_id, StoredCircleMember::new(target_msn_addr, &display_name, false, false));
                                     },
                                     MembershipChange::Left | MembershipChange::Banned | MembershipChange::Kicked | MembershipChange::KickedAndBanned => {
                                         debug!("SYNC|MEMBERSHIPS|JOIN: Contact Left the circle: {}", &target_msn_addr);
-                                        circle_members.push(ContactType::new_circle_member_contact(&target_user.uuid, target_msn_addr, &display_name, ContactTypeEnum::Live, RelationshipState::Accepted , CircleRelationshipRole::Member , true));
+                                        changes.add_circle_member(&circle_id, StoredCircleMember::new(target_msn_addr, &display_name, false, true));
                                     },
                                     MembershipChange::Invited => {
                                         debug!("SYNC|MEMBERSHIPS|JOIN: Invited contact to join circle: {}", &target_msn_addr);
-                                        circle_members.push(ContactType::new_circle_member_contact(&target_user.uuid, target_msn_addr, &display_name, ContactTypeEnum::Live, RelationshipState::WaitingResponse , CircleRelationshipRole::Member , false));
+                                        changes.add_circle_member(&circle_id, StoredCircleMember::new(target_msn_addr, &display_name, true, false));
                                     }
 
                                     _ => {
@@ -376,7 +315,7 @@ pub async fn handle_joined_room_member_event(event: &SyncRoomMemberEvent, room:
     Ok(())
 }
 
-async fn handle_invite_room_member_event(event: &StrippedRoomMemberEvent, room_id: &RoomId, me: &UserId, client: &Client, contacts: &mut Vec<Contact>, memberships: &mut VecDeque<BaseMember>) -> Result<(), anyhow::Error> {
+pub async fn handle_invite_room_member_event(event: &StrippedRoomMemberEvent, room_id: &RoomId, me: &UserId, client: &Client, changes: &mut AddressBookChanges) -> Result<(), anyhow::Error> {
     match event.content.membership {
         MembershipState::Invite => {
 
@@ -395,39 +334,16 @@ async fn handle_invite_room_member_event(event: &StrippedRoomMemberEvent, room_i
                         let target_msn_addr = target_msn_user.get_email_address().as_str();
 
                         log::info!("SYNC|MEMBERSHIPS|INVITE: I received Direct invite from: {}", &target_msn_addr);
-                        let mut current_pending_member = BaseMember::new_passport_member(&target_msn_user.uuid, &target_msn_addr, MemberState::Accepted, RoleList::Pending, false);
-                        current_pending_member.display_name = Some(target_msn_addr.to_string());
-                        let annotation = Annotation::new_invite(event.content.reason.as_ref().map(|e| e.as_str()).unwrap_or(""));
-                        let mut annotations = Vec::new();
-                        annotations.push(annotation);
-                        current_pending_member.annotations = Some(ArrayOfAnnotation { annotation: annotations });
-
-
-                        let current_reverse_member = BaseMember::new_passport_member(&target_msn_user.uuid, &target_msn_addr, MemberState::Accepted, RoleList::Reverse, false);
-                        memberships.push_back(current_pending_member);
-                        memberships.push_back(current_reverse_member);
+                        changes.members.push(StoredMember::new_pending(target_msn_addr, event.content.reason.as_ref().map(|e| e.as_str()).unwrap_or("")));
+                        changes.members.push(StoredMember::new(target_msn_addr, MemberRole::Reverse, false));
 
                     }
                     RoomMapping::Group => {
                         debug!("SYNC|MEMBERSHIPS|INVITE: Mapping is Group");
                         log::info!("SYNC|MEMBERSHIPS|INVITE: I received a Group invite from: {}", &target_user);
                         let room = client.get_room(room_id).unwrap();
-                        let mut circle = ContactType::new_circle(room_id.as_str(), &room.compute_display_name().await.unwrap().to_string(), false, RelationshipState::WaitingResponse, CircleRelationshipRole::StatePendingOutbound);
-                        let contact_info = circle.contact_info.as_mut().unwrap();
-                        let network_info = contact_info.network_info_list.as_mut().unwrap();
-                        let network_info_first = network_info.network_info.get_mut(0).unwrap();
-
-                        network_info_first.inviter_message = event.content.reason.clone();
-                        network_info_first.inviter_email = Some(target_msn_user.get_email_address().to_string());
-                        network_info_first.inviter_cid = target_msn_user.uuid.to_decimal_cid() as u64;
-                        network_info_first.inviter_name = Some(target_msn_user.get_email_address().to_string());
-                        //network_info_first.inviter_name = Some(room.get_member(&target_user).await?.unwrap().display_name().unwrap_or(target_msn_user.get_email_address().as_str()).to_string());
-
-                        let inverse_info = CircleInverseInfoType::new(circle.contact_id.clone().expect("to be here"), room.compute_display_name().await.expect("").to_string(), false, CircleRelationshipRole::StatePendingOutbound, RelationshipState::WaitingResponse);
-                        contacts.push(Contact::Circle(CircleData {
-                            contact: circle,
-                            inverse_info,
-                        }));
+                        let invite = StoredCircleInvite { inviter_email: target_msn_user.get_email_address().to_string(), message: event.content.reason.clone() };
+                        changes.circles.push(StoredCircle::new(room_id.as_str(), &room.compute_display_name().await?.to_string(), Some(invite), false));
                     }
                     RoomMapping::PendingDirect(_) => {}
                 }
@@ -441,25 +357,130 @@ async fn handle_invite_room_member_event(event: &StrippedRoomMemberEvent, room_i
 
 }
 
-async fn handle_leave_room_member_event(event: &SyncRoomMemberEvent, room: &Room, me: &UserId, client: &Client, contacts: &mut Vec<Contact>, memberships: &mut VecDeque<BaseMember>) -> Result<(), anyhow::Error> {
+/* BLP BL: DM invites from users not on the Allow list are declined, their circle invites hidden.
+   Matrix presence isn't per user: the homeserver shares it with everyone we have a room with, so users we share a group room with still see it.
+   Declining only avoids sharing a new room with them, BL mode doesn't hide our presence */
+pub async fn enforce_allow_list(changes: &mut AddressBookChanges, client: &Client, client_data: &ClientData) -> Result<(), anyhow::Error> {
+    if !client_data.is_allow_list_only() {
+        return Ok(());
+    }
+
+    let declined: HashSet<String> = changes.members.iter()
+        .filter(|member| member.role == MemberRole::Pending && !member.deleted && !client_data.is_allowed(&member.email))
+        .map(|member| member.email.clone())
+        .collect();
+
+    for email in &declined {
+        log::info!("SYNC|MEMBERSHIPS|INVITE: Declining invite from {}, not on the Allow list", email);
+        if let Some(user_id) = EmailAddress::from_str(email).ok().and_then(|addr| addr.try_to_owned_user_id()) {
+            for invited_room in get_pending_dm_invites(client, &user_id).await? {
+                reject_dm_invite(&invited_room).await?;
+            }
+        }
+    }
+
+    changes.members.retain(|member| member.deleted || !declined.contains(&member.email) || !matches!(member.role, MemberRole::Pending | MemberRole::Reverse));
+    changes.circles.retain(|circle| circle.invite.as_ref().map_or(true, |invite| client_data.is_allowed(&invite.inviter_email)));
+    Ok(())
+}
+
+/* Switching to BL mode declines the DM invites we haven't answered yet */
+pub async fn decline_invites_outside_allow_list(client: &Client, client_data: &ClientData) -> Result<(), anyhow::Error> {
+    let me = client.user_id().expect("A user to be logged in when declining invites");
+    let mut changes = AddressBookChanges::default();
+
+    for invited_room in client.invited_rooms() {
+        if !is_direct_invite(&invited_room, me).await? {
+            continue;
+        }
+
+        let Some(inviter) = invited_room.invite_details().await?.inviter else {
+            continue;
+        };
+
+        let inviter_email = EmailAddress::from_user_id(inviter.user_id()).to_string();
+        if client_data.is_allowed(&inviter_email) {
+            continue;
+        }
+
+        log::info!("SYNC|MEMBERSHIPS|INVITE: Declining invite from {}, not on the Allow list", &inviter_email);
+        reject_dm_invite(&invited_room).await?;
+        changes.members.push(StoredMember::new(&inviter_email, MemberRole::Pending, true));
+        changes.members.push(StoredMember::new(&inviter_email, MemberRole::Reverse, true));
+    }
+
+    queue_server_membership_pushes(&changes, None, client_data).await?;
+    client_data.inner.address_book_store.apply(changes);
+    client_data.send_contact_list_pushes().await;
+    Ok(())
+}
+
+/* RL/PL are server managed, member changes are mirrored in the contact list which queues the ADL/RML pushes.
+   A new Pending member gets the "X added you" prompt, blocked inviters don't */
+pub async fn queue_server_membership_pushes(changes: &AddressBookChanges, room: Option<&Room>, client_data: &ClientData) -> Result<(), anyhow::Error> {
+    let mut added: Vec<(String, u8)> = Vec::new();
+    let mut removed: Vec<(String, u8)> = Vec::new();
+
+    for member in changes.members.iter().filter(|member| matches!(member.role, MemberRole::Reverse | MemberRole::Pending)) {
+        let lists = if member.deleted { &mut removed } else { &mut added };
+        match lists.iter_mut().find(|(email, _)| email == &member.email) {
+            Some((_, roles)) => *roles |= member.role.to_role_list() as u8,
+            None => lists.push((member.email.clone(), member.role.to_role_list() as u8))
+        }
+    }
+
+    for (email, roles) in removed {
+        let email_addr = EmailAddress::from_str(&email)?;
+        client_data.inner.contact_list.lock().unwrap().remove_server_memberships(&email_addr, roles);
+    }
+
+    for (email, roles) in added {
+        if client_data.inner.address_book_store.is_blocked(&email) {
+            continue;
+        }
+
+        let email_addr = EmailAddress::from_str(&email)?;
+        let display_name = match (room, email_addr.try_to_owned_user_id()) {
+            (Some(room), Some(user_id)) => room.get_member_no_sync(&user_id).await?.and_then(|room_member| room_member.display_name().map(|name| name.to_string())),
+            _ => None
+        };
+
+        debug!("SYNC|MEMBERSHIPS: Queuing server lists {} for: {}", roles, &email);
+        client_data.inner.contact_list.lock().unwrap().add_server_memberships(&email_addr, NetworkId::WindowsLive, roles, Some(display_name.as_deref().unwrap_or(&email)));
+    }
+
+    Ok(())
+}
+
+/* The client gets RL/PL from FindMembership, the contact list starts from there so only later changes are pushed */
+pub fn seed_server_memberships(client_data: &ClientData) {
+    let members = client_data.inner.address_book_store.get_members(None);
+    let mut contact_list = client_data.inner.contact_list.lock().unwrap();
+
+    for member in members.iter().filter(|member| matches!(member.role, MemberRole::Reverse | MemberRole::Pending)) {
+        match EmailAddress::from_str(&member.email) {
+            Ok(email_addr) => contact_list.seed_server_memberships(&email_addr, NetworkId::WindowsLive, member.role.to_role_list() as u8),
+            Err(err) => warn!("SYNC|MEMBERSHIPS: Invalid member email in the address book: {}: {}", &member.email, err)
+        }
+    }
+}
+
+pub async fn handle_leave_room_member_event(event: &SyncRoomMemberEvent, room: &Room, me: &UserId, client: &Client, changes: &mut AddressBookChanges) -> Result<(), anyhow::Error> {
     match event {
         SyncRoomMemberEvent::Original(og_rm_event) => {
             debug!("SYNC|MEMBERSHIPS|LEAVE: Original SyncRoomMemberEvent Received: {:?}", og_rm_event);
 
-
+            /* The inviter withdrew a DM invite we didn't answer, its "X added you" prompt goes away */
+            let was_direct_invite = og_rm_event.prev_content().is_some_and(|prev| prev.membership == MembershipState::Invite && prev.is_direct.unwrap_or(false));
+            if og_rm_event.state_key == me && was_direct_invite && matches!(og_rm_event.membership_change(), MembershipChange::InvitationRevoked) {
+                let inviter_msn_addr = EmailAddress::from_user_id(&og_rm_event.sender).to_string();
+                log::info!("SYNC|MEMBERSHIPS|LEAVE: DM invite was withdrawn by: {}", &inviter_msn_addr);
+                changes.members.push(StoredMember::new(&inviter_msn_addr, MemberRole::Pending, true));
+                changes.members.push(StoredMember::new(&inviter_msn_addr, MemberRole::Reverse, true));
+            }
 
             //TODO circle before this.
             // let mapping = get_left_room_mapping_info(&room, me, &client).await?;
-            // match mapping {
-            //     RoomMappingInfo::Direct(direct_target) => {
-            //
-            //     }
-            //     RoomMappingInfo::Group => {
-            //
-            //     }
-            // }
-
-
             }
         _ => {
             debug!("SYNC|MEMBERSHIPS|LEAVE: Non Original SyncRoomMemberEvent Received: {:?}", event);
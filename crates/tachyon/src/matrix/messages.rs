@@ -0,0 +1,348 @@
+use log::{debug, warn};
+use matrix_sdk::{Client, Room};
+use matrix_sdk::ruma::{EventId, OwnedMxcUri, OwnedUserId, UserId};
+use matrix_sdk::ruma::events::{AnyMessageLikeEvent, AnyTimelineEvent, MessageLikeEvent, OriginalSyncMessageLikeEvent};
+use matrix_sdk::ruma::events::reaction::OriginalSyncReactionEvent;
+use matrix_sdk::ruma::events::room::message::{MessageType, OriginalSyncRoomMessageEvent, Relation, TextMessageEventContent};
+use matrix_sdk::ruma::events::room::redaction::OriginalSyncRoomRedactionEvent;
+use tokio::sync::mpsc::Sender;
+
+use msnp::msnp::notification::command::command::NotificationServerCommand;
+use msnp::msnp::notification::command::sdg::SdgServer;
+use msnp::shared::models::email_address::EmailAddress;
+use msnp::shared::models::network_id::NetworkId;
+use msnp::shared::models::network_id_email::NetworkIdEmail;
+use msnp::shared::models::presence_status::PresenceStatus;
+use msnp::shared::models::uuid::Uuid;
+use msnp::shared::payload::msg::emoticon_msg::{EmoticonDeclaration, EmoticonMessageContent};
+use msnp::shared::payload::msg::text_msg::TextMessageContent;
+use msnp::shared::payload::nfy::nfy_put_payload::RawNfyPayload;
+
+use crate::matrix::circles::get_circle_address;
+use crate::matrix::directs::resolve_direct_target;
+use crate::matrix::emoticons::{emoticon_to_msn_object, find_shortcuts_in_text, find_inline_emoticons, get_room_emoticons};
+use crate::matrix::events::wink_sticker::WinkStickerEventContent;
+use crate::matrix::receipts::send_read_receipt_for;
+use crate::matrix::rendering::{parse_reply_fallback, render_edit, render_emote, render_location, render_media, render_notice, render_reaction, render_redaction_notice, render_reply, strip_reply_fallback};
+use crate::matrix::winks::wink_to_msn_object;
+use crate::notification::client_store::ClientData;
+use crate::shared::identifiers::MatrixIdCompatible;
+use crate::web::media::media_link::get_media_link;
+
+/* Messages from a contact in a DM or from a circle member are delivered to the client with SDG */
+pub async fn handle_room_message(event: OriginalSyncRoomMessageEvent, room: Room, client: Client, notif_sender: Sender<NotificationServerCommand>, client_data: ClientData) -> Result<(), anyhow::Error> {
+    let Some((from, to)) = get_message_route(&event.sender, &room, &client, &client_data).await? else {
+        return Ok(());
+    };
+
+    let result = match &event.content.msgtype {
+        MessageType::Text(content) => {
+            handle_text_message(content, &event, &room, from, to, &client, &notif_sender, &client_data).await
+        },
+        MessageType::Emote(content) => {
+            let Some(body) = get_action_body(&event, &content.body) else {
+                return Ok(());
+            };
+            let sender_name = get_sender_name(&room, &event.sender).await;
+            let payload = RawNfyPayload::new_action_message(from, to, render_emote(&sender_name, &body));
+            notif_sender.send(to_sdg(payload)).await?;
+            Ok(())
+        },
+        MessageType::Notice(content) => {
+            let Some(body) = get_action_body(&event, &content.body) else {
+                return Ok(());
+            };
+            let sender_name = get_sender_name(&room, &event.sender).await;
+            let payload = RawNfyPayload::new_action_message(from, to, render_notice(&sender_name, &body));
+            notif_sender.send(to_sdg(payload)).await?;
+            Ok(())
+        },
+        MessageType::Image(content) => {
+            send_media_link("an image", &content.body, &event, &room, from, to, &client, &notif_sender).await
+        },
+        MessageType::Video(content) => {
+            send_media_link("a video", &content.body, &event, &room, from, to, &client, &notif_sender).await
+        },
+        MessageType::Audio(content) => {
+            send_media_link("an audio clip", &content.body, &event, &room, from, to, &client, &notif_sender).await
+        },
+        MessageType::File(content) => {
+            send_media_link("a file", &content.body, &event, &room, from, to, &client, &notif_sender).await
+        },
+        MessageType::Location(content) => {
+            let payload = RawNfyPayload::new_text_message(from, to, TextMessageContent::new_with_default_style(&render_location(&content.body, &content.geo_uri)));
+            notif_sender.send(to_sdg(payload)).await?;
+            Ok(())
+        },
+        _ => {
+            debug!("SYNC|MESSAGES: Unhandled message type: {}", event.content.msgtype.msgtype());
+            return Ok(());
+        }
+    };
+
+    result?;
+    acknowledge_delivery(&room, &event.event_id, &client_data).await;
+    Ok(())
+}
+
+/* The message reached WLM, the other side gets a read receipt for it unless the user is away. Typing or closing the window catches up later */
+async fn acknowledge_delivery(room: &Room, event_id: &EventId, client_data: &ClientData) {
+    let is_away = client_data.get_user().map(|user| matches!(user.status, PresenceStatus::AWY | PresenceStatus::BRB | PresenceStatus::IDL | PresenceStatus::LUN)).unwrap_or(false);
+    if is_away {
+        debug!("SYNC|MESSAGES: User is away, not sending read receipt to {}", room.room_id());
+        return;
+    }
+
+    if let Err(err) = send_read_receipt_for(room, event_id.to_owned(), client_data).await {
+        warn!("SYNC|MESSAGES: Couldn't send read receipt to {}: {}", room.room_id(), err);
+    }
+}
+
+/* The link is signed for the logged in user, the web server fetches & decrypts the media when it's clicked */
+async fn send_media_link(kind: &str, name: &str, event: &OriginalSyncRoomMessageEvent, room: &Room, from: NetworkIdEmail, to: NetworkIdEmail, client: &Client, notif_sender: &Sender<NotificationServerCommand>) -> Result<(), anyhow::Error> {
+    let me = client.user_id().expect("to be here");
+    let link = get_media_link(me, room.room_id(), &event.event_id);
+
+    let payload = RawNfyPayload::new_text_message(from, to, TextMessageContent::new_with_default_style(&render_media(kind, name, &link)));
+    notif_sender.send(to_sdg(payload)).await?;
+    Ok(())
+}
+
+/* Action messages are a single line, replies lose their quote and edits are resent whole */
+fn get_action_body(event: &OriginalSyncRoomMessageEvent, body: &str) -> Option<String> {
+    match &event.content.relates_to {
+        Some(Relation::Replacement(replacement)) => match &replacement.new_content.msgtype {
+            MessageType::Emote(new_content) => Some(render_edit(&new_content.body)),
+            MessageType::Notice(new_content) => Some(render_edit(&new_content.body)),
+            _ => {
+                debug!("SYNC|MESSAGES: Unhandled edit message type: {}", replacement.new_content.msgtype.msgtype());
+                None
+            }
+        },
+        Some(Relation::Reply { .. }) => Some(strip_reply_fallback(body)),
+        _ => Some(body.to_string())
+    }
+}
+
+async fn handle_text_message(content: &TextMessageEventContent, event: &OriginalSyncRoomMessageEvent, room: &Room, from: NetworkIdEmail, to: NetworkIdEmail, client: &Client, notif_sender: &Sender<NotificationServerCommand>, client_data: &ClientData) -> Result<(), anyhow::Error> {
+    let (content, body) = match &event.content.relates_to {
+        Some(Relation::Replacement(replacement)) => {
+            let MessageType::Text(new_content) = &replacement.new_content.msgtype else {
+                debug!("SYNC|MESSAGES: Unhandled edit message type: {}", replacement.new_content.msgtype.msgtype());
+                return Ok(());
+            };
+            (new_content, render_edit(&new_content.body))
+        },
+        Some(Relation::Reply { in_reply_to }) => {
+            let body = strip_reply_fallback(&content.body);
+            let quote = match get_text_event(room, &in_reply_to.event_id).await {
+                Some((sender, quoted_body)) => Some((get_sender_name(room, &sender).await, quoted_body)),
+                None => parse_reply_fallback(&content.body)
+            };
+
+            match quote {
+                Some((quoted_sender, quoted_body)) => (content, render_reply(&quoted_sender, &quoted_body, &body)),
+                None => (content, body)
+            }
+        },
+        _ => (content, content.body.clone())
+    };
+
+    /* Custom emoticons are fetched over P2P, which circles don't have */
+    let emoticons = if to.network_id == NetworkId::Circle { EmoticonMessageContent::new(Vec::new()) } else { get_emoticon_declarations(content, &body, room, &from.email, client, client_data).await };
+    if !emoticons.is_empty() {
+        let payload = RawNfyPayload::new_emoticon_message(from.clone(), to.clone(), &emoticons);
+        notif_sender.send(to_sdg(payload)).await?;
+    }
+
+    let payload = RawNfyPayload::new_text_message(from, to, TextMessageContent::new_with_default_style(&body));
+    notif_sender.send(to_sdg(payload)).await?;
+
+    Ok(())
+}
+
+/* WLM has no reactions, they are shown as action messages */
+pub async fn handle_reaction(event: OriginalSyncReactionEvent, room: Room, client: Client, notif_sender: Sender<NotificationServerCommand>, client_data: ClientData) -> Result<(), anyhow::Error> {
+    let Some((from, to)) = get_message_route(&event.sender, &room, &client, &client_data).await? else {
+        return Ok(());
+    };
+
+    let annotation = &event.content.relates_to;
+    let reacted_body = get_text_event(&room, &annotation.event_id).await.map(|(_, body)| body);
+
+    let payload = RawNfyPayload::new_action_message(from, to, render_reaction(&annotation.key, reacted_body.as_deref()));
+    notif_sender.send(to_sdg(payload)).await?;
+    Ok(())
+}
+
+/* Only redacted messages are notified, removing a reaction isn't worth a notice */
+pub async fn handle_redaction(event: OriginalSyncRoomRedactionEvent, room: Room, client: Client, notif_sender: Sender<NotificationServerCommand>, client_data: ClientData) -> Result<(), anyhow::Error> {
+    let Some((from, to)) = get_message_route(&event.sender, &room, &client, &client_data).await? else {
+        return Ok(());
+    };
+
+    let Some(redacts) = event.content.redacts.as_ref().or(event.redacts.as_ref()) else {
+        return Ok(());
+    };
+
+    match room.event(redacts).await {
+        Ok(redacted) => {
+            if !matches!(redacted.event.deserialize(), Ok(AnyTimelineEvent::MessageLike(AnyMessageLikeEvent::RoomMessage(_)))) {
+                return Ok(());
+            }
+        },
+        Err(err) => {
+            debug!("SYNC|MESSAGES: Ignoring redaction of unknown event {} in {}: {}", redacts, room.room_id(), err);
+            return Ok(());
+        }
+    }
+
+    let payload = RawNfyPayload::new_action_message(from, to, render_redaction_notice(event.content.reason.as_deref()));
+    notif_sender.send(to_sdg(payload)).await?;
+    Ok(())
+}
+
+/* Stickers carrying a wink sent by another Tachyon user are replayed as real winks */
+pub async fn handle_wink_sticker(event: OriginalSyncMessageLikeEvent<WinkStickerEventContent>, room: Room, client: Client, notif_sender: Sender<NotificationServerCommand>, client_data: ClientData) -> Result<(), anyhow::Error> {
+    let Some(wink) = event.content.wink.as_ref() else {
+        return Ok(());
+    };
+
+    let Some((from, to)) = get_dm_route(&event.sender, &room, &client, &client_data).await? else {
+        return Ok(());
+    };
+
+    let msn_object = wink_to_msn_object(&client, &from.email, wink).await?;
+    let payload = RawNfyPayload::new_wink_message(from, to, &msn_object);
+    notif_sender.send(to_sdg(payload)).await?;
+
+    acknowledge_delivery(&room, &event.event_id, &client_data).await;
+    Ok(())
+}
+
+/* Only events sent by the contact of a DM are forwarded, returns (contact, me) */
+pub(crate) async fn get_dm_route(sender: &UserId, room: &Room, client: &Client, client_data: &ClientData) -> Result<Option<(NetworkIdEmail, NetworkIdEmail)>, anyhow::Error> {
+    let me = client.user_id().expect("to be here");
+
+    if sender == me || !room.is_direct().await? {
+        return Ok(None);
+    }
+
+    let direct_target = resolve_direct_target(&room.direct_targets(), room, me, client).await?;
+    if direct_target.as_deref() != Some(sender) {
+        debug!("SYNC|MESSAGES: Ignoring event from {} in {}, not the DM target", sender, room.room_id());
+        return Ok(None);
+    }
+
+    let from = NetworkIdEmail::new(NetworkId::WindowsLive, EmailAddress::from_user_id(sender));
+    if !client_data.is_allowed(from.email.as_str()) {
+        debug!("SYNC|MESSAGES: Hiding event from {} in {}, not on the Allow list", sender, room.room_id());
+        return Ok(None);
+    }
+
+    let to = client_data.get_user()?.get_network_id_email();
+    Ok(Some((from, to)))
+}
+
+/* Events from the other members of a group room go to its circle, once the client joined the circle conversation. Returns (member, circle) */
+async fn get_circle_route(sender: &UserId, room: &Room, client: &Client, client_data: &ClientData) -> Result<Option<(NetworkIdEmail, NetworkIdEmail)>, anyhow::Error> {
+    let me = client.user_id().expect("to be here");
+
+    if sender == me || room.is_space() || room.is_direct().await? {
+        return Ok(None);
+    }
+
+    let circle_id = Uuid::from_seed(room.room_id().as_str());
+    if !client_data.inner.address_book_store.has_circle(&circle_id.to_string()) || !client_data.inner.circle_store.is_joined(&circle_id) {
+        debug!("SYNC|MESSAGES: Not relaying event from {}, {} isn't a joined circle", sender, room.room_id());
+        return Ok(None);
+    }
+
+    let from = NetworkIdEmail::new(NetworkId::WindowsLive, EmailAddress::from_user_id(sender));
+    if !client_data.is_allowed(from.email.as_str()) {
+        debug!("SYNC|MESSAGES: Hiding event from {} in circle {}, not on the Allow list", sender, room.room_id());
+        return Ok(None);
+    }
+
+    Ok(Some((from, get_circle_address(room.room_id()))))
+}
+
+async fn get_message_route(sender: &UserId, room: &Room, client: &Client, client_data: &ClientData) -> Result<Option<(NetworkIdEmail, NetworkIdEmail)>, anyhow::Error> {
+    if let Some(route) = get_dm_route(sender, room, client, client_data).await? {
+        return Ok(Some(route));
+    }
+
+    get_circle_route(sender, room, client, client_data).await
+}
+
+/* Circle conversations are addressed with path=IM, on whichever side the circle is */
+pub fn to_sdg(mut payload: RawNfyPayload) -> NotificationServerCommand {
+    if payload.envelope.to.network_id == NetworkId::Circle {
+        payload.envelope.to_path = Some("IM".to_string());
+    }
+
+    if payload.envelope.from.network_id == NetworkId::Circle {
+        payload.envelope.from_path = Some("IM".to_string());
+    }
+
+    NotificationServerCommand::SDG(SdgServer { tr_id: 0, payload })
+}
+
+/* Replied or reacted events may predate the session, they are fetched from the homeserver if needed */
+async fn get_text_event(room: &Room, event_id: &EventId) -> Option<(OwnedUserId, String)> {
+    let event = match room.event(event_id).await {
+        Ok(event) => event,
+        Err(err) => {
+            warn!("SYNC|MESSAGES: Couldn't fetch event {} in {}: {}", event_id, room.room_id(), err);
+            return None;
+        }
+    };
+
+    let Ok(AnyTimelineEvent::MessageLike(AnyMessageLikeEvent::RoomMessage(MessageLikeEvent::Original(original)))) = event.event.deserialize() else {
+        return None;
+    };
+
+    Some((original.sender, strip_reply_fallback(original.content.body())))
+}
+
+async fn get_sender_name(room: &Room, sender: &UserId) -> String {
+    match room.get_member_no_sync(sender).await {
+        Ok(Some(member)) => member.name().to_string(),
+        _ => sender.localpart().to_string()
+    }
+}
+
+/* Inline emoticons come from the sender's own packs, shortcuts of the room packs are offered too */
+async fn get_emoticon_declarations(content: &TextMessageEventContent, body: &str, room: &Room, creator: &EmailAddress, client: &Client, client_data: &ClientData) -> EmoticonMessageContent {
+    let mut candidates: Vec<(String, OwnedMxcUri)> = match content.formatted.as_ref() {
+        Some(formatted) => find_inline_emoticons(&formatted.body).into_iter().filter(|(shortcut, _)| body.contains(shortcut.as_str())).collect(),
+        None => Vec::new()
+    };
+
+    match get_room_emoticons(room).await {
+        Ok(available) => {
+            candidates.extend(find_shortcuts_in_text(body, &available).into_iter().map(|(shortcut, image)| (shortcut, image.url)));
+        },
+        Err(err) => {
+            warn!("SYNC|MESSAGES: Couldn't fetch the emoticon packs of {}: {}", room.room_id(), err);
+        }
+    }
+
+    let mut emoticons: Vec<EmoticonDeclaration> = Vec::new();
+    for (shortcut, mxc) in candidates {
+        if emoticons.iter().any(|e| e.shortcut == shortcut) {
+            continue;
+        }
+
+        match emoticon_to_msn_object(client, &client_data.inner.emoticon_store, creator, &mxc).await {
+            Ok(msn_object) => {
+                emoticons.push(EmoticonDeclaration { shortcut, msn_object });
+            },
+            Err(err) => {
+                warn!("SYNC|MESSAGES: Couldn't convert emoticon {} to an MSNObject: {}", &mxc, err);
+            }
+        }
+    }
+
+    EmoticonMessageContent::new(emoticons)
+}
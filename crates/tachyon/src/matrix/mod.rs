@@ -5,3 +5,10 @@ pub mod oim;
 pub mod memberships;
 pub mod msn_user_resolver;
 pub mod events;
+pub mod emoticons;
+pub mod messages;
+pub mod winks;
+pub mod receipts;
+pub mod rendering;
+pub mod group_mapping;
+pub mod circles;
\ No newline at end of file
@@ -1,5 +1,4 @@
 use anyhow::{anyhow, Error};
-use log::warn;
 use matrix_sdk::{Client, Room};
 use matrix_sdk::crypto::vodozemac::base64_encode;
 use matrix_sdk::media::{MediaFormat, MediaRequest, MediaThumbnailSettings, MediaThumbnailSize};
@@ -16,6 +15,7 @@ use msnp::shared::models::presence_status::PresenceStatus;
 use msnp::soap::storage_service::msnstorage_datatypes::Profile;
 
 use crate::notification::client_store::ClientData;
+use crate::notification::msn_object_store::StoredMsnObject;
 use crate::shared::identifiers::MatrixIdCompatible;
 use crate::shared::traits::PresenceStateCompatible;
 
@@ -27,7 +27,9 @@ pub fn resolve_msn_user_lean(user_id: &UserId, client_data: &ClientData) -> MsnU
         return client_data.get_user_clone().expect("to be here");
     }
 
-    MsnUser::with_email_addr(EmailAddress::from_user_id(user_id))
+    let mut out = MsnUser::with_email_addr(EmailAddress::from_user_id(user_id));
+    out.nickname = client_data.inner.contact_nicknames.get(out.get_email_address().as_str()).map(|nickname| nickname.value().clone());
+    out
 }
 
 pub async fn resolve_msn_user_from_rm(room_member: &RoomMember, client_data: &ClientData, profile: bool, presence: bool) -> Result<MsnUser, anyhow::Error> {
@@ -37,6 +39,7 @@ pub async fn resolve_msn_user_from_rm(room_member: &RoomMember, client_data: &Cl
     Ok(out)
 }
 
+/* Only cached display pictures are set, the others are prefetched in the background after ILN */
 pub async fn resolve_msn_user_from_presence_event(presence_event: PresenceEvent, client_data: &ClientData) -> MsnUser {
 
     let user_id = presence_event.sender;
@@ -45,19 +48,9 @@ pub async fn resolve_msn_user_from_presence_event(presence_event: PresenceEvent,
     msn_user.display_name = presence_event.content.displayname;
     msn_user.status = PresenceStatus::from_presence_state(presence_event.content.presence);
 
-    match presence_event.content.avatar_url {
-        Some(avatar_url) => {
-            let client = client_data.get_matrix_client();
-            let result = avatar_mxid_to_msn_object(&client, msn_user.get_email_address(), &avatar_url).await;
-            match result {
-                Ok(avatar) => {
-                    msn_user.display_picture =  Some(avatar);
-                }
-                Err(e) => {warn!("Could not fetch avatar for user: {} {}", &user_id, e)}
-            }
-        },
-        _ => {}
-    };
+    if let Some(avatar_url) = presence_event.content.avatar_url {
+        msn_user.display_picture = get_cached_avatar_msn_object(client_data, msn_user.get_email_address(), &avatar_url);
+    }
 
     msn_user
 }
@@ -160,15 +153,26 @@ async fn resolve_room(user_id: &UserId, room: Option<Room>, client: &Client) ->
     return Ok(None);
 }
 
-pub async fn avatar_mxid_to_msn_object(client: &Client, email_address: &EmailAddress, avatar_mxc: &MxcUri) -> Result<MsnObject, anyhow::Error> {
-    match get_avatar_bytes(client, &avatar_mxc).await {
-        Ok(avatar_bytes) => {
-            Ok(avatar_to_msn_obj(&avatar_bytes, email_address, &avatar_mxc))
-        },
-        Err(e) => {
-            Err(e)
-        }
+/* Display pictures are only downloaded once, the MsnObject is rebuilt from the store afterwards */
+pub async fn avatar_mxid_to_msn_object(client_data: &ClientData, email_address: &EmailAddress, avatar_mxc: &MxcUri) -> Result<MsnObject, anyhow::Error> {
+    if let Some(msn_object) = get_cached_avatar_msn_object(client_data, email_address, avatar_mxc) {
+        return Ok(msn_object);
     }
+
+    let avatar_bytes = get_avatar_bytes(&client_data.get_matrix_client(), avatar_mxc).await?;
+    let msn_object = avatar_to_msn_obj(&avatar_bytes, email_address, avatar_mxc);
+
+    client_data.inner.msn_object_store.insert(avatar_mxc, StoredMsnObject {
+        size: msn_object.size,
+        sha1d: msn_object.sha1d.clone(),
+    });
+
+    Ok(msn_object)
+}
+
+pub fn get_cached_avatar_msn_object(client_data: &ClientData, email_address: &EmailAddress, avatar_mxc: &MxcUri) -> Option<MsnObject> {
+    let stored = client_data.inner.msn_object_store.get(avatar_mxc)?;
+    Some(MSNObjectFactory::get_display_picture_from_metadata(stored.size, stored.sha1d, email_address, get_avatar_location(avatar_mxc), FriendlyName::default()))
 }
 
 pub async fn get_avatar_bytes(client: &Client, avatar_mxc: &MxcUri) -> Result<Vec<u8>, anyhow::Error> {
@@ -180,6 +184,10 @@ pub async fn get_avatar_bytes(client: &Client, avatar_mxc: &MxcUri) -> Result<Ve
 }
 
 pub fn avatar_to_msn_obj(avatar_bytes: &Vec<u8>, msn_addr: &EmailAddress, avatar_mxc: &MxcUri) -> MsnObject {
-    let base64_mxc = base64_encode(avatar_mxc.to_string());
-    return MSNObjectFactory::get_display_picture(&avatar_bytes, msn_addr,format!("{}.tmp", base64_mxc), FriendlyName::default());
+    return MSNObjectFactory::get_display_picture(&avatar_bytes, msn_addr, get_avatar_location(avatar_mxc), FriendlyName::default());
+}
+
+/* The location has to stay the same for the SHA1C to be stable */
+fn get_avatar_location(avatar_mxc: &MxcUri) -> String {
+    format!("{}.tmp", base64_encode(avatar_mxc.to_string()))
 }
\ No newline at end of file
@@ -7,9 +7,10 @@ use matrix_sdk::room::MessagesOptions;
 use matrix_sdk::ruma::api::client::filter::RoomEventFilter;
 use matrix_sdk::ruma::events::{AnyMessageLikeEvent, AnySyncMessageLikeEvent, AnySyncTimelineEvent, AnyTimelineEvent, EventContent, MessageLikeEvent, MessageLikeEventType, SyncMessageLikeEvent};
 use matrix_sdk::ruma::events::room::message::{FormattedBody, MessageType, RoomMessageEvent, SyncRoomMessageEvent};
-use matrix_sdk::ruma::{EventId, OwnedRoomId, OwnedUserId, uint, UserId};
+use matrix_sdk::ruma::{EventId, OwnedEventId, OwnedRoomId, OwnedUserId, RoomId, uint, UserId};
 use matrix_sdk::ruma::events::room::member::MembershipState;
 use matrix_sdk::ruma::events::StateEvent::{Original, Redacted};
+use matrix_sdk::ruma::serde::Raw;
 use matrix_sdk::sync::SyncResponse;
 
 use thiserror::Error;
@@ -21,6 +22,7 @@ use msnp::shared::models::oim::OIM;
 use msnp::shared::models::uuid::Uuid;
 use msnp::shared::payload::msg::raw_msg_payload::factories::RawMsgPayloadFactory;
 use msnp::shared::payload::msg::raw_msg_payload::MsgContentType;
+use crate::matrix::messages::get_dm_route;
 use crate::notification::client_store::{ClientData, ClientStoreError};
 use crate::shared::identifiers::MatrixIdCompatible;
 
@@ -39,106 +41,47 @@ pub enum OIMError {
     NativeDatetimeConversionError{ source: anyhow::Error}
 }
 
+/* Messages missed since the last sign-in are served as OIMs through the RSI service.
+We walk the gap between the previous sync token & the first timeline batch, then the batch itself. */
 pub async fn handle_oims(client: Client, response: SyncResponse, mut client_data: ClientData, notif_sender: Sender<NotificationServerCommand>, first_sync_token: Option<String>) -> Result<(), OIMError>{
     let me_email_addr = client_data.get_user()?.endpoint_id.email_addr.clone();
 
-    for (room_id, room) in &response.rooms.join {
+    for (room_id, joined_room) in &response.rooms.join {
+        let Some(room) = client.get_room(room_id) else {
+            continue;
+        };
+
         let room_uuid = Uuid::from_seed(room_id.to_string().as_str());
         let mut seq_num = 1;
 
-        if let Some(prev_batch) = room.timeline.prev_batch.as_ref() {
-            //We missed some events
-            if let Some(room) = client.get_room(&room_id) {
-                let mut config = get_message_options(first_sync_token.clone(), Some(prev_batch.clone()));
-
-                while {
-                    let messages = room.messages(config).await?;
-                    config = get_message_options(messages.end, Some(prev_batch.clone()));
-
-                    for event in messages.chunk {
-
-                        if let Ok(AnySyncTimelineEvent::MessageLike(e)) = event.raw().deserialize() {
-                         //   debug!("Loopty_LOOP: {:?}", e);
-
-                            match e {
-                                AnySyncMessageLikeEvent::RoomMessage(ref room_message) => {
-                                    let oim = match room_message {
-                                        SyncMessageLikeEvent::Original(ref original_event) => {
-
-                                            let member = room.get_member(e.sender()).await?.expect("to be here");
-                                            let display_name = member.display_name().map(|e| e.to_string());
-
-                                            handle_original_message(&original_event.content.msgtype, &room_id, room_uuid.clone(),e.event_id(), original_event.origin_server_ts.0.into(), e.sender(), display_name, seq_num, me_email_addr.clone())?
-                                        },
-                                        SyncMessageLikeEvent::Redacted(ref redacted) => {
-                                            None
-                                        }
-                                    };
-
-                                    match oim {
-                                        None => {}
-                                        Some(oim) => {
-                                            client_data.add_oim(oim);
-                                            seq_num+=1;
-                                        }
-                                    }
-                                },
-                                _ => {}
-                            }
-                        }
+        //We missed some events, first sync ever doesn't have a gap to fill
+        if let (Some(from), Some(prev_batch), true) = (first_sync_token.as_ref(), joined_room.timeline.prev_batch.as_ref(), joined_room.timeline.limited) {
+            let mut config = get_message_options(Some(from.clone()), Some(prev_batch.clone()));
 
+            while {
+                let messages = room.messages(config).await?;
+                config = get_message_options(messages.end, Some(prev_batch.clone()));
 
+                for event in messages.chunk {
+                    if let Some(oim) = event_to_oim(event.raw(), &room, &client, &client_data, room_uuid.clone(), seq_num, &me_email_addr).await? {
+                        client_data.add_oim(oim);
+                        seq_num += 1;
                     }
+                }
 
-                    config.from != None
-                } {};
-            };
-        };
-
+                config.from != None
+            } {};
+        }
 
-        for event in &room.timeline.events {
-
-            if let Ok(AnySyncTimelineEvent::MessageLike(e)) = event.raw().deserialize() {
-                debug!("{:?}", e);
-
-                match e {
-                    AnySyncMessageLikeEvent::RoomMessage(ref room_message) => {
-                        let oim = match room_message {
-                            SyncMessageLikeEvent::Original(ref original_event) => {
-
-                                let display_name = {
-                                    match client.get_room(&room_id) {
-                                        None => {
-                                            None
-                                        }
-                                        Some(room) => {
-                                            let member = room.get_member(e.sender()).await?.expect("to be here");
-                                            member.display_name().map(|e| e.to_string())
-                                        }
-                                    }
-                                };
-                                handle_original_message(&original_event.content.msgtype, &room_id, room_uuid.clone(),e.event_id(), original_event.origin_server_ts.0.into(), e.sender(), display_name, seq_num, me_email_addr.clone())?
-                            },
-                            SyncRoomMessageEvent::Redacted(ref redacted) => {
-                                None
-                            }
-                        };
-
-                        match oim {
-                            None => {}
-                            Some(oim) => {
-                                client_data.add_oim(oim);
-                                seq_num+=1;
-                            }
-                        }
-                    },
-                    _ => {}
-                }
+        for event in &joined_room.timeline.events {
+            if let Some(oim) = event_to_oim(event.raw(), &room, &client, &client_data, room_uuid.clone(), seq_num, &me_email_addr).await? {
+                client_data.add_oim(oim);
+                seq_num += 1;
             }
-
         }
     }
 
+    debug!("OIM: {} offline messages gathered", client_data.get_oims().len());
 
     let payload = if !client_data.get_oims().is_empty() { RawMsgPayloadFactory::get_initial_mail_data_too_large_notification() } else { RawMsgPayloadFactory::get_initial_mail_data_empty_notification() };
 
@@ -152,6 +95,36 @@ pub async fn handle_oims(client: Client, response: SyncResponse, mut client_data
 
 }
 
+/* Only messages sent by the contact of a DM can be OIMs */
+async fn event_to_oim(raw_event: &Raw<AnySyncTimelineEvent>, room: &Room, client: &Client, client_data: &ClientData, room_uuid: Uuid, seq_num: u32, me: &EmailAddress) -> Result<Option<OIM>, OIMError> {
+    let Ok(AnySyncTimelineEvent::MessageLike(AnySyncMessageLikeEvent::RoomMessage(SyncMessageLikeEvent::Original(original_event)))) = raw_event.deserialize() else {
+        return Ok(None);
+    };
+
+    match get_dm_route(&original_event.sender, room, client, client_data).await {
+        Ok(Some(_)) => {},
+        Ok(None) => {
+            return Ok(None);
+        },
+        Err(err) => {
+            warn!("OIM: Couldn't resolve DM for event {} in {}: {}", &original_event.event_id, room.room_id(), err);
+            return Ok(None);
+        }
+    }
+
+    let display_name = room.get_member(&original_event.sender).await?.and_then(|member| member.display_name().map(|e| e.to_string()));
+
+    handle_original_message(&original_event.content.msgtype, &room.room_id().to_owned(), room_uuid, &original_event.event_id, original_event.origin_server_ts.0.into(), &original_event.sender, display_name, seq_num, me.clone())
+}
+
+/* OIM message ids are {room_id}_{event_id}, event ids always start with a $ */
+pub fn parse_oim_message_id(message_id: &str) -> Option<(OwnedRoomId, OwnedEventId)> {
+    let (room_id, event_id) = message_id.split_once("_$")?;
+    let room_id = RoomId::parse(room_id).ok()?;
+    let event_id = EventId::parse(format!("${}", event_id)).ok()?;
+    Some((room_id, event_id))
+}
+
 pub fn handle_original_message(message_type: &MessageType, room_id: &OwnedRoomId, room_uuid: Uuid, event_id: &EventId, event_timestamp: i64, sender: &UserId, sender_display_name: Option<String>, seq_num: u32, me: EmailAddress) -> Result<Option<OIM>, OIMError>{
 
     Ok(match message_type {
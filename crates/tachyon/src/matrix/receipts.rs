@@ -0,0 +1,45 @@
+use log::debug;
+use matrix_sdk::Room;
+use matrix_sdk::room::Receipts;
+use matrix_sdk::ruma::api::client::receipt::create_receipt::v3::ReceiptType;
+use matrix_sdk::ruma::events::AnySyncMessageLikeEvent;
+use matrix_sdk::ruma::events::receipt::ReceiptThread;
+use matrix_sdk::ruma::OwnedEventId;
+
+use crate::notification::client_store::ClientData;
+
+pub fn handle_latest_event(event: &AnySyncMessageLikeEvent, room: &Room, client_data: &ClientData) {
+    client_data.inner.read_marker_store.set_latest_event(room.room_id().to_owned(), event.event_id().to_owned());
+}
+
+/* The user is active in the conversation, everything up to the latest event has been seen */
+pub async fn send_read_receipt(room: &Room, client_data: &ClientData) -> Result<(), anyhow::Error> {
+    let room_id = room.room_id().to_owned();
+    let Some(event_id) = client_data.inner.read_marker_store.get_unread_event(&room_id) else {
+        return Ok(());
+    };
+
+    send_read_receipt_for(room, event_id, client_data).await
+}
+
+/* A receipt on an event marks every event before it as read */
+pub async fn send_read_receipt_for(room: &Room, event_id: OwnedEventId, client_data: &ClientData) -> Result<(), anyhow::Error> {
+    debug!("RECEIPTS: Sending read receipt for {} in {}", &event_id, room.room_id());
+    room.send_single_receipt(ReceiptType::Read, ReceiptThread::Unthreaded, event_id.clone()).await?;
+    client_data.inner.read_marker_store.set_read(room.room_id().to_owned(), event_id);
+    Ok(())
+}
+
+/* The conversation window was closed, move the fully read marker along with the read receipt */
+pub async fn send_fully_read(room: &Room, client_data: &ClientData) -> Result<(), anyhow::Error> {
+    let room_id = room.room_id().to_owned();
+    let Some(event_id) = client_data.inner.read_marker_store.get_latest_event(&room_id) else {
+        return Ok(());
+    };
+
+    debug!("RECEIPTS: Moving fully read marker to {} in {}", &event_id, &room_id);
+    let receipts = Receipts::new().fully_read_marker(event_id.clone()).public_read_receipt(event_id.clone());
+    room.send_multiple_receipts(receipts).await?;
+    client_data.inner.read_marker_store.set_read(room_id, event_id);
+    Ok(())
+}
@@ -0,0 +1,101 @@
+const EXCERPT_MAX_CHARS: usize = 50;
+
+/* WLM can't replace a message it already displayed, the edit is shown as a new message */
+pub fn render_edit(new_body: &str) -> String {
+    format!("{} (edited)", new_body)
+}
+
+/* Quotes only keep the first line of the replied message */
+pub fn render_reply(quoted_sender: &str, quoted_body: &str, body: &str) -> String {
+    format!("> {}: {}\r\n{}", quoted_sender, excerpt(quoted_body), body)
+}
+
+/* Action messages are a single line, rendered like IRC: "bob waves" */
+pub fn render_emote(sender_name: &str, body: &str) -> String {
+    format!("{} {}", sender_name, single_line(body))
+}
+
+/* Bot notices are sent as action messages, they are greyed out and don't pop a toast */
+pub fn render_notice(sender_name: &str, body: &str) -> String {
+    format!("[{}] {}", sender_name, single_line(body))
+}
+
+/* P2P file transfers aren't possible with every client, media are sent as download links */
+pub fn render_media(kind: &str, name: &str, link: &str) -> String {
+    format!("Sent {}: {}\r\n{}", kind, single_line(name), link)
+}
+
+pub fn render_location(body: &str, geo_uri: &str) -> String {
+    let link = match parse_geo_uri(geo_uri) {
+        Some((latitude, longitude)) => format!("https://www.openstreetmap.org/?mlat={}&mlon={}", latitude, longitude),
+        None => geo_uri.to_string()
+    };
+
+    format!("Shared a location: {}\r\n{}", single_line(body), link)
+}
+
+pub fn render_reaction(key: &str, reacted_body: Option<&str>) -> String {
+    match reacted_body {
+        Some(reacted_body) => format!("reacted {} to \"{}\"", key, excerpt(reacted_body)),
+        None => format!("reacted {}", key)
+    }
+}
+
+/* Sent as an action message so it doesn't look like something the contact typed */
+pub fn render_redaction_notice(reason: Option<&str>) -> String {
+    match reason {
+        Some(reason) if !reason.trim().is_empty() => format!("deleted a message ({})", single_line(reason.trim())),
+        _ => "deleted a message".to_string()
+    }
+}
+
+/* Circles are the group room timeline, what can't be relayed there is answered in the conversation */
+pub fn render_unsupported_circle_message(message_type: &str) -> String {
+    format!("Your {} couldn't be sent, only text messages reach the circle", message_type.to_lowercase())
+}
+
+/* Reply bodies start with "> <@sender> quoted text" lines followed by an empty line */
+pub fn strip_reply_fallback(body: &str) -> String {
+    if !body.starts_with("> ") {
+        return body.to_string();
+    }
+
+    body.lines()
+        .skip_while(|line| line.starts_with('>'))
+        .skip_while(|line| line.trim().is_empty())
+        .collect::<Vec<&str>>()
+        .join("\r\n")
+}
+
+/* Used when the replied event can't be fetched, returns (sender, first quoted line) */
+pub fn parse_reply_fallback(body: &str) -> Option<(String, String)> {
+    let first_line = body.lines().next()?.strip_prefix("> ")?;
+    let first_line = first_line.strip_prefix("* ").unwrap_or(first_line);
+    let (sender, quoted) = first_line.strip_prefix('<')?.split_once("> ")?;
+    Some((sender.to_string(), quoted.to_string()))
+}
+
+pub fn excerpt(body: &str) -> String {
+    let first_line = body.lines().find(|line| !line.trim().is_empty()).unwrap_or_default().trim();
+
+    if first_line.chars().count() <= EXCERPT_MAX_CHARS {
+        return first_line.to_string();
+    }
+
+    let mut out: String = first_line.chars().take(EXCERPT_MAX_CHARS).collect();
+    out.push_str("...");
+    out
+}
+
+fn single_line(body: &str) -> String {
+    body.lines().map(|line| line.trim()).filter(|line| !line.is_empty()).collect::<Vec<&str>>().join(" ")
+}
+
+/* geo:48.8584,2.2945;u=35 */
+fn parse_geo_uri(geo_uri: &str) -> Option<(f64, f64)> {
+    let coordinates = geo_uri.strip_prefix("geo:")?.split(';').next()?;
+    let mut parts = coordinates.split(',');
+    let latitude = parts.next()?.trim().parse::<f64>().ok()?;
+    let longitude = parts.next()?.trim().parse::<f64>().ok()?;
+    Some((latitude, longitude))
+}
@@ -4,19 +4,22 @@ use std::mem;
 use std::time::Duration;
 
 use anyhow::{anyhow, Error};
-use log::{debug, info};
-use matrix_sdk::{Client, LoopCtrl, Room};
+use log::{debug, error, info, warn};
+use matrix_sdk::{Client, LoopCtrl, Room, RoomState};
 use matrix_sdk::config::SyncSettings;
 use matrix_sdk::event_handler::Ctx;
 use matrix_sdk::ruma::{OwnedMxcUri, OwnedUserId};
 use matrix_sdk::ruma::api::client::filter::FilterDefinition;
 use matrix_sdk::ruma::api::client::sync::sync_events::v3::Filter;
-use matrix_sdk::ruma::events::AnyGlobalAccountDataEvent;
+use matrix_sdk::ruma::events::{AnyGlobalAccountDataEvent, AnySyncMessageLikeEvent, OriginalSyncMessageLikeEvent};
 use matrix_sdk::ruma::events::direct::DirectEvent;
 use matrix_sdk::ruma::events::GlobalAccountDataEventType::IgnoredUserList;
 use matrix_sdk::ruma::events::ignored_user_list::IgnoredUserListEvent;
 use matrix_sdk::ruma::events::presence::PresenceEvent;
-use matrix_sdk::ruma::events::room::member::{RoomMemberEvent, SyncRoomMemberEvent};
+use matrix_sdk::ruma::events::reaction::OriginalSyncReactionEvent;
+use matrix_sdk::ruma::events::room::member::{RoomMemberEvent, StrippedRoomMemberEvent, SyncRoomMemberEvent};
+use matrix_sdk::ruma::events::room::message::OriginalSyncRoomMessageEvent;
+use matrix_sdk::ruma::events::room::redaction::OriginalSyncRoomRedactionEvent;
 use matrix_sdk::ruma::presence::PresenceState;
 use matrix_sdk::ruma::serde::Raw;
 use matrix_sdk::sync::SyncResponse;
@@ -25,6 +28,7 @@ use tokio::sync::mpsc::Sender;
 
 use msnp::msnp::notification::command::command::NotificationServerCommand;
 use msnp::msnp::notification::command::iln::IlnServer;
+use msnp::msnp::notification::command::nln::NlnServer;
 use msnp::msnp::notification::command::not::factories::NotificationFactory;
 use msnp::msnp::notification::command::not::NotServer;
 use msnp::shared::models::email_address::EmailAddress;
@@ -34,9 +38,16 @@ use msnp::shared::models::presence_status::PresenceStatus;
 use msnp::shared::models::role_list::RoleList;
 use msnp::soap::abch::msnab_datatypes::{BaseMember, ContactType, ContactTypeEnum, MemberState};
 
-use crate::matrix::memberships::{handle_joined_room_member_event, handle_memberships};
-use crate::matrix::msn_user_resolver::{avatar_to_msn_obj, get_avatar_bytes, resolve_msn_user_from_presence_event};
+use crate::matrix::circles::get_circle_roster_updates;
+use crate::matrix::events::contact_properties::fetch_contact_properties;
+use crate::matrix::events::privacy_settings::fetch_privacy_settings;
+use crate::matrix::events::wink_sticker::WinkStickerEventContent;
+use crate::matrix::messages::{handle_reaction, handle_redaction, handle_room_message, handle_wink_sticker};
+use crate::matrix::memberships::{enforce_allow_list, handle_invite_room_member_event, handle_joined_room_member_event, handle_leave_room_member_event, handle_memberships, queue_server_membership_pushes, seed_server_memberships};
+use crate::matrix::msn_user_resolver::{avatar_mxid_to_msn_object, avatar_to_msn_obj, get_avatar_bytes, resolve_msn_user_from_presence_event};
 use crate::matrix::oim::handle_oims;
+use crate::matrix::receipts::handle_latest_event;
+use crate::notification::address_book_store::{AddressBookChanges, AppliedChanges};
 use crate::notification::client_store::ClientData;
 use crate::shared::identifiers::MatrixIdCompatible;
 use crate::shared::traits::PresenceStateCompatible;
@@ -47,7 +58,7 @@ struct TachyonContext {
     client_data: ClientData
 }
 
-pub async fn initial_sync(tr_id: u128, client_data: &ClientData) -> Result<(Vec<IlnServer>, Vec<NotServer>), anyhow::Error> {
+pub async fn initial_sync(tr_id: u128, client_data: &ClientData) -> Result<(Vec<IlnServer>, Vec<NotServer>, Vec<(MsnUser, OwnedMxcUri)>), anyhow::Error> {
 
     let me_msn_user = client_data.get_user_clone()?;
     let client = client_data.get_matrix_client();
@@ -67,55 +78,50 @@ pub async fn initial_sync(tr_id: u128, client_data: &ClientData) -> Result<(Vec<
 
     let response = client.sync_once(settings.clone()).await?;
 
-    let (mut contacts, mut memberships, mut circle_members) = handle_memberships(client, response.clone()).await?;
+    /* ILN comes before the address book is fetched, nicknames have to be known already */
+    client_data.refresh_contact_nicknames(&fetch_contact_properties(&client).await?);
+    client_data.set_allow_list_only(fetch_privacy_settings(&client).await?.allow_list_only);
 
-    let mut notifications = Vec::new();
-
-    if !contacts.is_empty() || !memberships.is_empty() {
-        {
-            let mut contacts_mtx = client_data.inner.soap_holder.contacts.lock().unwrap();
-            let mut memberships_mtx = client_data.inner.soap_holder.memberships.lock().unwrap();
-            contacts_mtx.append(&mut contacts);
-            memberships_mtx.append(&mut memberships);
+    let mut changes = handle_memberships(client.clone(), response.clone()).await?;
+    enforce_allow_list(&mut changes, &client, client_data).await?;
+    let applied = client_data.inner.address_book_store.apply(changes);
+    seed_server_memberships(client_data);
 
-            notifications.push(NotServer {
-                payload: NotificationFactory::get_abch_updated(&me_msn_user.uuid, me_msn_user.get_email_address().as_str()),
-            });
+    let mut notifications = Vec::new();
 
-        }
+    if applied.address_book_changed {
+        notifications.push(NotServer {
+            payload: NotificationFactory::get_abch_updated(&me_msn_user.uuid, me_msn_user.get_email_address().as_str()),
+        });
     }
 
-    if !circle_members.is_empty() {
-        for (circle_id, mut members) in circle_members.drain() {
-
-            match client_data.inner.soap_holder.circle_contacts.get_mut(&circle_id) {
-                None => {
-                    client_data.inner.soap_holder.circle_contacts.insert(circle_id.clone(), members);
-                }
-                Some(mut circle_members) => {
-                    circle_members.append(&mut members);
-                }
-            }
-
-            notifications.push(NotServer {
-                payload: NotificationFactory::get_circle_updated(&me_msn_user.uuid, me_msn_user.get_email_address().as_str(), &circle_id)
-            });
-
-        }
+    for circle_id in applied.changed_circles {
+        notifications.push(NotServer {
+            payload: NotificationFactory::get_circle_updated(&me_msn_user.uuid, me_msn_user.get_email_address().as_str(), &circle_id)
+        });
     }
 
-    let iln = handle_initial_presence(tr_id, response.presence, client_data).await?;
+    let (iln, pending_display_pictures) = handle_initial_presence(tr_id, response.presence, client_data).await?;
 
-    Ok((iln, notifications))
+    Ok((iln, notifications, pending_display_pictures))
 }
 
-pub async fn handle_initial_presence(tr_id: u128, presence: Vec<Raw<PresenceEvent>>, client_data: &ClientData) -> Result<Vec<IlnServer>, anyhow::Error> {
+/* Also returns the contacts whose display picture isn't in the MsnObject store yet */
+pub async fn handle_initial_presence(tr_id: u128, presence: Vec<Raw<PresenceEvent>>, client_data: &ClientData) -> Result<(Vec<IlnServer>, Vec<(MsnUser, OwnedMxcUri)>), anyhow::Error> {
     let mut out = Vec::with_capacity(presence.len());
+    let mut pending_display_pictures = Vec::new();
 
     for current in presence {
         let presence_event = current.deserialize()?;
+        let avatar_url = presence_event.content.avatar_url.clone();
         let msn_user = resolve_msn_user_from_presence_event(presence_event, client_data).await;
 
+        if let Some(avatar_url) = avatar_url {
+            if msn_user.display_picture.is_none() {
+                pending_display_pictures.push((msn_user.clone(), avatar_url));
+            }
+        }
+
         let target_user = msn_user.get_network_id_email();
         let display_name = msn_user.compute_display_name().to_string();
         let iln = IlnServer{
@@ -131,7 +137,41 @@ pub async fn handle_initial_presence(tr_id: u128, presence: Vec<Raw<PresenceEven
 
         out.push(iln);
     }
-   Ok(out)
+   Ok((out, pending_display_pictures))
+}
+
+/* Runs after ILN, the display pictures are downloaded one by one and the contacts updated with NLN */
+pub async fn prefetch_display_pictures(pending_display_pictures: Vec<(MsnUser, OwnedMxcUri)>, client_data: ClientData, notif_sender: Sender<NotificationServerCommand>) {
+    for (mut msn_user, avatar_url) in pending_display_pictures {
+        match avatar_mxid_to_msn_object(&client_data, msn_user.get_email_address(), &avatar_url).await {
+            Ok(avatar) => {
+                msn_user.display_picture = Some(avatar);
+            },
+            Err(err) => {
+                warn!("SYNC|PRESENCE: Could not fetch avatar for user: {} {}", msn_user.get_email_address(), err);
+                continue;
+            }
+        }
+
+        /* Offline contacts get the display picture at their next ILN */
+        if msn_user.status == PresenceStatus::FLN {
+            continue;
+        }
+
+        let nln = NlnServer {
+            presence_status: msn_user.status.clone(),
+            target_user: msn_user.get_network_id_email(),
+            via: None,
+            display_name: msn_user.compute_display_name().to_string(),
+            client_capabilities: msn_user.capabilities.clone(),
+            avatar: msn_user.display_picture.clone(),
+            badge_url: None,
+        };
+
+        if notif_sender.send(NotificationServerCommand::NLN(nln)).await.is_err() {
+            return;
+        }
+    }
 }
 
 pub async fn start_sync_task(client: Client, notif_sender: Sender<NotificationServerCommand>, mut client_data: ClientData, mut kill_signal: broadcast::Receiver<()>) {
@@ -155,39 +195,70 @@ pub async fn start_sync_task(client: Client, notif_sender: Sender<NotificationSe
 
     client.add_event_handler({ |event: SyncRoomMemberEvent, room: Room, client: Client, context: Ctx<TachyonContext>| async move {
         let client_data = &context.client_data;
+        let me_msn_user = client_data.get_user_clone().expect("to be here");
 
-        let mut contacts = Vec::new();
-        let mut memberships = VecDeque::new();
-        let mut circle_members: HashMap<String, Vec<ContactType>> = HashMap::new();
+        let mut changes = AddressBookChanges::default();
 
         let me = client.user_id().expect("to be here");
 
-        handle_joined_room_member_event(&event, &room, me, &client, &mut contacts, &mut memberships, &mut circle_members).await;
+        let result = match room.state() {
+            RoomState::Left => handle_leave_room_member_event(&event, &room, me, &client, &mut changes).await,
+            _ => handle_joined_room_member_event(&event, &room, me, &client, &mut changes).await
+        };
 
-        if !contacts.is_empty() || !memberships.is_empty() {
-            {
-                let mut contacts_mtx = client_data.inner.soap_holder.contacts.lock().unwrap();
-                let mut memberships_mtx = client_data.inner.soap_holder.memberships.lock().unwrap();
-                contacts_mtx.append(&mut contacts);
-                memberships_mtx.append(&mut memberships);
-            }
+        if let Err(err) = result {
+            error!("SYNC|MEMBERSHIPS: An error has occured handling a member event: {}", err);
         }
 
-        if !circle_members.is_empty() {
-            for (circle_id, mut members) in circle_members.drain() {
-                match client_data.inner.soap_holder.circle_contacts.get_mut(&circle_id) {
-                    None => {
-                        client_data.inner.soap_holder.circle_contacts.insert(circle_id.clone(), members);
-                    }
-                    Some(mut circle_members) => {
-                        circle_members.append(&mut members);
-                    }
+        if let Err(err) = queue_server_membership_pushes(&changes, Some(&room), client_data).await {
+            error!("SYNC|MEMBERSHIPS: An error has occured preparing contact list pushes: {}", err);
+        }
+
+        let applied = client_data.inner.address_book_store.apply(changes);
+        client_data.send_contact_list_pushes().await;
+        send_address_book_notifications(applied, &me_msn_user, &context.notif_sender).await;
+
+        match get_circle_roster_updates(&event, &room, me, client_data) {
+            Ok(roster_updates) => {
+                for roster_update in roster_updates {
+                    let _result = context.notif_sender.send(NotificationServerCommand::NFY(roster_update)).await;
                 }
+            },
+            Err(err) => {
+                error!("SYNC|CIRCLES: An error has occured preparing circle roster updates: {}", err);
             }
         }
 
     }});
 
+    client.add_event_handler({ |event: StrippedRoomMemberEvent, room: Room, client: Client, context: Ctx<TachyonContext>| async move {
+        let client_data = &context.client_data;
+        let me_msn_user = client_data.get_user_clone().expect("to be here");
+
+        let mut changes = AddressBookChanges::default();
+
+        let me = client.user_id().expect("to be here");
+
+        if let Err(err) = handle_invite_room_member_event(&event, room.room_id(), me, &client, &mut changes).await {
+            error!("SYNC|MEMBERSHIPS: An error has occured handling an invite event: {}", err);
+        }
+
+        if let Err(err) = enforce_allow_list(&mut changes, &client, client_data).await {
+            error!("SYNC|MEMBERSHIPS: An error has occured enforcing the Allow list: {}", err);
+        }
+
+        if let Err(err) = queue_server_membership_pushes(&changes, Some(&room), client_data).await {
+            error!("SYNC|MEMBERSHIPS: An error has occured preparing contact list pushes: {}", err);
+        }
+
+        let applied = client_data.inner.address_book_store.apply(changes);
+        client_data.send_contact_list_pushes().await;
+        send_address_book_notifications(applied, &me_msn_user, &context.notif_sender).await;
+    }});
+
+    client.add_event_handler({ |event: AnySyncMessageLikeEvent, room: Room, context: Ctx<TachyonContext>| async move {
+        handle_latest_event(&event, &room, &context.client_data);
+    }});
 
     // client.add_event_handler({ |event: DirectEvent, client: Client, context: Ctx<TachyonContext>| async move{
     //
@@ -216,9 +287,14 @@ pub async fn start_sync_task(client: Client, notif_sender: Sender<NotificationSe
             let notif_sender_cloned = notif_sender.clone();
             let sync_token_clone = sync_token.clone();
 
-            // tokio::spawn(async move{
-            //     handle_oims(client_cloned, response_cloned, client_data_cloned, notif_sender_cloned, sync_token_clone).await
-            // });
+            tokio::spawn(async move{
+                if let Err(err) = handle_oims(client_cloned, response_cloned, client_data_cloned, notif_sender_cloned, sync_token_clone).await {
+                    error!("SYNC|OIM: An error has occured gathering offline messages: {}", err);
+                }
+            });
+
+            //Messages from the first sync are delivered as OIMs
+            register_message_handlers(&client);
 
         }
 
@@ -235,27 +311,6 @@ pub async fn start_sync_task(client: Client, notif_sender: Sender<NotificationSe
             info!("Synced finished....");
             info!("Dispatching Notifications...");
 
-                let send_ab_notify = {
-                    let contacts_mtx = client_data.inner.soap_holder.contacts.lock().unwrap();
-                    let memberships_mtx = client_data.inner.soap_holder.memberships.lock().unwrap();
-                    !contacts_mtx.is_empty() || !memberships_mtx.is_empty()
-                };
-
-            if send_ab_notify {
-                  //TODO make this less shit later
-                notif_sender.send(NotificationServerCommand::NOT(NotServer {
-                    payload: NotificationFactory::get_abch_updated(&me_msn_user.uuid, me_msn_user.get_email_address().as_str()),
-                    })).await;
-            }
-
-            {
-                for circle_id in client_data.inner.soap_holder.circle_contacts.iter().map(|m| m.key().clone()) {
-                     notif_sender.send(NotificationServerCommand::NOT(NotServer {
-                            payload: NotificationFactory::get_circle_updated(&me_msn_user.uuid, me_msn_user.get_email_address().as_str(), &circle_id)
-                    })).await;
-                }
-            }
-
 
             sync_token = Some(response.next_batch.clone());
 
@@ -267,4 +322,44 @@ pub async fn start_sync_task(client: Client, notif_sender: Sender<NotificationSe
 
         }
     }
-}
\ No newline at end of file
+}
+
+async fn send_address_book_notifications(applied: AppliedChanges, me_msn_user: &MsnUser, notif_sender: &Sender<NotificationServerCommand>) {
+    if applied.address_book_changed {
+        let _result = notif_sender.send(NotificationServerCommand::NOT(NotServer {
+            payload: NotificationFactory::get_abch_updated(&me_msn_user.uuid, me_msn_user.get_email_address().as_str()),
+        })).await;
+    }
+
+    for circle_id in applied.changed_circles {
+        let _result = notif_sender.send(NotificationServerCommand::NOT(NotServer {
+            payload: NotificationFactory::get_circle_updated(&me_msn_user.uuid, me_msn_user.get_email_address().as_str(), &circle_id)
+        })).await;
+    }
+}
+
+fn register_message_handlers(client: &Client) {
+    client.add_event_handler({ |event: OriginalSyncRoomMessageEvent, room: Room, client: Client, context: Ctx<TachyonContext>| async move {
+        if let Err(err) = handle_room_message(event, room, client, context.notif_sender.clone(), context.client_data.clone()).await {
+            error!("SYNC|MESSAGES: An error has occured handling a room message: {}", err);
+        }
+    }});
+
+    client.add_event_handler({ |event: OriginalSyncMessageLikeEvent<WinkStickerEventContent>, room: Room, client: Client, context: Ctx<TachyonContext>| async move {
+        if let Err(err) = handle_wink_sticker(event, room, client, context.notif_sender.clone(), context.client_data.clone()).await {
+            error!("SYNC|MESSAGES: An error has occured handling a wink sticker: {}", err);
+        }
+    }});
+
+    client.add_event_handler({ |event: OriginalSyncReactionEvent, room: Room, client: Client, context: Ctx<TachyonContext>| async move {
+        if let Err(err) = handle_reaction(event, room, client, context.notif_sender.clone(), context.client_data.clone()).await {
+            error!("SYNC|MESSAGES: An error has occured handling a reaction: {}", err);
+        }
+    }});
+
+    client.add_event_handler({ |event: OriginalSyncRoomRedactionEvent, room: Room, client: Client, context: Ctx<TachyonContext>| async move {
+        if let Err(err) = handle_redaction(event, room, client, context.notif_sender.clone(), context.client_data.clone()).await {
+            error!("SYNC|MESSAGES: An error has occured handling a redaction: {}", err);
+        }
+    }});
+}
@@ -0,0 +1,65 @@
+use anyhow::anyhow;
+use log::debug;
+use matrix_sdk::{Client, Room};
+use matrix_sdk::crypto::vodozemac::base64_encode;
+use matrix_sdk::media::{MediaFormat, MediaRequest};
+use matrix_sdk::ruma::MxcUri;
+use matrix_sdk::ruma::events::room::{ImageInfo, MediaSource};
+
+use msnp::shared::models::email_address::EmailAddress;
+use msnp::shared::models::msn_object::{FriendlyName, MSNObjectFactory, MsnObject};
+use msnp::shared::models::wink::Wink;
+
+use crate::matrix::events::wink_sticker::{WinkAttachment, WinkStickerEventContent};
+
+pub const WINK_CAB_MIME: &str = "application/vnd.ms-cab-compressed";
+
+/* Matrix clients can't play the flash animation, they get the thumbnail as a sticker */
+pub async fn send_wink_to_room(client: &Client, room: &Room, cab: Vec<u8>, msn_object: &MsnObject) -> Result<(), anyhow::Error> {
+    let wink = Wink::try_from(cab.as_slice())?;
+    let (thumbnail, thumbnail_file) = wink.get_thumbnail().ok_or(anyhow!("Wink has no thumbnail"))?;
+
+    let thumbnail_mime = thumbnail.mimetype.parse::<mime::Mime>().unwrap_or(mime::IMAGE_PNG);
+    let thumbnail_upload = client.media().upload(&thumbnail_mime, thumbnail_file.data.clone()).await?;
+
+    let cab_mime = WINK_CAB_MIME.parse::<mime::Mime>()?;
+    let cab_size = cab.len();
+    let cab_upload = client.media().upload(&cab_mime, cab).await?;
+
+    let mut info = ImageInfo::new();
+    info.mimetype = Some(thumbnail_mime.to_string());
+    info.size = Some((thumbnail_file.data.len() as u32).into());
+
+    let name = wink.content.name.clone();
+    let content = WinkStickerEventContent {
+        body: name.clone().unwrap_or("Wink".to_string()),
+        info,
+        url: thumbnail_upload.content_uri,
+        wink: Some(WinkAttachment {
+            url: cab_upload.content_uri,
+            mimetype: WINK_CAB_MIME.to_string(),
+            size: cab_size,
+            name,
+            contentid: msn_object.contentid.clone(),
+            stamp: msn_object.stamp.clone(),
+        }),
+    };
+
+    debug!("WINKS: Sending wink {} to {}", &content.body, room.room_id());
+    room.send(content).await?;
+    Ok(())
+}
+
+pub async fn get_wink_bytes(client: &Client, wink_mxc: &MxcUri) -> Result<Vec<u8>, anyhow::Error> {
+    let media_request = MediaRequest{ source: MediaSource::Plain(wink_mxc.to_owned()), format: MediaFormat::File };
+    client.media().get_media_content(&media_request, true).await.map_err(|e| anyhow!(e))
+}
+
+/* The location is the base64 encoded mxc of the cabinet, like emoticons & display pictures */
+pub async fn wink_to_msn_object(client: &Client, creator: &EmailAddress, wink: &WinkAttachment) -> Result<MsnObject, anyhow::Error> {
+    let cab = get_wink_bytes(client, &wink.url).await?;
+    let location = format!("{}.tmp", base64_encode(wink.url.to_string()));
+    let friendly = wink.name.as_ref().map(|name| FriendlyName::new(name)).unwrap_or_default();
+
+    Ok(MSNObjectFactory::get_wink(&cab, creator, location, friendly, wink.contentid.clone(), wink.stamp.clone()))
+}
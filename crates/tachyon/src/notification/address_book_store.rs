@@ -0,0 +1,608 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::sync::{Arc, Mutex};
+
+use chrono::{NaiveDateTime, TimeZone, Utc};
+use log::warn;
+use matrix_sdk::ruma::UserId;
+use serde::{Deserialize, Serialize};
+
+use msnp::shared::models::role_list::RoleList;
+use msnp::shared::models::uuid::Uuid;
+use msnp::soap::abch::ab_service::ab_find_contacts_paged::response::CircleData;
+use msnp::soap::abch::msnab_datatypes::{Annotation, ArrayOfAnnotation, BaseMember, CircleInverseInfoType, CircleRelationshipRole, ContactType, ContactTypeEnum, GroupType, MemberState, RelationshipState};
+
+use crate::shared::paths::get_user_data;
+use crate::shared::persist::DebouncedPersist;
+
+const LAST_CHANGE_FORMAT: &str = "%Y-%m-%dT%H:%M:%SZ";
+
+/* Deleted entries are kept this long so clients can get them as deltas, older caches need a fullsync */
+pub const TOMBSTONE_LIFETIME_SECS: i64 = 30 * 24 * 60 * 60;
+
+/* The address book as WLM knows it. Every entry carries the time of its last change,
+so delta requests only get what changed since the lastChange of the client cache. */
+pub struct AddressBookStore {
+    address_book: Arc<Mutex<AddressBook>>,
+    persist: DebouncedPersist
+}
+
+#[derive(Serialize, Deserialize)]
+struct AddressBook {
+    /* Older changes were pruned, clients with an older cache have to fullsync */
+    oldest_change: i64,
+    contacts_seeded: bool,
+    members_seeded: bool,
+    contacts: HashMap<String, StoredContact>,
+    members: HashMap<String, StoredMember>,
+    circles: HashMap<String, StoredCircle>,
+    circle_members: HashMap<String, HashMap<String, StoredCircleMember>>,
+    #[serde(default)]
+    space_groups: HashMap<String, StoredSpaceGroup>,
+    /* The groupIds WLM last got for each contact, Spaces and tags can change from any Matrix client */
+    #[serde(default)]
+    contact_group_ids: HashMap<String, Vec<String>>
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct StoredContact {
+    pub email: String,
+    pub display_name: String,
+    pub pending: bool,
+    pub deleted: bool,
+    pub last_change: i64
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum MemberRole {
+    Allow,
+    Block,
+    Reverse,
+    Pending
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct StoredMember {
+    pub email: String,
+    pub role: MemberRole,
+    pub invite_message: Option<String>,
+    pub deleted: bool,
+    pub last_change: i64
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct StoredCircleInvite {
+    pub inviter_email: String,
+    pub message: Option<String>
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct StoredCircle {
+    pub room_id: String,
+    pub display_name: String,
+    /* Set while we haven't answered the invite */
+    pub invite: Option<StoredCircleInvite>,
+    pub deleted: bool,
+    pub last_change: i64
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct StoredCircleMember {
+    pub email: String,
+    pub display_name: String,
+    pub pending: bool,
+    pub deleted: bool,
+    pub last_change: i64
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct StoredSpaceGroup {
+    pub room_id: String,
+    pub display_name: String,
+    pub deleted: bool,
+    pub last_change: i64
+}
+
+#[derive(Default)]
+pub struct AddressBookChanges {
+    pub contacts: Vec<StoredContact>,
+    pub members: Vec<StoredMember>,
+    pub circles: Vec<StoredCircle>,
+    pub circle_members: HashMap<String, Vec<StoredCircleMember>>
+}
+
+#[derive(Default)]
+pub struct AppliedChanges {
+    pub address_book_changed: bool,
+    pub changed_circles: Vec<String>
+}
+
+pub fn format_last_change(timestamp: i64) -> String {
+    Utc.timestamp_opt(timestamp, 0).single().unwrap_or_else(Utc::now).format(LAST_CHANGE_FORMAT).to_string()
+}
+
+/* lastChange is written in UTC everywhere in the ABCH responses, the Z suffix is optional for older clients */
+pub fn parse_last_change(last_change: &str) -> Option<i64> {
+    let naive = NaiveDateTime::parse_from_str(last_change, LAST_CHANGE_FORMAT)
+        .or_else(|_| NaiveDateTime::parse_from_str(last_change, "%Y-%m-%dT%H:%M:%S%.fZ"))
+        .or_else(|_| NaiveDateTime::parse_from_str(last_change, "%Y-%m-%dT%H:%M:%S%.f"))
+        .ok()?;
+
+    Some(Utc.from_utc_datetime(&naive).timestamp())
+}
+
+fn get_member_key(role: MemberRole, email: &str) -> String {
+    format!("{}\\{}", role.to_role_list(), email)
+}
+
+fn now() -> i64 {
+    Utc::now().timestamp()
+}
+
+impl MemberRole {
+    pub fn to_role_list(&self) -> RoleList {
+        match self {
+            MemberRole::Allow => RoleList::Allow,
+            MemberRole::Block => RoleList::Block,
+            MemberRole::Reverse => RoleList::Reverse,
+            MemberRole::Pending => RoleList::Pending
+        }
+    }
+}
+
+impl StoredContact {
+    pub fn new(email: &str, display_name: &str, pending: bool, deleted: bool) -> Self {
+        Self { email: email.to_string(), display_name: display_name.to_string(), pending, deleted, last_change: now() }
+    }
+
+    pub fn to_contact_type(&self) -> ContactType {
+        let contact_type = if self.pending { ContactTypeEnum::LivePending } else { ContactTypeEnum::Live };
+        let mut contact = ContactType::new(&Uuid::from_seed(&self.email), &self.email, &self.display_name, contact_type, self.deleted);
+        contact.last_change = Some(format_last_change(self.last_change));
+        contact
+    }
+
+    fn same_as(&self, other: &StoredContact) -> bool {
+        self.display_name == other.display_name && self.pending == other.pending && self.deleted == other.deleted
+    }
+}
+
+impl StoredMember {
+    pub fn new(email: &str, role: MemberRole, deleted: bool) -> Self {
+        Self { email: email.to_string(), role, invite_message: None, deleted, last_change: now() }
+    }
+
+    pub fn new_pending(email: &str, invite_message: &str) -> Self {
+        Self { email: email.to_string(), role: MemberRole::Pending, invite_message: Some(invite_message.to_string()), deleted: false, last_change: now() }
+    }
+
+    pub fn to_base_member(&self) -> BaseMember {
+        let mut member = BaseMember::new_passport_member(&Uuid::from_seed(&self.email), &self.email, MemberState::Accepted, self.role.to_role_list(), self.deleted);
+        member.last_changed = Some(format_last_change(self.last_change));
+
+        if let Some(invite_message) = self.invite_message.as_ref() {
+            member.display_name = Some(self.email.clone());
+            member.annotations = Some(ArrayOfAnnotation { annotation: vec![Annotation::new_invite(invite_message)] });
+        }
+
+        member
+    }
+
+    fn key(&self) -> String {
+        get_member_key(self.role, &self.email)
+    }
+
+    fn same_as(&self, other: &StoredMember) -> bool {
+        self.invite_message == other.invite_message && self.deleted == other.deleted
+    }
+}
+
+impl StoredCircle {
+    pub fn new(room_id: &str, display_name: &str, invite: Option<StoredCircleInvite>, deleted: bool) -> Self {
+        Self { room_id: room_id.to_string(), display_name: display_name.to_string(), invite, deleted, last_change: now() }
+    }
+
+    pub fn get_circle_id(&self) -> String {
+        Uuid::from_seed(&self.room_id).to_string()
+    }
+
+    pub fn to_circle_data(&self) -> CircleData {
+        let (state, inverse_state, role, inverse_role) = match self.invite {
+            None => (RelationshipState::Accepted, RelationshipState::Accepted, CircleRelationshipRole::None, CircleRelationshipRole::Member),
+            Some(_) => (RelationshipState::WaitingResponse, RelationshipState::WaitingResponse, CircleRelationshipRole::StatePendingOutbound, CircleRelationshipRole::StatePendingOutbound)
+        };
+
+        let mut contact = ContactType::new_circle(&self.room_id, &self.display_name, self.deleted, state, role);
+        contact.last_change = Some(format_last_change(self.last_change));
+
+        if let Some(invite) = self.invite.as_ref() {
+            let network_info = contact.contact_info.as_mut()
+                .and_then(|contact_info| contact_info.network_info_list.as_mut())
+                .and_then(|network_info_list| network_info_list.network_info.get_mut(0));
+
+            if let Some(network_info) = network_info {
+                network_info.inviter_message = invite.message.clone();
+                network_info.inviter_email = Some(invite.inviter_email.clone());
+                network_info.inviter_cid = Uuid::from_seed(&invite.inviter_email).to_decimal_cid() as u64;
+                network_info.inviter_name = Some(invite.inviter_email.clone());
+            }
+        }
+
+        let inverse_info = CircleInverseInfoType::new(self.get_circle_id(), self.display_name.clone(), self.deleted, inverse_role, inverse_state);
+        CircleData { contact, inverse_info }
+    }
+
+    fn same_as(&self, other: &StoredCircle) -> bool {
+        self.display_name == other.display_name && self.invite == other.invite && self.deleted == other.deleted
+    }
+}
+
+impl StoredCircleMember {
+    pub fn new(email: &str, display_name: &str, pending: bool, deleted: bool) -> Self {
+        Self { email: email.to_string(), display_name: display_name.to_string(), pending, deleted, last_change: now() }
+    }
+
+    pub fn to_contact_type(&self) -> ContactType {
+        let (contact_type, state) = if self.pending { (ContactTypeEnum::LivePending, RelationshipState::WaitingResponse) } else { (ContactTypeEnum::Live, RelationshipState::Accepted) };
+        let mut contact = ContactType::new_circle_member_contact(&Uuid::from_seed(&self.email), &self.email, &self.display_name, contact_type, state, CircleRelationshipRole::Member, self.deleted);
+        contact.last_change = Some(format_last_change(self.last_change));
+        contact
+    }
+
+    fn same_as(&self, other: &StoredCircleMember) -> bool {
+        self.display_name == other.display_name && self.pending == other.pending && self.deleted == other.deleted
+    }
+}
+
+impl StoredSpaceGroup {
+    pub fn new(room_id: &str, display_name: &str) -> Self {
+        Self { room_id: room_id.to_string(), display_name: display_name.to_string(), deleted: false, last_change: now() }
+    }
+
+    pub fn get_group_id(&self) -> String {
+        Uuid::from_seed(&self.room_id).to_string()
+    }
+
+    pub fn to_group_type(&self) -> GroupType {
+        GroupType::new(&self.get_group_id(), &self.display_name, false, self.deleted, &format_last_change(self.last_change))
+    }
+
+    fn same_as(&self, other: &StoredSpaceGroup) -> bool {
+        self.display_name == other.display_name && self.deleted == other.deleted
+    }
+}
+
+impl AddressBookChanges {
+    pub fn is_empty(&self) -> bool {
+        self.contacts.is_empty() && self.members.is_empty() && self.circles.is_empty() && self.circle_members.is_empty()
+    }
+
+    pub fn add_circle_member(&mut self, circle_id: &str, member: StoredCircleMember) {
+        self.circle_members.entry(circle_id.to_string()).or_default().push(member);
+    }
+}
+
+impl AddressBook {
+    fn new() -> Self {
+        Self {
+            oldest_change: now(),
+            contacts_seeded: false,
+            members_seeded: false,
+            contacts: HashMap::new(),
+            members: HashMap::new(),
+            circles: HashMap::new(),
+            circle_members: HashMap::new(),
+            space_groups: HashMap::new(),
+            contact_group_ids: HashMap::new(),
+        }
+    }
+
+    fn prune(&mut self) {
+        let cutoff = now() - TOMBSTONE_LIFETIME_SECS;
+        let before = self.count_entries();
+
+        self.contacts.retain(|_, contact| !contact.deleted || contact.last_change >= cutoff);
+        self.members.retain(|_, member| !member.deleted || member.last_change >= cutoff);
+        self.circles.retain(|_, circle| !circle.deleted || circle.last_change >= cutoff);
+        for members in self.circle_members.values_mut() {
+            members.retain(|_, member| !member.deleted || member.last_change >= cutoff);
+        }
+        self.space_groups.retain(|_, group| !group.deleted || group.last_change >= cutoff);
+
+        if self.count_entries() != before {
+            self.oldest_change = self.oldest_change.max(cutoff);
+        }
+    }
+
+    fn count_entries(&self) -> usize {
+        self.contacts.len() + self.members.len() + self.circles.len() + self.space_groups.len() + self.circle_members.values().map(|members| members.len()).sum::<usize>()
+    }
+}
+
+impl AddressBookStore {
+
+    pub fn load(user_id: Option<&UserId>) -> Self {
+        let path = user_id.and_then(get_user_data).map(|user_data| user_data.join("address_book.json"));
+
+        let address_book = match path.as_ref().map(fs::read_to_string) {
+            Some(Ok(content)) => {
+                match serde_json::from_str::<AddressBook>(&content) {
+                    Ok(address_book) => address_book,
+                    Err(err) => {
+                        warn!("SOAP|ABCH: Couldn't parse address book store, starting from scratch: {}", err);
+                        AddressBook::new()
+                    }
+                }
+            },
+            _ => AddressBook::new()
+        };
+
+        Self {
+            address_book: Arc::new(Mutex::new(address_book)),
+            persist: DebouncedPersist::new(path)
+        }
+    }
+
+    /* A delta can only be served if nothing the client may have missed was pruned */
+    pub fn can_serve_delta(&self, last_change: Option<&str>) -> bool {
+        let Some(last_change) = last_change.and_then(parse_last_change) else {
+            return false;
+        };
+
+        last_change >= self.address_book.lock().unwrap().oldest_change
+    }
+
+    pub fn apply(&self, changes: AddressBookChanges) -> AppliedChanges {
+        let mut applied = AppliedChanges::default();
+        if changes.is_empty() {
+            return applied;
+        }
+
+        {
+            let mut address_book = self.address_book.lock().unwrap();
+
+            for contact in changes.contacts {
+                address_book.contacts.insert(contact.email.clone(), contact);
+                applied.address_book_changed = true;
+            }
+
+            for member in changes.members {
+                let key = member.key();
+                if member.deleted && !address_book.members.contains_key(&key) {
+                    continue;
+                }
+                address_book.members.insert(key, member);
+                applied.address_book_changed = true;
+            }
+
+            for circle in changes.circles {
+                let circle_id = circle.get_circle_id();
+                /* Leaving a room which isn't a circle anymore (a deleted DM) maps to leaving an unknown circle */
+                if circle.deleted && !address_book.circles.contains_key(&circle_id) {
+                    continue;
+                }
+                address_book.circles.insert(circle_id, circle);
+                applied.address_book_changed = true;
+            }
+
+            for (circle_id, members) in changes.circle_members {
+                let circle_members = address_book.circle_members.entry(circle_id.clone()).or_default();
+                for member in members {
+                    circle_members.insert(member.email.clone(), member);
+                }
+                applied.changed_circles.push(circle_id);
+            }
+
+            address_book.prune();
+        }
+
+        self.schedule_persist();
+        applied
+    }
+
+    pub fn is_contacts_seeded(&self) -> bool {
+        self.address_book.lock().unwrap().contacts_seeded
+    }
+
+    pub fn is_members_seeded(&self) -> bool {
+        self.address_book.lock().unwrap().members_seeded
+    }
+
+    /* Circles we are a member of, not the ones we were only invited to */
+    pub fn has_circle(&self, circle_id: &str) -> bool {
+        self.address_book.lock().unwrap().circles.get(circle_id).is_some_and(|circle| !circle.deleted && circle.invite.is_none())
+    }
+
+    pub fn is_circle_seeded(&self, circle_id: &str) -> bool {
+        self.address_book.lock().unwrap().circle_members.contains_key(circle_id)
+    }
+
+    /* Every full sync reconciles the store with Matrix. Entries that didn't change keep their lastChange, so caches built from them stay valid,
+    entries which aren't in Matrix anymore are tombstoned so deltas remove them */
+    pub fn reconcile_contacts(&self, contacts: Vec<StoredContact>) {
+        {
+            let mut address_book = self.address_book.lock().unwrap();
+            let found_emails: HashSet<String> = contacts.iter().map(|contact| contact.email.clone()).collect();
+
+            for contact in contacts {
+                if !address_book.contacts.get(&contact.email).is_some_and(|found| found.same_as(&contact)) {
+                    address_book.contacts.insert(contact.email.clone(), contact);
+                }
+            }
+
+            let last_change = now();
+            for contact in address_book.contacts.values_mut() {
+                if !contact.deleted && !found_emails.contains(&contact.email) {
+                    contact.deleted = true;
+                    contact.last_change = last_change;
+                }
+            }
+
+            address_book.contacts_seeded = true;
+        }
+        self.schedule_persist();
+    }
+
+    pub fn reconcile_members(&self, members: Vec<StoredMember>) {
+        {
+            let mut address_book = self.address_book.lock().unwrap();
+            let found_keys: HashSet<String> = members.iter().map(|member| member.key()).collect();
+
+            for member in members {
+                let key = member.key();
+                if !address_book.members.get(&key).is_some_and(|found| found.same_as(&member)) {
+                    address_book.members.insert(key, member);
+                }
+            }
+
+            let last_change = now();
+            for (key, member) in address_book.members.iter_mut() {
+                if !member.deleted && !found_keys.contains(key) {
+                    member.deleted = true;
+                    member.last_change = last_change;
+                }
+            }
+
+            address_book.members_seeded = true;
+        }
+        self.schedule_persist();
+    }
+
+    pub fn seed_circle(&self, circle: StoredCircle, members: Vec<StoredCircleMember>) {
+        {
+            let mut address_book = self.address_book.lock().unwrap();
+            let circle_id = circle.get_circle_id();
+
+            if !address_book.circles.get(&circle_id).is_some_and(|found| found.same_as(&circle)) {
+                address_book.circles.insert(circle_id.clone(), circle);
+            }
+
+            let circle_members = address_book.circle_members.entry(circle_id).or_default();
+            for member in members {
+                if !circle_members.get(&member.email).is_some_and(|found| found.same_as(&member)) {
+                    circle_members.insert(member.email.clone(), member);
+                }
+            }
+        }
+        self.schedule_persist();
+    }
+
+    /* Spaces and m.favourite tags aren't changed through us only. The Spaces we left are tombstoned,
+    and contacts whose groupIds changed since WLM last got them are bumped so deltas send them again */
+    pub fn reconcile_groups(&self, space_groups: Vec<StoredSpaceGroup>, contact_group_ids: &HashMap<String, Vec<String>>) {
+        {
+            let mut address_book = self.address_book.lock().unwrap();
+            let found_room_ids: HashSet<String> = space_groups.iter().map(|group| group.room_id.clone()).collect();
+
+            for group in space_groups {
+                if !address_book.space_groups.get(&group.room_id).is_some_and(|found| found.same_as(&group)) {
+                    address_book.space_groups.insert(group.room_id.clone(), group);
+                }
+            }
+
+            let last_change = now();
+            for group in address_book.space_groups.values_mut() {
+                if !group.deleted && !found_room_ids.contains(&group.room_id) {
+                    group.deleted = true;
+                    group.last_change = last_change;
+                }
+            }
+
+            let emails: HashSet<String> = address_book.contact_group_ids.keys().chain(contact_group_ids.keys()).cloned().collect();
+            for email in emails {
+                let mut group_ids = contact_group_ids.get(&email).cloned().unwrap_or_default();
+                group_ids.sort();
+
+                if address_book.contact_group_ids.get(&email).map(Vec::as_slice).unwrap_or_default() == group_ids.as_slice() {
+                    continue;
+                }
+
+                if let Some(contact) = address_book.contacts.get_mut(&email) {
+                    contact.last_change = last_change;
+                }
+
+                if group_ids.is_empty() {
+                    address_book.contact_group_ids.remove(&email);
+                } else {
+                    address_book.contact_group_ids.insert(email, group_ids);
+                }
+            }
+        }
+        self.schedule_persist();
+    }
+
+    pub fn find_contact_by_id(&self, contact_id: &str) -> Option<StoredContact> {
+        let address_book = self.address_book.lock().unwrap();
+        address_book.contacts.values().find(|contact| Uuid::from_seed(&contact.email).to_string().eq_ignore_ascii_case(contact_id)).cloned()
+    }
+
+    /* Bumps lastChange of existing contacts, so deltas send them again with their new groupIds */
+    pub fn touch_contacts<'a>(&self, emails: impl IntoIterator<Item = &'a str>) {
+        {
+            let mut address_book = self.address_book.lock().unwrap();
+            let last_change = now();
+            for email in emails {
+                if let Some(contact) = address_book.contacts.get_mut(email) {
+                    contact.last_change = last_change;
+                }
+            }
+        }
+
+        self.schedule_persist();
+    }
+
+    pub fn is_blocked(&self, email: &str) -> bool {
+        self.has_member(email, MemberRole::Block)
+    }
+
+    pub fn has_member(&self, email: &str, role: MemberRole) -> bool {
+        let address_book = self.address_book.lock().unwrap();
+        address_book.members.get(&get_member_key(role, email)).is_some_and(|member| !member.deleted)
+    }
+
+    /* None returns the whole address book without deleted entries */
+    pub fn get_contacts(&self, since: Option<i64>) -> Vec<StoredContact> {
+        let address_book = self.address_book.lock().unwrap();
+        address_book.contacts.values().filter(|contact| Self::is_wanted(contact.deleted, contact.last_change, since)).cloned().collect()
+    }
+
+    pub fn get_members(&self, since: Option<i64>) -> Vec<StoredMember> {
+        let address_book = self.address_book.lock().unwrap();
+        address_book.members.values().filter(|member| Self::is_wanted(member.deleted, member.last_change, since)).cloned().collect()
+    }
+
+    pub fn get_circles(&self, since: Option<i64>) -> Vec<StoredCircle> {
+        let address_book = self.address_book.lock().unwrap();
+        address_book.circles.values().filter(|circle| Self::is_wanted(circle.deleted, circle.last_change, since)).cloned().collect()
+    }
+
+    pub fn get_space_groups(&self, since: Option<i64>) -> Vec<StoredSpaceGroup> {
+        let address_book = self.address_book.lock().unwrap();
+        address_book.space_groups.values().filter(|group| Self::is_wanted(group.deleted, group.last_change, since)).cloned().collect()
+    }
+
+    pub fn get_circle_members(&self, circle_id: &str, since: Option<i64>) -> Vec<StoredCircleMember> {
+        let address_book = self.address_book.lock().unwrap();
+        match address_book.circle_members.get(circle_id) {
+            None => Vec::new(),
+            Some(members) => members.values().filter(|member| Self::is_wanted(member.deleted, member.last_change, since)).cloned().collect()
+        }
+    }
+
+    /* lastChange only has a second precision, entries of that same second are sent again rather than lost */
+    fn is_wanted(deleted: bool, last_change: i64, since: Option<i64>) -> bool {
+        match since {
+            None => !deleted,
+            Some(since) => last_change >= since
+        }
+    }
+
+    /* Changes come in bursts during a sync, they are written to disk together */
+    fn schedule_persist(&self) {
+        let address_book = self.address_book.clone();
+        self.persist.schedule(move || {
+            let address_book = address_book.lock().unwrap();
+            serde_json::to_string(&*address_book)
+        });
+    }
+}
@@ -6,6 +6,7 @@ use matrix_sdk::ruma::OwnedUserId;
 
 use msnp::shared::models::uuid::Uuid;
 
+/* Rosters of the circle conversations the client joined, live joins & leaves are only pushed for those */
 pub struct CircleStore {
     circles: DashMap<Uuid, Circle>,
 }
@@ -18,34 +19,42 @@ impl CircleStore {
         }
     }
 
-    pub async fn add_to_roster(&self, circle_id: &Uuid, to_add: Vec<OwnedUserId>) {
-        match self.circles.get_mut(circle_id) {
-            None => {
-                let circle = Circle {
-                    roster:  HashSet::from_iter(to_add.into_iter())
-                };
+    pub fn join(&self, circle_id: &Uuid, roster: Vec<OwnedUserId>) {
+        let circle = Circle {
+            roster: HashSet::from_iter(roster.into_iter())
+        };
+
+        self.circles.insert(circle_id.clone(), circle);
+    }
 
-                self.circles.insert(circle_id.clone(),  circle);
+    pub fn leave(&self, circle_id: &Uuid) {
+        self.circles.remove(circle_id);
+    }
+
+    pub fn is_joined(&self, circle_id: &Uuid) -> bool {
+        self.circles.contains_key(circle_id)
+    }
 
+    /* Returns who wasn't in the roster yet */
+    pub fn add_to_roster(&self, circle_id: &Uuid, to_add: Vec<OwnedUserId>) -> Vec<OwnedUserId> {
+        match self.circles.get_mut(circle_id) {
+            None => {
+                Vec::new()
             }
             Some(mut circle) => {
-                circle.roster.extend(to_add.into_iter())
+                to_add.into_iter().filter(|current| circle.roster.insert(current.clone())).collect()
             }
         }
     }
 
-    pub async fn remove_from_roster(&self, circle_id: &Uuid, to_remove: Vec<OwnedUserId>) {
+    /* Returns who was actually in the roster */
+    pub fn remove_from_roster(&self, circle_id: &Uuid, to_remove: Vec<OwnedUserId>) -> Vec<OwnedUserId> {
         match self.circles.get_mut(circle_id) {
-            None => {}
+            None => {
+                Vec::new()
+            }
             Some(mut circle) => {
-
-                for current in to_remove {
-                    circle.roster.remove(&current);
-                }
-
-                if circle.roster.is_empty() {
-                    self.circles.remove(circle_id);
-                }
+                to_remove.into_iter().filter(|current| circle.roster.remove(current)).collect()
             }
         }
     }
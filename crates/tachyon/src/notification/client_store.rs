@@ -1,22 +1,30 @@
 use std::collections::VecDeque;
 use std::sync::{Arc, LockResult, Mutex, MutexGuard, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::sync::atomic::{AtomicBool, Ordering};
 
 use anyhow::anyhow;
 use dashmap::DashMap;
+use log::warn;
 use matrix_sdk::Client;
-use matrix_sdk::ruma::OwnedRoomId;
+use matrix_sdk::ruma::{OwnedRoomId, UserId};
 use thiserror::__private::AsDynError;
 use thiserror::Error;
 use tokio::sync::mpsc;
 
 use msnp::msnp::models::contact_list::ContactList;
+use msnp::msnp::notification::command::command::NotificationServerCommand;
 use msnp::msnp::switchboard::command::command::SwitchboardServerCommand;
+use msnp::shared::models::email_address::EmailAddress;
 use msnp::shared::models::msn_user::MsnUser;
 use msnp::shared::models::oim::OIM;
 use msnp::shared::models::ticket_token::TicketToken;
-use msnp::soap::abch::ab_service::ab_find_contacts_paged::response::CircleData;
-use msnp::soap::abch::msnab_datatypes::{BaseMember, ContactType};
+use crate::matrix::events::contact_properties::ContactPropertiesEventContent;
+use crate::notification::address_book_store::{AddressBookStore, MemberRole};
 use crate::notification::circle_store::CircleStore;
+use crate::notification::emoticon_store::EmoticonStore;
+use crate::notification::msn_object_store::MsnObjectStore;
+use crate::notification::read_marker_store::ReadMarkerStore;
+use crate::shared::tachyon_config::TachyonConfig;
 
 #[derive(Clone)]
 pub struct SwitchboardHandle {
@@ -32,20 +40,32 @@ pub struct ClientDataInner {
     pub contact_list: Mutex<ContactList>,
     pub soap_holder: SoapHolder,
     pub switchboards: DashMap<OwnedRoomId, SwitchboardHandle>,
-    pub circle_store: CircleStore
-}
-
-pub enum Contact {
-    Contact(ContactType),
-    Circle(CircleData)
+    pub circle_store: CircleStore,
+    pub emoticon_store: EmoticonStore,
+    pub read_marker_store: ReadMarkerStore,
+    pub msn_object_store: MsnObjectStore,
+    pub address_book_store: AddressBookStore,
+    /* Winks the client is sending us over P2P: SHA1D -> contact */
+    pub pending_winks: DashMap<String, EmailAddress>,
+    /* Contact email -> nickname from our contact properties account data */
+    pub contact_nicknames: DashMap<String, String>,
+    /* BLP mode, from our privacy settings account data */
+    pub allow_list_only: AtomicBool,
+    /* Held across the fetch & save of our contact groups account data, so concurrent ABCH requests don't overwrite each other */
+    pub contact_groups_lock: tokio::sync::Mutex<()>,
+    /* Same for our contact properties account data */
+    pub contact_properties_lock: tokio::sync::Mutex<()>,
+    /* Spaces are shown as contact groups, from the Tachyon config */
+    pub spaces_as_groups: bool,
+    /* Server initiated commands, like the contact list pushes */
+    pub notif_sender: mpsc::Sender<NotificationServerCommand>
 }
 
 #[derive(Default)]
 pub struct SoapHolder {
     pub oims: DashMap<String, OIM>,
-    pub contacts: Mutex<Vec<Contact>>,
-    pub circle_contacts: DashMap<String, Vec<ContactType>>,
-    pub memberships: Mutex<VecDeque<BaseMember>>
+    /* The LockKeyChallenge the client has to answer to send OIMs */
+    pub oim_lock_key_challenge: Mutex<Option<String>>
 }
 
 #[derive(Clone)]
@@ -60,7 +80,9 @@ pub enum ClientStoreError {
 }
 
 impl ClientData {
-    pub fn new(user: MsnUser, token: TicketToken, matrix_client: Client) -> ClientData {
+    pub fn new(user: MsnUser, token: TicketToken, matrix_client: Client, spaces_as_groups: bool, notif_sender: mpsc::Sender<NotificationServerCommand>) -> ClientData {
+        let msn_object_store = MsnObjectStore::load(matrix_client.user_id());
+        let address_book_store = AddressBookStore::load(matrix_client.user_id());
         ClientData{ inner: Arc::new(ClientDataInner {
             user: RwLock::new(user),
             ticket_token: token,
@@ -69,6 +91,17 @@ impl ClientData {
             soap_holder: Default::default(),
             switchboards: Default::default(),
             circle_store: CircleStore::new(),
+            emoticon_store: EmoticonStore::new(),
+            read_marker_store: ReadMarkerStore::new(),
+            msn_object_store,
+            address_book_store,
+            pending_winks: Default::default(),
+            contact_nicknames: Default::default(),
+            allow_list_only: Default::default(),
+            contact_groups_lock: Default::default(),
+            contact_properties_lock: Default::default(),
+            spaces_as_groups,
+            notif_sender,
         })
         }
     }
@@ -113,15 +146,6 @@ impl ClientData {
         &self.inner.soap_holder.oims
     }
 
-    pub fn get_contact_holder_mut(&mut self) -> LockResult<MutexGuard<'_, Vec<Contact>>> {
-       self.inner.soap_holder.contacts.lock()
-    }
-
-    pub fn get_member_holder_mut(&mut self) -> LockResult<MutexGuard<'_, VecDeque<BaseMember>>> {
-        self.inner.soap_holder.memberships.lock()
-
-    }
-
     pub fn remove_oim(&mut self, message_id: &str) -> Option<(String, OIM)> {
         self.inner.soap_holder.oims.remove(message_id)
     }
@@ -133,16 +157,60 @@ impl ClientData {
     pub fn get_matrix_client(&self) -> Client {
         self.inner.matrix_client.clone()
     }
+
+    pub fn is_allow_list_only(&self) -> bool {
+        self.inner.allow_list_only.load(Ordering::Relaxed)
+    }
+
+    pub fn set_allow_list_only(&self, allow_list_only: bool) {
+        self.inner.allow_list_only.store(allow_list_only, Ordering::Relaxed);
+    }
+
+    /* In BL mode, only contacts on the Allow list can reach us */
+    pub fn is_allowed(&self, email: &str) -> bool {
+        !self.is_allow_list_only() || self.inner.address_book_store.has_member(email, MemberRole::Allow)
+    }
+
+    /* Sends the RL/PL changes queued in the contact list as ADL/RML */
+    pub async fn send_contact_list_pushes(&self) {
+        let pushes = self.inner.contact_list.lock().unwrap().take_pushes();
+        for push in pushes {
+            if let Err(err) = self.inner.notif_sender.send(push).await {
+                warn!("MSNP|NS: Couldn't push contact list change: {}", err);
+            }
+        }
+    }
+
+    pub fn refresh_contact_nicknames(&self, contact_properties: &ContactPropertiesEventContent) {
+        self.inner.contact_nicknames.clear();
+        for (email, nickname) in contact_properties.get_nicknames() {
+            self.inner.contact_nicknames.insert(email.clone(), nickname.clone());
+        }
+    }
 }
 
 
 #[derive(Clone, Default)]
 pub struct ClientStoreFacade {
-    data: Arc<DashMap<String, ClientData>>
+    data: Arc<DashMap<String, ClientData>>,
+    spaces_as_groups: bool,
+    disable_ssl: bool
 }
 
 impl ClientStoreFacade {
 
+    pub fn new(config: &TachyonConfig) -> Self {
+        Self { data: Default::default(), spaces_as_groups: config.spaces_as_groups, disable_ssl: config.disable_ssl }
+    }
+
+    pub fn is_spaces_as_groups(&self) -> bool {
+        self.spaces_as_groups
+    }
+
+    pub fn is_ssl_disabled(&self) -> bool {
+        self.disable_ssl
+    }
+
     pub fn get_client_data(&self, key: &str) -> Option<ClientData> {
         match self.data.get(key) {
             None => {
@@ -154,6 +222,13 @@ impl ClientStoreFacade {
         }
     }
 
+    /* For requests that don't carry a ticket token, like media links */
+    pub fn get_client_data_by_user_id(&self, user_id: &UserId) -> Option<ClientData> {
+        self.data.iter()
+            .find(|entry| entry.value().inner.matrix_client.user_id() == Some(user_id))
+            .map(|entry| entry.value().clone())
+    }
+
     pub fn insert_client_data(&self, key: String, client_data: ClientData) {
         self.data.insert(key, client_data);
     }
@@ -0,0 +1,77 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use dashmap::DashMap;
+use matrix_sdk::ruma::{MxcUri, OwnedMxcUri};
+use tokio::sync::watch;
+
+/* Custom emoticons the client declared in its messages, keyed by SHA1D.
+The mxc is set once the client sent us the emoticon over P2P and we uploaded it.
+Pack emoticons we offer to the client are kept by mxc, media behind an mxc never changes. */
+pub struct EmoticonStore {
+    emoticons: DashMap<String, StoredEmoticon>,
+    pack_emoticons: DashMap<OwnedMxcUri, Arc<Vec<u8>>>,
+}
+
+struct StoredEmoticon {
+    shortcut: String,
+    mxc: watch::Sender<Option<OwnedMxcUri>>
+}
+
+impl EmoticonStore {
+
+    pub fn new() -> Self {
+        Self {
+            emoticons: Default::default(),
+            pack_emoticons: Default::default(),
+        }
+    }
+
+    /* Returns true when the emoticon is unknown and has to be fetched from the client */
+    pub fn request(&self, sha1d: &str, shortcut: &str) -> bool {
+        if self.emoticons.contains_key(sha1d) {
+            return false;
+        }
+
+        let (mxc, _) = watch::channel(None);
+        self.emoticons.insert(sha1d.to_string(), StoredEmoticon { shortcut: shortcut.to_string(), mxc });
+        true
+    }
+
+    pub fn get_shortcut(&self, sha1d: &str) -> Option<String> {
+        self.emoticons.get(sha1d).map(|e| e.shortcut.clone())
+    }
+
+    pub fn set_mxc(&self, sha1d: &str, mxc: OwnedMxcUri) {
+        if let Some(emoticon) = self.emoticons.get(sha1d) {
+            emoticon.mxc.send_replace(Some(mxc));
+        }
+    }
+
+    /* Dropping the sender wakes up everyone waiting for this emoticon */
+    pub fn remove(&self, sha1d: &str) {
+        self.emoticons.remove(sha1d);
+    }
+
+    pub async fn wait_for_mxc(&self, sha1d: &str, timeout: Duration) -> Option<OwnedMxcUri> {
+        let mut receiver = match self.emoticons.get(sha1d) {
+            None => {
+                return None;
+            }
+            Some(emoticon) => emoticon.mxc.subscribe()
+        };
+
+        match tokio::time::timeout(timeout, receiver.wait_for(|mxc| mxc.is_some())).await {
+            Ok(Ok(mxc)) => mxc.clone(),
+            _ => None
+        }
+    }
+
+    pub fn get_bytes(&self, mxc: &MxcUri) -> Option<Arc<Vec<u8>>> {
+        self.pack_emoticons.get(mxc).map(|bytes| bytes.clone())
+    }
+
+    pub fn set_bytes(&self, mxc: OwnedMxcUri, bytes: Arc<Vec<u8>>) {
+        self.pack_emoticons.insert(mxc, bytes);
+    }
+}
@@ -1,14 +1,17 @@
 use std::path::Path;
 use std::str::FromStr;
+use std::time::Duration;
 
 use anyhow::{anyhow, Error};
 use lazy_static_include::syn::BinOp::Ne;
 use log::{debug, error, warn};
 use matrix_sdk::RoomMemberships;
 use matrix_sdk::ruma::events::room::message::RoomMessageEventContent;
+use matrix_sdk::ruma::OwnedMxcUri;
 use tokio::sync::broadcast;
 use tokio::sync::mpsc::Sender;
 
+use msnp::msnp::notification::command::blp::ListType;
 use msnp::msnp::notification::command::command::{NotificationClientCommand, NotificationServerCommand};
 use msnp::msnp::notification::command::cvr::CvrServer;
 use msnp::msnp::notification::command::iln::IlnServer;
@@ -18,11 +21,13 @@ use msnp::msnp::notification::command::nln::NlnServer;
 use msnp::msnp::notification::command::ubx::{ExtendedPresenceContent, UbxPayload, UbxServer};
 use msnp::msnp::notification::command::usr::{AuthPolicy, OperationTypeClient, OperationTypeServer, SsoPhaseClient, SsoPhaseServer, UsrServer};
 use msnp::msnp::notification::command::uum::UumPayload;
+use msnp::msnp::notification::command::uun::UunPayload;
 use msnp::msnp::notification::command::uux::UuxPayload;
 use msnp::msnp::notification::models::endpoint_data::EndpointData;
 use msnp::msnp::notification::models::endpoint_guid::EndpointGuid;
 use msnp::msnp::notification::models::msnp_version::MsnpVersion::MSNP18;
 use msnp::msnp::raw_command_parser::RawCommand;
+use msnp::shared::command::error::ErrorCommand;
 use msnp::shared::models::capabilities::ClientCapabilities;
 use msnp::shared::models::email_address::EmailAddress;
 use msnp::shared::models::endpoint_id::EndpointId;
@@ -32,16 +37,27 @@ use msnp::shared::models::network_id_email::NetworkIdEmail;
 use msnp::shared::models::presence_status::PresenceStatus;
 use msnp::shared::models::uuid::Uuid;
 use msnp::shared::payload::msg::raw_msg_payload::factories::RawMsgPayloadFactory;
-use msnp::shared::payload::msg::text_msg::FontStyle;
-use msnp::shared::payload::nfy::nfy_put_payload::RawNfyPayload;
+use msnp::shared::payload::msg::text_msg::{FontStyle, TextMessageContent};
+use msnp::shared::payload::nfy::nfy_put_payload::{NfyContentType, RawNfyPayload};
 
 use crate::{matrix, notification};
+use crate::matrix::circles::{find_circle_room, join_circle_conversation};
+use crate::matrix::emoticons::{escape_html, render_inline_emoticons};
+use crate::matrix::events::privacy_settings::{fetch_privacy_settings, save_privacy_settings};
+use crate::matrix::memberships::decline_invites_outside_allow_list;
+use crate::matrix::messages::to_sdg;
 use crate::matrix::msn_user_resolver;
-use crate::matrix::sync::initial_sync;
+use crate::matrix::receipts::{send_fully_read, send_read_receipt};
+use crate::matrix::rendering::render_unsupported_circle_message;
+use crate::matrix::sync::{initial_sync, prefetch_display_pictures};
 use crate::notification::client_store::{ClientData, ClientStoreFacade};
 use crate::notification::notification_server::{LocalStore, Phase};
+use crate::notification::p2p;
 use crate::shared::identifiers::{MatrixDeviceId, MatrixIdCompatible};
 
+/* How long a message waits for its custom emoticons to be transferred before being sent without them */
+const EMOTICON_TRANSFER_TIMEOUT: Duration = Duration::from_secs(20);
+
 pub(crate) async fn handle_negotiation(raw_command: NotificationClientCommand, notif_sender: Sender<NotificationServerCommand>, mut local_store: &mut LocalStore) -> Result<(), anyhow::Error> {
     match raw_command {
         NotificationClientCommand::VER(command) => {
@@ -93,7 +109,7 @@ pub(crate) async fn handle_auth(raw_command: NotificationClientCommand, notif_se
                             let endpoint_id = EndpointId::new(local_store.email_addr.clone(), Some(endpoint_guid));
                             let msn_user = MsnUser::new(endpoint_id);
 
-                            let client_data = ClientData::new(msn_user.clone(), ticket_token.clone(), matrix_client.clone());
+                            let client_data = ClientData::new(msn_user.clone(), ticket_token.clone(), matrix_client.clone(), client_store.is_spaces_as_groups(), notif_sender.clone());
                             client_store.insert_client_data(ticket_token.as_str().to_owned(), client_data.clone());
 
                             local_store.token = ticket_token.clone();
@@ -174,19 +190,32 @@ pub(crate) async fn handle_command(raw_command: NotificationClientCommand, notif
 
             debug!("ADL: {:?}", &command);
 
-            let contacts = command.payload.get_contacts()?;
-
-            client_data.inner.contact_list.lock().unwrap().add_contacts(contacts, command.payload.is_initial());
-
-            notif_sender.send(NotificationServerCommand::Ok(command.get_ok_response("ADL"))).await?;
+            let result = client_data.inner.contact_list.lock().unwrap().apply_adl(&command.payload);
+            match result {
+                Ok(()) => {
+                    notif_sender.send(NotificationServerCommand::Ok(command.get_ok_response("ADL"))).await?;
+                },
+                Err(err) => {
+                    warn!("MSNP|NS|ADL: Rejected payload: {}", &err);
+                    notif_sender.send(NotificationServerCommand::Error(ErrorCommand { error_code: err.get_error_code(), tr_id: command.tr_id })).await?;
+                }
+            }
 
             Ok(())
         }
         NotificationClientCommand::RML(command) => {
             debug!("RML: {:?}", &command);
 
-            client_data.inner.contact_list.lock().unwrap().remove_contacts(command.payload.get_contacts()?);
-            notif_sender.send(NotificationServerCommand::Ok(command.get_ok_response("RML"))).await?;
+            let result = client_data.inner.contact_list.lock().unwrap().apply_rml(&command.payload);
+            match result {
+                Ok(()) => {
+                    notif_sender.send(NotificationServerCommand::Ok(command.get_ok_response("RML"))).await?;
+                },
+                Err(err) => {
+                    warn!("MSNP|NS|RML: Rejected payload: {}", &err);
+                    notif_sender.send(NotificationServerCommand::Error(ErrorCommand { error_code: err.get_error_code(), tr_id: command.tr_id })).await?;
+                }
+            }
 
             Ok(())
         }
@@ -212,11 +241,14 @@ pub(crate) async fn handle_command(raw_command: NotificationClientCommand, notif
         NotificationClientCommand::UUM(command) => {
 
             let ok_response = command.get_ok_response();
+            let tr_id = command.tr_id;
 
             match command.payload {
                 UumPayload::TextMessage(content) => {
                     let matrix_client = client_data.get_matrix_client();
                     let room = matrix_client.get_dm_room(&command.destination.email_addr.to_owned_user_id());
+                    let pending_emoticons = local_store.pending_emoticons.remove(command.destination.email_addr.as_str()).unwrap_or_default();
+
                     match room {
                         None => {
                             //NO DM ROOM FOUND
@@ -225,61 +257,110 @@ pub(crate) async fn handle_command(raw_command: NotificationClientCommand, notif
                             //TODO SMILEY TO EMOJI
                             //TODO Store event id for dedup
 
-                            let content = if content.is_styling_default() {
-                                RoomMessageEventContent::text_plain(content.body)
-                            } else {
-                                let mut message = content.body.clone();
-
-                                if !content.is_default_font_styles() {
-                                    if content.font_styles.matches(FontStyle::Bold) {
-                                        message = format!("<b>{}</b>", message)
-                                    }
-
-                                    if content.font_styles.matches(FontStyle::Italic) {
-                                        message = format!("<i>{}</i>", message)
-                                    }
+                            /* The emoticons are still coming over P2P, which goes through this loop, so the message is sent from a task.
+                            It waits for the previous one so messages can't overtake each other. */
+                            let previous_send = local_store.message_send_task.take();
+                            let notif_sender = notif_sender.clone();
+                            let client_data = client_data.clone();
+                            local_store.message_send_task = Some(tokio::spawn(async move {
+                                if let Some(previous_send) = previous_send {
+                                    let _result = previous_send.await;
+                                }
 
-                                    if content.font_styles.matches(FontStyle::Underline) {
-                                        message = format!("<u>{}</u>", message)
+                                let mut emoticons = Vec::with_capacity(pending_emoticons.len());
+                                for declaration in pending_emoticons {
+                                    match client_data.inner.emoticon_store.wait_for_mxc(&declaration.msn_object.sha1d, EMOTICON_TRANSFER_TIMEOUT).await {
+                                        None => {
+                                            warn!("MSNP|NS: Custom emoticon {} never arrived, sending it as text", &declaration.shortcut);
+                                        },
+                                        Some(mxc) => {
+                                            emoticons.push((declaration.shortcut, mxc));
+                                        }
                                     }
+                                }
 
-                                    if content.font_styles.matches(FontStyle::StrikeThrough) {
-                                        message = format!("<strike>{}</strike>", message)
+                                match room.send(text_message_to_room_message(content, &emoticons)).await {
+                                    Ok(_response) => {
+                                        //self.add_to_events_sent(response.event_id.to_string());
+                                        let _result = notif_sender.send(NotificationServerCommand::Ok(ok_response)).await;
+                                    },
+                                    Err(err) => {
+                                        /* Without an answer WLM would show the message as delivered */
+                                        error!("MSNP|NS: Couldn't send message to {}: {}", room.room_id(), err);
+                                        let _result = notif_sender.send(NotificationServerCommand::Error(ErrorCommand { error_code: 500, tr_id })).await;
                                     }
                                 }
-
-                                let color_attr = if content.is_default_font_color() { String::new() } else { format!(" color=\"{}\"", content.font_color.serialize_rgb())};
-                                let face_attr = if content.is_default_font() { String::new() } else { format!(" face=\"{}\"", content.font_family) };
-                                message = format!("<font{}{}>{}</font>",  color_attr, face_attr, message);
-
-                                RoomMessageEventContent::text_html(content.body, message)
-                            };
-
-                            let response = room.send(content).await?;
-                            //self.add_to_events_sent(response.event_id.to_string());
-                            notif_sender.send(NotificationServerCommand::Ok(ok_response)).await?;
+                            }));
                         }
                     }
 
                     Ok(())
                 },
+                UumPayload::Emoticon(content) => {
+                    let mut contact = MsnUser::new(command.destination.clone());
+                    contact.network_id = command.network_id.clone();
+
+                    p2p::request_emoticons(content, contact, notif_sender.clone(), client_data.clone(), local_store, kill_signal)?;
+                    notif_sender.send(NotificationServerCommand::Ok(ok_response)).await?;
+                    Ok(())
+                },
                 UumPayload::TypingUser(_) => {
-                    todo!()
+                    /* Typing means the conversation window is focused */
+                    let matrix_client = client_data.get_matrix_client();
+                    if let Some(room) = matrix_client.get_dm_room(&command.destination.email_addr.to_owned_user_id()) {
+                        if let Err(err) = send_read_receipt(&room, &client_data).await {
+                            warn!("MSNP|NS: Couldn't send read receipt to {}: {}", room.room_id(), err);
+                        }
+                    }
+
+                    notif_sender.send(NotificationServerCommand::Ok(ok_response)).await?;
+                    Ok(())
                 }
                 UumPayload::Nudge(_) => {
                     todo!()
 
                 }
+                UumPayload::Wink(content) => {
+                    let msn_object = content.get_msn_object().cloned().ok_or(anyhow!("Wink datacast without MSNObject"))?;
+
+                    let mut contact = MsnUser::new(command.destination.clone());
+                    contact.network_id = command.network_id.clone();
+
+                    p2p::request_wink(msn_object, contact, notif_sender.clone(), client_data.clone(), local_store, kill_signal)?;
+                    notif_sender.send(NotificationServerCommand::Ok(ok_response)).await?;
+                    Ok(())
+                }
                 UumPayload::Raw(_) => {
                     todo!()
                 }
             }
         }
         NotificationClientCommand::BLP(command) => {
+            let allow_list_only = command.list_type == ListType::BlockList;
+            let matrix_client = client_data.get_matrix_client();
+
+            let mut privacy_settings = fetch_privacy_settings(&matrix_client).await?;
+            if privacy_settings.allow_list_only != allow_list_only {
+                privacy_settings.allow_list_only = allow_list_only;
+                save_privacy_settings(&matrix_client, privacy_settings).await?;
+            }
+            client_data.set_allow_list_only(allow_list_only);
+
+            if allow_list_only {
+                decline_invites_outside_allow_list(&matrix_client, &client_data).await?;
+            }
+
             notif_sender.send(NotificationServerCommand::BLP(command)).await?;
             Ok(())
         }
         NotificationClientCommand::CHG(command) => {
+            {
+                let mut client_data = client_data.clone();
+                let mut user = client_data.get_user_mut()?;
+                user.capabilities = command.client_capabilities.clone();
+                user.status = command.presence_status.clone();
+            }
+
             if local_store.needs_initial_presence {
                 local_store.needs_initial_presence = false;
 
@@ -295,7 +376,7 @@ pub(crate) async fn handle_command(raw_command: NotificationClientCommand, notif
                         let _result = notif_sender.send(NotificationServerCommand::OUT).await;
                     }
 
-                    let (mut iln, mut notifications) = initial_sync_result.expect("to be here");
+                    let (mut iln, mut notifications, pending_display_pictures) = initial_sync_result.expect("to be here");
 
                     for current in iln.drain(..) {
                         let _result = notif_sender.send(NotificationServerCommand::ILN(current)).await;
@@ -304,6 +385,8 @@ pub(crate) async fn handle_command(raw_command: NotificationClientCommand, notif
                     for current in notifications.drain(..) {
                         let _result = notif_sender.send(NotificationServerCommand::NOT(current)).await;
                     }
+
+                    prefetch_display_pictures(pending_display_pictures, client_data, notif_sender).await;
                 });
             }
 
@@ -316,7 +399,26 @@ pub(crate) async fn handle_command(raw_command: NotificationClientCommand, notif
             Ok(())
         }
         NotificationClientCommand::PRP(command) => {Ok(())}
-        NotificationClientCommand::UUN(command) => {Ok(())}
+        NotificationClientCommand::UUN(command) => {
+            let ok_response = command.get_ok_response();
+
+            match command.payload {
+                UunPayload::ConversationWindowClosed { email_addr } => {
+                    let contact = EmailAddress::from_str(&email_addr)?;
+                    let matrix_client = client_data.get_matrix_client();
+
+                    if let Some(room) = matrix_client.get_dm_room(&contact.to_owned_user_id()) {
+                        if let Err(err) = send_fully_read(&room, &client_data).await {
+                            warn!("MSNP|NS: Couldn't move fully read marker in {}: {}", room.room_id(), err);
+                        }
+                    }
+                },
+                _ => {}
+            }
+
+            notif_sender.send(NotificationServerCommand::Ok(ok_response)).await?;
+            Ok(())
+        }
         NotificationClientCommand::XFR() => {Ok(())}
         NotificationClientCommand::RAW(command) => {
             warn!("Received RAW command: {:?}", command);
@@ -329,6 +431,13 @@ pub(crate) async fn handle_command(raw_command: NotificationClientCommand, notif
             let ok = command.get_ok_command();
             notif_sender.send(NotificationServerCommand::PUT(ok)).await?;
 
+            if command.payload.envelope.to.network_id == NetworkId::Circle && command.payload.content_type == NfyContentType::Circle {
+                if let Some(roster) = join_circle_conversation(&command.payload, &client_data).await? {
+                    notif_sender.send(NotificationServerCommand::NFY(roster)).await?;
+                }
+                return Ok(());
+            }
+
             let mut payload = command.payload;
             payload.envelope.swap_sides();
             payload.envelope.flags = None;
@@ -340,6 +449,13 @@ pub(crate) async fn handle_command(raw_command: NotificationClientCommand, notif
 
             Ok(())
         },
+        NotificationClientCommand::SDG(command) => {
+            if command.payload.envelope.to.network_id == NetworkId::Circle {
+                return send_circle_message(command.payload, &notif_sender, &client_data).await;
+            }
+
+            p2p::handle_sdg(command, notif_sender, client_data, local_store, kill_signal).await
+        },
         NotificationClientCommand::OUT => {Ok(())}
         _ => {
             warn!("Received unknown command");
@@ -348,3 +464,79 @@ pub(crate) async fn handle_command(raw_command: NotificationClientCommand, notif
     }
 }
 
+
+
+/* Circle conversations are the group room timeline, only text is relayed */
+async fn send_circle_message(payload: RawNfyPayload, notif_sender: &Sender<NotificationServerCommand>, client_data: &ClientData) -> Result<(), anyhow::Error> {
+    match payload.get_header("Message-Type") {
+        Some("Text") => {},
+        Some(message_type) if !message_type.starts_with("Control/") && !message_type.starts_with("Signal/") => {
+            /* Typing and signals are dropped silently, the rest is answered so it isn't lost without a word */
+            debug!("MSNP|NS|CIRCLES: Can't relay {} message to circle {}", message_type, &payload.envelope.to);
+            /* The circle tells the user, it isn't something the user said in the conversation */
+            let notice = RawNfyPayload::new_action_message(payload.envelope.to.clone(), payload.envelope.from.clone(), render_unsupported_circle_message(message_type));
+            notif_sender.send(to_sdg(notice)).await?;
+            return Ok(());
+        },
+        other => {
+            debug!("MSNP|NS|CIRCLES: Ignoring {:?} message to circle {}", other, &payload.envelope.to);
+            return Ok(());
+        }
+    }
+
+    let matrix_client = client_data.get_matrix_client();
+    let Some(room) = find_circle_room(&matrix_client, &payload.envelope.to.email) else {
+        warn!("MSNP|NS|CIRCLES: No room found for circle: {}", &payload.envelope.to);
+        return Ok(());
+    };
+
+    let content = payload.get_text_message()?;
+    room.send(text_message_to_room_message(content, &[])).await?;
+    Ok(())
+}
+
+fn text_message_to_room_message(mut content: TextMessageContent, emoticons: &[(String, OwnedMxcUri)]) -> RoomMessageEventContent {
+    let is_emote = match content.get_me_action() {
+        Some(action) => {
+            content.body = action.to_string();
+            true
+        },
+        None => false
+    };
+
+    if content.is_styling_default() && emoticons.is_empty() {
+        return if is_emote { RoomMessageEventContent::emote_plain(content.body) } else { RoomMessageEventContent::text_plain(content.body) };
+    }
+
+    let mut message = render_inline_emoticons(&content.body, emoticons);
+
+    if !content.is_default_font_styles() {
+        if content.font_styles.matches(FontStyle::Bold) {
+            message = format!("<b>{}</b>", message)
+        }
+
+        if content.font_styles.matches(FontStyle::Italic) {
+            message = format!("<i>{}</i>", message)
+        }
+
+        if content.font_styles.matches(FontStyle::Underline) {
+            message = format!("<u>{}</u>", message)
+        }
+
+        if content.font_styles.matches(FontStyle::StrikeThrough) {
+            message = format!("<strike>{}</strike>", message)
+        }
+    }
+
+    if !content.is_default_font_color() || !content.is_default_font() {
+        let color_attr = if content.is_default_font_color() { String::new() } else { format!(" color=\"{}\"", content.font_color.serialize_rgb())};
+        let face_attr = if content.is_default_font() { String::new() } else { format!(" face=\"{}\"", escape_html(&content.font_family)) };
+        message = format!("<font{}{}>{}</font>",  color_attr, face_attr, message);
+    }
+
+    if is_emote {
+        return RoomMessageEventContent::emote_html(content.body, message);
+    }
+
+    RoomMessageEventContent::text_html(content.body, message)
+}
@@ -1,5 +1,10 @@
 pub mod notification_server;
 pub(crate) mod client_store;
 mod handlers;
+mod p2p;
 mod chg;
-pub mod circle_store;
\ No newline at end of file
+pub mod circle_store;
+pub mod emoticon_store;
+pub mod read_marker_store;
+pub mod msn_object_store;
+pub mod address_book_store;
\ No newline at end of file
@@ -0,0 +1,68 @@
+use std::collections::HashMap;
+use std::fs;
+use std::sync::Arc;
+
+use dashmap::DashMap;
+use log::warn;
+use matrix_sdk::ruma::{MxcUri, UserId};
+use serde::{Deserialize, Serialize};
+
+use crate::shared::paths::get_user_data;
+use crate::shared::persist::DebouncedPersist;
+
+/* Display pictures MsnObjects, keyed by mxc. mxc content never changes, so neither does the SHA1D.
+Persisted to avoid downloading every avatar of the contact list at each sign in. */
+pub struct MsnObjectStore {
+    msn_objects: Arc<DashMap<String, StoredMsnObject>>,
+    persist: DebouncedPersist
+}
+
+/* The SHA1C depends on the owner of the display picture, it is computed again when the MsnObject is rebuilt */
+#[derive(Clone, Serialize, Deserialize)]
+pub struct StoredMsnObject {
+    pub size: usize,
+    pub sha1d: String
+}
+
+impl MsnObjectStore {
+
+    pub fn load(user_id: Option<&UserId>) -> Self {
+        let path = user_id.and_then(get_user_data).map(|user_data| user_data.join("msn_objects.json"));
+
+        let msn_objects = match path.as_ref().map(fs::read_to_string) {
+            Some(Ok(content)) => {
+                match serde_json::from_str::<HashMap<String, StoredMsnObject>>(&content) {
+                    Ok(msn_objects) => msn_objects.into_iter().collect(),
+                    Err(err) => {
+                        warn!("MSNOBJ: Couldn't parse MsnObject store, starting from scratch: {}", err);
+                        DashMap::new()
+                    }
+                }
+            },
+            _ => DashMap::new()
+        };
+
+        Self {
+            msn_objects: Arc::new(msn_objects),
+            persist: DebouncedPersist::new(path)
+        }
+    }
+
+    pub fn get(&self, mxc: &MxcUri) -> Option<StoredMsnObject> {
+        self.msn_objects.get(mxc.as_str()).map(|found| found.value().clone())
+    }
+
+    pub fn insert(&self, mxc: &MxcUri, msn_object: StoredMsnObject) {
+        self.msn_objects.insert(mxc.to_string(), msn_object);
+        self.schedule_persist();
+    }
+
+    /* Inserts come in bursts when the contact list avatars are prefetched, they are written to disk together */
+    fn schedule_persist(&self) {
+        let msn_objects = self.msn_objects.clone();
+        self.persist.schedule(move || {
+            let snapshot: HashMap<String, StoredMsnObject> = msn_objects.iter().map(|entry| (entry.key().clone(), entry.value().clone())).collect();
+            serde_json::to_string(&snapshot)
+        });
+    }
+}
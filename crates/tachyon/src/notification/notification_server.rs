@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::future::Future;
 use std::path::Path;
 use std::str::from_utf8_unchecked;
@@ -19,8 +20,10 @@ use msnp::shared::payload::msg::raw_msg_payload::factories::RawMsgPayloadFactory
 use msnp::shared::traits::MSNPCommand;
 use tokio::{io::{AsyncReadExt, AsyncWriteExt, BufReader}, net::{tcp::OwnedWriteHalf, TcpListener, TcpStream}, sync::{broadcast::{self, Receiver}, mpsc::{self, Sender}}};
 use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
 use msnp::msnp::notification::command::uum::UumPayload;
 use msnp::msnp::notification::models::endpoint_data::PrivateEndpointData;
+use msnp::shared::payload::msg::emoticon_msg::EmoticonDeclaration;
 use msnp::shared::models::email_address::EmailAddress;
 use msnp::shared::models::endpoint_id::EndpointId;
 use msnp::shared::models::msn_user::MsnUser;
@@ -29,6 +32,7 @@ use crate::matrix;
 
 use crate::notification::client_store::{ClientData, ClientStoreFacade};
 use crate::notification::handlers::{handle_auth, handle_command, handle_negotiation};
+use crate::notification::p2p::P2PClientHandle;
 use crate::shared::identifiers::{MatrixDeviceId, MatrixIdCompatible};
 
 pub struct NotificationServer;
@@ -85,7 +89,11 @@ pub(crate) struct LocalStore {
     pub(crate) token: TicketToken,
     pub(crate) client_data: Option<ClientData>,
     pub(crate) private_endpoint_data: PrivateEndpointData,
-    pub(crate) needs_initial_presence: bool
+    pub(crate) needs_initial_presence: bool,
+    pub(crate) p2p_clients: HashMap<String, P2PClientHandle>,
+    pub(crate) pending_emoticons: HashMap<String, Vec<EmoticonDeclaration>>,
+    /* The last UUM text being sent to Matrix, the next one waits for it so messages stay in order */
+    pub(crate) message_send_task: Option<JoinHandle<()>>
 }
 
 impl Default for LocalStore {
@@ -97,6 +105,9 @@ impl Default for LocalStore {
             client_data: None,
             private_endpoint_data: Default::default(),
             needs_initial_presence: true,
+            p2p_clients: HashMap::new(),
+            pending_emoticons: HashMap::new(),
+            message_send_task: None,
         }
     }
 }
@@ -0,0 +1,249 @@
+use std::str::from_utf8;
+
+use anyhow::anyhow;
+use log::{debug, error, info, warn};
+use matrix_sdk::crypto::vodozemac::base64_decode;
+use matrix_sdk::ruma::OwnedMxcUri;
+use tokio::sync::broadcast;
+use tokio::sync::mpsc::{self, Sender};
+use tokio::sync::oneshot;
+
+use msnp::msnp::notification::command::command::NotificationServerCommand;
+use msnp::msnp::notification::command::sdg::{SdgClient, SdgServer};
+use msnp::p2p::v2::events::content::msb_object_received_event_content::MSNObjectReceivedEventContent;
+use msnp::p2p::v2::events::content::msn_object_requested_event_content::MSNObjectRequestedEventContent;
+use msnp::p2p::v2::events::p2p_event::P2PEvent;
+use msnp::p2p::v2::p2p_client::P2PClient;
+use msnp::p2p::v2::p2p_transport_packet::P2PTransportPacket;
+use msnp::p2p::v2::pending_packet::PendingPacket;
+use msnp::p2p::v2::session::p2p_session_type::P2PSessionType;
+use msnp::shared::models::msn_object::{MsnObject, MsnObjectType};
+use msnp::shared::models::msn_user::MsnUser;
+use msnp::shared::payload::msg::emoticon_msg::EmoticonMessageContent;
+use msnp::shared::payload::nfy::nfy_put_payload::{NfyContentType, RawNfyPayload};
+
+use crate::matrix::emoticons::{add_to_user_emotes, get_emoticon_bytes, guess_image_mime};
+use crate::matrix::msn_user_resolver::get_avatar_bytes;
+use crate::matrix::winks::{get_wink_bytes, send_wink_to_room};
+use crate::notification::client_store::ClientData;
+use crate::notification::notification_server::LocalStore;
+use crate::shared::identifiers::MatrixIdCompatible;
+
+/* With MPOP, WLM routes P2P packets through the NS with SDG when no switchboard is open.
+We act as the contact's endpoint, there is one P2P transport per contact endpoint the client talks to. */
+pub(crate) async fn handle_sdg(command: SdgClient, notif_sender: Sender<NotificationServerCommand>, client_data: ClientData, local_store: &mut LocalStore, kill_signal: &broadcast::Receiver<()>) -> Result<(), anyhow::Error> {
+    let payload = command.payload;
+
+    match payload.content_type {
+        NfyContentType::P2P => {
+            let mut source = MsnUser::new(payload.envelope.get_from_endpoint_id());
+            source.network_id = payload.envelope.from.network_id.clone();
+
+            let mut destination = MsnUser::new(payload.envelope.get_to_endpoint_id());
+            destination.network_id = payload.envelope.to.network_id.clone();
+
+            let p2p_client = get_or_start_p2p_client(&destination, notif_sender, client_data, local_store, kill_signal);
+
+            let packet = P2PTransportPacket::try_from(payload.body.as_slice())?;
+            p2p_client.on_message_received(PendingPacket::new(packet, source, destination));
+        },
+        _ => {
+            warn!("MSNP|NS|P2P: Received SDG with unhandled content type: {}", payload.content_type);
+        }
+    }
+
+    Ok(())
+}
+
+/* Dropping the handle stops the client's event task */
+pub(crate) struct P2PClientHandle {
+    client: P2PClient,
+    _stop_sender: oneshot::Sender<()>
+}
+
+fn get_or_start_p2p_client<'a>(contact: &MsnUser, notif_sender: Sender<NotificationServerCommand>, client_data: ClientData, local_store: &'a mut LocalStore, kill_signal: &broadcast::Receiver<()>) -> &'a mut P2PClient {
+    let endpoint_id = contact.endpoint_id.to_string();
+
+    /* Clients are only kept while something is in flight with the contact */
+    local_store.p2p_clients.retain(|current, handle| current == &endpoint_id || !handle.client.is_idle());
+
+    &mut local_store.p2p_clients
+        .entry(endpoint_id)
+        .or_insert_with(|| start_p2p_client(notif_sender, client_data, kill_signal.resubscribe()))
+        .client
+}
+
+/* The client declares its custom emoticons before the text message using them, we fetch the ones we don't know yet
+while acting as the contact. The declarations are kept until the text message arrives. */
+pub(crate) fn request_emoticons(content: EmoticonMessageContent, contact: MsnUser, notif_sender: Sender<NotificationServerCommand>, client_data: ClientData, local_store: &mut LocalStore, kill_signal: &broadcast::Receiver<()>) -> Result<(), anyhow::Error> {
+    let me = client_data.get_user_clone()?;
+
+    let to_fetch: Vec<MsnObject> = content.emoticons.iter()
+        .filter(|declaration| client_data.inner.emoticon_store.request(&declaration.msn_object.sha1d, &declaration.shortcut))
+        .map(|declaration| declaration.msn_object.clone())
+        .collect();
+
+    if !to_fetch.is_empty() {
+        let p2p_client = get_or_start_p2p_client(&contact, notif_sender, client_data, local_store, kill_signal);
+        for msn_object in to_fetch {
+            debug!("MSNP|NS|P2P: Requesting custom emoticon {} from client", &msn_object.sha1d);
+            p2p_client.initiate_session(contact.clone(), me.clone(), P2PSessionType::MSNObject(msn_object));
+        }
+    }
+
+    local_store.pending_emoticons.entry(contact.get_email_address().to_string()).or_default().extend(content.emoticons);
+    Ok(())
+}
+
+/* Winks are sent as a datacast, the cabinet is then requested from the client like any MSNObject */
+pub(crate) fn request_wink(msn_object: MsnObject, contact: MsnUser, notif_sender: Sender<NotificationServerCommand>, client_data: ClientData, local_store: &mut LocalStore, kill_signal: &broadcast::Receiver<()>) -> Result<(), anyhow::Error> {
+    let me = client_data.get_user_clone()?;
+    client_data.inner.pending_winks.insert(msn_object.sha1d.clone(), contact.get_email_address().clone());
+
+    let p2p_client = get_or_start_p2p_client(&contact, notif_sender, client_data, local_store, kill_signal);
+    debug!("MSNP|NS|P2P: Requesting wink {} from client", &msn_object.sha1d);
+    p2p_client.initiate_session(contact, me, P2PSessionType::MSNObject(msn_object));
+    Ok(())
+}
+
+fn start_p2p_client(notif_sender: Sender<NotificationServerCommand>, client_data: ClientData, mut kill_signal: broadcast::Receiver<()>) -> P2PClientHandle {
+    let (event_sender, mut event_receiver) = mpsc::unbounded_channel::<P2PEvent>();
+    let p2p_client = P2PClient::new(event_sender);
+    p2p_client.start_sweep_task();
+
+    let (stop_sender, mut stop_receiver) = oneshot::channel::<()>();
+
+    let mut task_p2p_client = p2p_client.clone();
+    tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                /* Packets queued by the client are still forwarded once it is pruned */
+                biased;
+                event = event_receiver.recv() => {
+                    match event {
+                        None => {
+                            break;
+                        },
+                        Some(event) => {
+                            if let Err(err) = handle_p2p_event(event, &mut task_p2p_client, &notif_sender, &client_data).await {
+                                error!("MSNP|NS|P2P: An error has occured handling a P2P event: {}", err);
+                            }
+                        }
+                    }
+                },
+                _kill_signal = kill_signal.recv() => {
+                    break;
+                },
+                _stop = &mut stop_receiver => {
+                    break;
+                }
+            }
+        }
+        debug!("MSNP|NS|P2P: P2P event task gracefully shutdown...");
+    });
+
+    P2PClientHandle { client: p2p_client, _stop_sender: stop_sender }
+}
+
+async fn handle_p2p_event(event: P2PEvent, p2p_client: &mut P2PClient, notif_sender: &Sender<NotificationServerCommand>, client_data: &ClientData) -> Result<(), anyhow::Error> {
+    match event {
+        P2PEvent::Message(content) => {
+            for packet in &content.packets {
+                let payload = RawNfyPayload::new_p2p(&content.sender, &content.receiver, packet);
+                notif_sender.send(NotificationServerCommand::SDG(SdgServer { tr_id: 0, payload })).await?;
+            }
+        },
+        P2PEvent::MSNObjectReceived(content) => {
+            let sha1d = content.msn_object.sha1d.clone();
+            match content.msn_object.obj_type {
+                MsnObjectType::Wink => {
+                    forward_received_wink(content, client_data).await?;
+                },
+                _ => {
+                    if let Err(err) = store_received_emoticon(content, client_data).await {
+                        client_data.inner.emoticon_store.remove(&sha1d);
+                        return Err(err);
+                    }
+                }
+            }
+        },
+        P2PEvent::MSNObjectRequested(content) => {
+            let session_id = content.session_id;
+            if let Err(err) = send_requested_msn_object(content, p2p_client, client_data).await {
+                p2p_client.decline_session(session_id);
+                return Err(err);
+            }
+        },
+        P2PEvent::SessionDeclined(content) => {
+            info!("MSNP|NS|P2P: {} declined P2P session {} ({:?})", &content.sender.endpoint_id, content.session_id, &content.session_type);
+            forget_msn_object_request(content.session_type.as_ref(), client_data);
+        },
+        P2PEvent::SessionCancelled(content) => {
+            warn!("MSNP|NS|P2P: {} cancelled P2P session {} ({:?}) with status: {:?}", &content.sender.endpoint_id, content.session_id, &content.session_type, &content.status_code);
+            forget_msn_object_request(content.session_type.as_ref(), client_data);
+        },
+        P2PEvent::SessionTimedOut(content) => {
+            warn!("MSNP|NS|P2P: P2P session {} ({:?}) timed out while {:?}", content.session_id, &content.session_type, &content.status);
+            forget_msn_object_request(Some(&content.session_type), client_data);
+        },
+        _ => {
+            debug!("MSNP|NS|P2P: Unhandled P2P event: {:?}", event);
+        }
+    }
+
+    Ok(())
+}
+
+async fn send_requested_msn_object(content: MSNObjectRequestedEventContent, p2p_client: &mut P2PClient, client_data: &ClientData) -> Result<(), anyhow::Error> {
+    /* Display pictures & emoticons locations are the base64 encoded mxc, see msn_user_resolver::avatar_to_msn_obj */
+    let encoded_mxc = content.msn_object.location.strip_suffix(".tmp").unwrap_or(&content.msn_object.location);
+    let decoded_mxc = base64_decode(encoded_mxc)?;
+    let mxc = OwnedMxcUri::from(from_utf8(&decoded_mxc)?);
+
+    let matrix_client = client_data.get_matrix_client();
+
+    let bytes = match content.msn_object.obj_type {
+        MsnObjectType::DisplayPicture => get_avatar_bytes(&matrix_client, &mxc).await?,
+        MsnObjectType::CustomEmoticon => get_emoticon_bytes(&matrix_client, &client_data.inner.emoticon_store, &mxc).await?.to_vec(),
+        MsnObjectType::Wink => get_wink_bytes(&matrix_client, &mxc).await?,
+        _ => {
+            return Err(anyhow!("Requested MSNObject type is not supported: {:?}", content.msn_object.obj_type));
+        }
+    };
+
+    p2p_client.send_msn_object(content.session_id, content.call_id, bytes, content.invitee, content.inviter);
+    Ok(())
+}
+
+/* A failed transfer is forgotten so the emoticon or wink is asked for again the next time it's declared */
+fn forget_msn_object_request(session_type: Option<&P2PSessionType>, client_data: &ClientData) {
+    if let Some(P2PSessionType::MSNObject(msn_object)) = session_type {
+        client_data.inner.emoticon_store.remove(&msn_object.sha1d);
+        client_data.inner.pending_winks.remove(&msn_object.sha1d);
+    }
+}
+
+async fn store_received_emoticon(content: MSNObjectReceivedEventContent, client_data: &ClientData) -> Result<(), anyhow::Error> {
+    if content.msn_object.obj_type != MsnObjectType::CustomEmoticon {
+        return Err(anyhow!("Received MSNObject type is not supported: {:?}", content.msn_object.obj_type));
+    }
+
+    let emoticon_store = &client_data.inner.emoticon_store;
+    let shortcut = emoticon_store.get_shortcut(&content.msn_object.sha1d).ok_or(anyhow!("Received an emoticon we didn't ask for: {}", &content.msn_object.sha1d))?;
+
+    let content_type = guess_image_mime(&content.file_content);
+    let mxc = add_to_user_emotes(&client_data.get_matrix_client(), &shortcut, &content.file_content, &content_type).await?;
+
+    debug!("MSNP|NS|P2P: Custom emoticon {} uploaded to {}", &shortcut, &mxc);
+    emoticon_store.set_mxc(&content.msn_object.sha1d, mxc);
+    Ok(())
+}
+
+async fn forward_received_wink(content: MSNObjectReceivedEventContent, client_data: &ClientData) -> Result<(), anyhow::Error> {
+    let (_, contact) = client_data.inner.pending_winks.remove(&content.msn_object.sha1d).ok_or(anyhow!("Received a wink we didn't ask for: {}", &content.msn_object.sha1d))?;
+
+    let matrix_client = client_data.get_matrix_client();
+    let room = matrix_client.get_dm_room(&contact.to_owned_user_id()).ok_or(anyhow!("No DM room found for wink recipient: {}", &contact))?;
+
+    send_wink_to_room(&matrix_client, &room, content.file_content, &content.msn_object).await
+}
@@ -0,0 +1,41 @@
+use dashmap::DashMap;
+use matrix_sdk::ruma::{OwnedEventId, OwnedRoomId};
+
+/* The latest event of each room, & the last one we sent a read receipt for.
+WLM has no notion of read state, activity in a conversation is turned into receipts */
+pub struct ReadMarkerStore {
+    latest_events: DashMap<OwnedRoomId, OwnedEventId>,
+    read_events: DashMap<OwnedRoomId, OwnedEventId>,
+}
+
+impl ReadMarkerStore {
+
+    pub fn new() -> Self {
+        Self {
+            latest_events: Default::default(),
+            read_events: Default::default(),
+        }
+    }
+
+    pub fn set_latest_event(&self, room_id: OwnedRoomId, event_id: OwnedEventId) {
+        self.latest_events.insert(room_id, event_id);
+    }
+
+    /* Returns the latest event of the room if it wasn't marked as read yet */
+    pub fn get_unread_event(&self, room_id: &OwnedRoomId) -> Option<OwnedEventId> {
+        let latest = self.latest_events.get(room_id)?.value().clone();
+
+        match self.read_events.get(room_id) {
+            Some(read) if *read.value() == latest => None,
+            _ => Some(latest)
+        }
+    }
+
+    pub fn get_latest_event(&self, room_id: &OwnedRoomId) -> Option<OwnedEventId> {
+        self.latest_events.get(room_id).map(|e| e.value().clone())
+    }
+
+    pub fn set_read(&self, room_id: OwnedRoomId, event_id: OwnedEventId) {
+        self.read_events.insert(room_id, event_id);
+    }
+}
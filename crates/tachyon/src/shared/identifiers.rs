@@ -36,6 +36,9 @@ pub trait MatrixIdCompatible {
     fn from_user_id(value: &UserId) -> Self;
 
     fn to_owned_user_id(&self) -> OwnedUserId;
+
+    /* For addresses typed by the user, which may not make a valid MXID */
+    fn try_to_owned_user_id(&self) -> Option<OwnedUserId>;
 }
 
 
@@ -59,4 +62,9 @@ impl MatrixIdCompatible for EmailAddress {
         let (name, domain) = as_str.split_once("@").expect("Email to contain @");
         OwnedUserId::from_str(&format!("@{}:{}", name, domain)).expect("OwnedUserId to be valid")
     }
+
+    fn try_to_owned_user_id(&self) -> Option<OwnedUserId> {
+        let (name, domain) = self.as_str().split_once("@")?;
+        OwnedUserId::from_str(&format!("@{}:{}", name, domain)).ok()
+    }
 }
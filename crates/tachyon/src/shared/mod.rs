@@ -2,4 +2,5 @@ pub mod traits;
 pub mod error;
 pub mod identifiers;
 pub mod paths;
+pub mod persist;
 pub mod tachyon_config;
@@ -44,4 +44,7 @@ pub fn get_user_data(user_id: &UserId) -> Option<PathBuf> {
 
 pub fn get_store_path(user_id: &UserId) -> Option<PathBuf> {
     Some(get_user_data(user_id)?.join("store"))
-}
\ No newline at end of file
+}
+pub fn get_usertile_cache_path(user_id: &UserId) -> Option<PathBuf> {
+    Some(get_user_data(user_id)?.join("usertiles"))
+}
@@ -0,0 +1,70 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use log::warn;
+
+/* Changes come in bursts, they are written to disk together */
+const PERSIST_DELAY: Duration = Duration::from_secs(2);
+
+/* Debounced writes of a store file. Nothing is written for stores without a path */
+pub struct DebouncedPersist {
+    path: Option<PathBuf>,
+    scheduled: Arc<AtomicBool>
+}
+
+impl DebouncedPersist {
+
+    pub fn new(path: Option<PathBuf>) -> Self {
+        Self { path, scheduled: Arc::new(AtomicBool::new(false)) }
+    }
+
+    /* The content is serialized once the delay is over, so it includes every change of the burst */
+    pub fn schedule<F>(&self, serialize: F) where F: FnOnce() -> Result<String, serde_json::Error> + Send + 'static {
+        let Some(path) = self.path.clone() else {
+            return;
+        };
+
+        if self.scheduled.swap(true, Ordering::AcqRel) {
+            return;
+        }
+
+        let scheduled = self.scheduled.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(PERSIST_DELAY).await;
+
+            /* Changes from now on schedule another write */
+            scheduled.store(false, Ordering::Release);
+            let content = match serialize() {
+                Ok(content) => content,
+                Err(err) => {
+                    warn!("PERSIST: Couldn't serialize {}: {}", path.display(), err);
+                    return;
+                }
+            };
+
+            let task_path = path.clone();
+            match tokio::task::spawn_blocking(move || write_atomically(&task_path, content.as_bytes())).await {
+                Ok(Ok(())) => {},
+                Ok(Err(err)) => warn!("PERSIST: Couldn't write {}: {}", path.display(), err),
+                Err(err) => warn!("PERSIST: Write task of {} failed: {}", path.display(), err)
+            }
+        });
+    }
+}
+
+/* A crash mid-write leaves the temporary file behind, never a truncated store */
+pub fn write_atomically(path: &Path, content: &[u8]) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let mut temp_path = path.as_os_str().to_owned();
+    temp_path.push(".tmp");
+
+    fs::write(&temp_path, content)?;
+    fs::rename(&temp_path, path)
+}
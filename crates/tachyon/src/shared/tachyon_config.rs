@@ -14,6 +14,9 @@ pub struct TachyonConfig {
     pub disable_ssl: bool,
     #[serde(rename = "enableLogging")]
     pub enable_logging: bool,
+    /* Shows the Spaces the user is in as contact groups */
+    #[serde(rename = "spacesAsGroups", default)]
+    pub spaces_as_groups: bool,
 
 }
 
@@ -23,6 +26,7 @@ impl Default for TachyonConfig {
             simulate_presence: false,
             disable_ssl: false,
             enable_logging: false,
+            spaces_as_groups: false,
         }
     }
 }
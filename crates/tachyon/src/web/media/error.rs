@@ -0,0 +1,39 @@
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use log::error;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum MediaError {
+
+    #[error("Media link signature doesn't match")]
+    InvalidSignature,
+    #[error("Media link has expired")]
+    LinkExpired,
+    #[error("No logged in client for {}", .0)]
+    UnknownSession(String),
+    #[error("Media not found: {}", .0)]
+    NotFound(String),
+    #[error(transparent)]
+    MatrixError(#[from] matrix_sdk::Error),
+    #[error(transparent)]
+    InternalServerError(#[from] anyhow::Error),
+
+}
+
+impl IntoResponse for MediaError {
+    fn into_response(self) -> Response {
+        error!("WEB|MEDIA: {:?}", &self);
+
+        let status_code = match &self {
+            MediaError::InvalidSignature => StatusCode::FORBIDDEN,
+            MediaError::LinkExpired => StatusCode::GONE,
+            MediaError::UnknownSession(_) => StatusCode::UNAUTHORIZED,
+            MediaError::NotFound(_) => StatusCode::NOT_FOUND,
+            MediaError::MatrixError(_) => StatusCode::BAD_GATEWAY,
+            MediaError::InternalServerError(_) => StatusCode::INTERNAL_SERVER_ERROR
+        };
+
+        (status_code, self.to_string()).into_response()
+    }
+}
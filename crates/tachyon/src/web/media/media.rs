@@ -0,0 +1,166 @@
+use std::io::{self, Read};
+
+use anyhow::anyhow;
+use axum::body::Body;
+use axum::extract::{Path, Query, State};
+use axum::http::header::{CACHE_CONTROL, CONTENT_DISPOSITION, CONTENT_TYPE};
+use axum::response::Response;
+use bytes::Bytes;
+use futures_util::TryStreamExt;
+use lazy_static::lazy_static;
+use log::{debug, warn};
+use matrix_sdk::Client;
+use matrix_sdk::crypto::AttachmentDecryptor;
+use matrix_sdk::ruma::{EventId, MxcUri, RoomId, UserId};
+use matrix_sdk::ruma::events::{AnyMessageLikeEvent, AnyTimelineEvent, MessageLikeEvent};
+use matrix_sdk::ruma::events::room::MediaSource;
+use matrix_sdk::ruma::events::room::message::MessageType;
+use serde::Deserialize;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_util::io::{StreamReader, SyncIoBridge};
+
+use crate::notification::client_store::ClientStoreFacade;
+use crate::web::media::error::MediaError;
+use crate::web::media::media_link::verify_media_link;
+
+const MEDIA_CHUNK_SIZE: usize = 64 * 1024;
+const MEDIA_CHUNKS_BUFFER: usize = 4;
+
+lazy_static! {
+    static ref HTTP_CLIENT: reqwest::Client = reqwest::Client::builder().build().expect("HTTP client to build");
+    /* Certificates are only skipped when disableSsl is set, like the Matrix client, see login::get_matrix_client_builder */
+    static ref UNVERIFIED_HTTP_CLIENT: reqwest::Client = reqwest::Client::builder().danger_accept_invalid_certs(true).build().expect("HTTP client to build");
+}
+
+#[derive(Deserialize)]
+pub struct MediaLinkQuery {
+    user: String,
+    expires: i64,
+    sig: String
+}
+
+/* Encrypted attachments are decrypted with the EncryptedFile keys of the event */
+pub async fn get_media(Path((room_id, event_id)): Path<(String, String)>, Query(query): Query<MediaLinkQuery>, State(state): State<ClientStoreFacade>) -> Result<Response, MediaError> {
+    verify_media_link(&query.user, &room_id, &event_id, query.expires, &query.sig)?;
+
+    let user_id = UserId::parse(&query.user).map_err(|e| anyhow!(e))?;
+    let room_id = RoomId::parse(&room_id).map_err(|e| anyhow!(e))?;
+    let event_id = EventId::parse(&event_id).map_err(|e| anyhow!(e))?;
+
+    let client_data = state.get_client_data_by_user_id(&user_id).ok_or(MediaError::UnknownSession(user_id.to_string()))?;
+    let client = client_data.get_matrix_client();
+
+    let room = client.get_room(&room_id).ok_or(MediaError::NotFound(room_id.to_string()))?;
+    let event = match room.event(&event_id).await {
+        Ok(event) => event,
+        Err(err) => {
+            if err.as_client_api_error().is_some_and(|api_error| api_error.status_code.as_u16() == 404) {
+                return Err(MediaError::NotFound(event_id.to_string()));
+            }
+            return Err(MediaError::MatrixError(err));
+        }
+    };
+
+    let Ok(AnyTimelineEvent::MessageLike(AnyMessageLikeEvent::RoomMessage(MessageLikeEvent::Original(event)))) = event.event.deserialize() else {
+        return Err(MediaError::NotFound(event_id.to_string()));
+    };
+
+    let (source, mimetype, filename) = get_media_source(&event.content.msgtype).ok_or(MediaError::NotFound(event_id.to_string()))?;
+
+    debug!("WEB|MEDIA: Streaming {} from {}", &filename, &room_id);
+    let http_client = if state.is_ssl_disabled() { &*UNVERIFIED_HTTP_CLIENT } else { &*HTTP_CLIENT };
+    let body = stream_media(&client, http_client, source).await?;
+
+    let response = Response::builder()
+        .header(CONTENT_TYPE, mimetype.unwrap_or("application/octet-stream".to_string()))
+        .header(CONTENT_DISPOSITION, format!("inline; filename=\"{}\"", filename.replace('"', "")))
+        .header(CACHE_CONTROL, "private, no-store")
+        .body(body)
+        .map_err(|e| anyhow!(e))?;
+
+    Ok(response)
+}
+
+/* Media can be large, they are never held in memory nor cached: encrypted attachments are decrypted while they are forwarded */
+async fn stream_media(client: &Client, http_client: &reqwest::Client, source: MediaSource) -> Result<Body, MediaError> {
+    match source {
+        MediaSource::Plain(mxc) => {
+            let response = download(client, http_client, &mxc).await?;
+            Ok(Body::from_stream(response.bytes_stream()))
+        },
+        MediaSource::Encrypted(file) => {
+            let response = download(client, http_client, &file.url).await?;
+            let encrypted = StreamReader::new(Box::pin(response.bytes_stream().map_err(|e| io::Error::new(io::ErrorKind::Other, e))));
+            let mut encrypted = SyncIoBridge::new(encrypted);
+
+            /* The decryptor is a blocking reader, it checks the hash of the file once it reached the end */
+            let (sender, receiver) = mpsc::channel::<Result<Bytes, io::Error>>(MEDIA_CHUNKS_BUFFER);
+            tokio::task::spawn_blocking(move || {
+                let mut decryptor = match AttachmentDecryptor::new(&mut encrypted, (*file).into()) {
+                    Ok(decryptor) => decryptor,
+                    Err(err) => {
+                        let _result = sender.blocking_send(Err(io::Error::new(io::ErrorKind::InvalidData, err)));
+                        return;
+                    }
+                };
+
+                let mut buffer = vec![0u8; MEDIA_CHUNK_SIZE];
+                loop {
+                    match decryptor.read(&mut buffer) {
+                        Ok(0) => {
+                            break;
+                        },
+                        Ok(read) => {
+                            if sender.blocking_send(Ok(Bytes::copy_from_slice(&buffer[..read]))).is_err() {
+                                break;
+                            }
+                        },
+                        Err(err) => {
+                            warn!("WEB|MEDIA: Couldn't decrypt media: {}", &err);
+                            let _result = sender.blocking_send(Err(err));
+                            break;
+                        }
+                    }
+                }
+            });
+
+            Ok(Body::from_stream(ReceiverStream::new(receiver)))
+        }
+    }
+}
+
+/* Authenticated media (Matrix 1.11) with a fallback on the legacy endpoint for older homeservers */
+async fn download(client: &Client, http_client: &reqwest::Client, mxc: &MxcUri) -> Result<reqwest::Response, MediaError> {
+    let (server_name, media_id) = mxc.parts().map_err(|e| anyhow!(e))?;
+    let access_token = client.access_token().ok_or(anyhow!("No Token present in Matrix Client"))?;
+    let homeserver = client.homeserver();
+    let homeserver = homeserver.as_str().trim_end_matches('/');
+
+    let mut response = http_client.get(format!("{}/_matrix/client/v1/media/download/{}/{}", homeserver, server_name, media_id))
+        .bearer_auth(&access_token)
+        .send().await.map_err(|e| anyhow!(e))?;
+
+    if matches!(response.status().as_u16(), 400 | 404 | 405) {
+        response = http_client.get(format!("{}/_matrix/media/v3/download/{}/{}", homeserver, server_name, media_id))
+            .bearer_auth(&access_token)
+            .send().await.map_err(|e| anyhow!(e))?;
+    }
+
+    match response.status().as_u16() {
+        200 => Ok(response),
+        404 => Err(MediaError::NotFound(mxc.to_string())),
+        status => Err(MediaError::InternalServerError(anyhow!("Homeserver answered {} for {}", status, mxc)))
+    }
+}
+
+/* Returns (source, mimetype, filename) */
+fn get_media_source(msgtype: &MessageType) -> Option<(MediaSource, Option<String>, String)> {
+    match msgtype {
+        MessageType::Image(content) => Some((content.source.clone(), content.info.as_ref().and_then(|info| info.mimetype.clone()), content.body.clone())),
+        MessageType::Video(content) => Some((content.source.clone(), content.info.as_ref().and_then(|info| info.mimetype.clone()), content.body.clone())),
+        MessageType::Audio(content) => Some((content.source.clone(), content.info.as_ref().and_then(|info| info.mimetype.clone()), content.body.clone())),
+        MessageType::File(content) => Some((content.source.clone(), content.info.as_ref().and_then(|info| info.mimetype.clone()), content.body.clone())),
+        _ => None
+    }
+}
@@ -0,0 +1,62 @@
+use chrono::{Duration, Utc};
+use hmac::{Hmac, Mac};
+use lazy_static::lazy_static;
+use matrix_sdk::ruma::{EventId, RoomId, UserId};
+use sha2::Sha256;
+
+use crate::web::media::error::MediaError;
+
+pub const MEDIA_BASE_URL: &str = "http://127.0.0.1:8080/media";
+
+/* Links end up in the WLM conversation history, they shouldn't work for long */
+const MEDIA_LINK_LIFETIME_MINUTES: i64 = 15;
+
+lazy_static! {
+    /* Links are only valid for the lifetime of the Tachyon process */
+    static ref MEDIA_LINK_KEY: [u8; 32] = rand::random();
+}
+
+type HmacSha256 = Hmac<Sha256>;
+
+pub fn get_media_link(user_id: &UserId, room_id: &RoomId, event_id: &EventId) -> String {
+    let expires = (Utc::now() + Duration::minutes(MEDIA_LINK_LIFETIME_MINUTES)).timestamp();
+    let signature = hex::encode(sign(user_id.as_str(), room_id.as_str(), event_id.as_str(), expires).finalize().into_bytes());
+
+    format!("{}/{}/{}?user={}&expires={}&sig={}", MEDIA_BASE_URL, urlencoding::encode(room_id.as_str()), urlencoding::encode(event_id.as_str()), urlencoding::encode(user_id.as_str()), expires, signature)
+}
+
+pub fn verify_media_link(user_id: &str, room_id: &str, event_id: &str, expires: i64, signature: &str) -> Result<(), MediaError> {
+    let signature = hex::decode(signature).map_err(|_| MediaError::InvalidSignature)?;
+    sign(user_id, room_id, event_id, expires).verify_slice(&signature).map_err(|_| MediaError::InvalidSignature)?;
+
+    if Utc::now().timestamp() > expires {
+        return Err(MediaError::LinkExpired);
+    }
+
+    Ok(())
+}
+
+/* Usertile ids don't expire, WLM keeps the url for as long as the display picture doesn't change */
+pub fn sign_usertile_id(user_id: &str, mxc: &str) -> String {
+    hex::encode(sign_usertile(user_id, mxc).finalize().into_bytes())
+}
+
+pub fn verify_usertile_id(user_id: &str, mxc: &str, signature: &str) -> bool {
+    let Ok(signature) = hex::decode(signature) else {
+        return false;
+    };
+
+    sign_usertile(user_id, mxc).verify_slice(&signature).is_ok()
+}
+
+fn sign_usertile(user_id: &str, mxc: &str) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(MEDIA_LINK_KEY.as_slice()).expect("HMAC to accept any key size");
+    mac.update(format!("usertile\n{}\n{}", user_id, mxc).as_bytes());
+    mac
+}
+
+fn sign(user_id: &str, room_id: &str, event_id: &str, expires: i64) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(MEDIA_LINK_KEY.as_slice()).expect("HMAC to accept any key size");
+    mac.update(format!("{}\n{}\n{}\n{}", user_id, room_id, event_id, expires).as_bytes());
+    mac
+}
@@ -0,0 +1,3 @@
+pub mod media;
+pub mod media_link;
+pub mod error;
\ No newline at end of file
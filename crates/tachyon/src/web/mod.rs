@@ -1,3 +1,5 @@
 pub mod web_server;
 pub mod web_endpoints;
-pub mod soap;
\ No newline at end of file
+pub mod soap;
+pub mod media;
+pub mod usertile;
\ No newline at end of file
@@ -0,0 +1,72 @@
+use std::str::FromStr;
+
+use anyhow::anyhow;
+use axum::http::StatusCode;
+use axum::response::Response;
+use log::{info, warn};
+use matrix_sdk::Client;
+use matrix_sdk::ruma::events::room::member::MembershipState;
+use msnp::shared::models::email_address::EmailAddress;
+use msnp::shared::models::ticket_token::TicketToken;
+use msnp::soap::abch::ab_service::ab_contact_add::request::AbcontactAddMessageSoapEnvelope;
+use msnp::soap::abch::ab_service::ab_contact_add::response::AbcontactAddResponseMessageSoapEnvelope;
+use msnp::soap::abch::msnab_faults::SoapFaultResponseEnvelope;
+use msnp::soap::traits::xml::ToXml;
+
+use crate::matrix::directs::get_or_create_dm_room;
+use crate::notification::address_book_store::{AddressBookChanges, MemberRole, StoredContact, StoredMember};
+use crate::notification::client_store::ClientData;
+use crate::shared::identifiers::MatrixIdCompatible;
+use crate::shared::traits::ToUuid;
+use crate::web::soap::error::ABError;
+use crate::web::soap::shared;
+
+const AB_CONTACT_ADD_SOAP_ACTION: &str = "http://www.msn.com/webservices/AddressBook/ABContactAdd";
+
+/* The contact stays LivePending in the Forward & Allow lists until the DM invite is accepted */
+pub async fn ab_contact_add(request: AbcontactAddMessageSoapEnvelope, _token: TicketToken, client: Client, client_data: ClientData) -> Result<Response, ABError> {
+    let cache_key = request.header.ok_or(anyhow!("Header missing"))?.application_header.cache_key.unwrap_or_default();
+
+    let contact_info = request.body.ab_contact_add.contacts
+        .and_then(|contacts| contacts.contact.into_iter().next())
+        .and_then(|contact| contact.contact_info)
+        .ok_or(anyhow!("ABContactAdd request contained no contact"))?;
+
+    let passport_name = contact_info.passport_name.ok_or(anyhow!("ABContactAdd contact had no passport name"))?;
+
+    let Ok(contact_addr) = EmailAddress::from_str(&passport_name) else {
+        return shared::build_ab_fault_response(SoapFaultResponseEnvelope::new_email_missing_at_sign(AB_CONTACT_ADD_SOAP_ACTION.to_string()));
+    };
+
+    let Some(contact_user_id) = contact_addr.try_to_owned_user_id() else {
+        return shared::build_ab_fault_response(SoapFaultResponseEnvelope::new_invalid_passport_user(AB_CONTACT_ADD_SOAP_ACTION.to_string(), contact_addr.as_str()));
+    };
+
+    let contact_uuid = contact_addr.to_uuid();
+
+    if let Err(err) = client.account().fetch_user_profile_of(&contact_user_id).await {
+        if err.as_client_api_error().is_some_and(|api_error| api_error.status_code.as_u16() == 404) {
+            warn!("SOAP|ABCH|ABContactAdd: No Matrix user found for: {}", &contact_user_id);
+            return shared::build_ab_fault_response(SoapFaultResponseEnvelope::new_invalid_passport_user(AB_CONTACT_ADD_SOAP_ACTION.to_string(), contact_addr.as_str()));
+        }
+        return Err(ABError::MatrixError(err));
+    }
+
+    if let Some(room) = client.get_dm_room(&contact_user_id) {
+        let is_joined = room.get_member_no_sync(&contact_user_id).await?.is_some_and(|member| member.membership() == &MembershipState::Join);
+        if is_joined {
+            return shared::build_ab_fault_response(SoapFaultResponseEnvelope::new_contact_already_exists(AB_CONTACT_ADD_SOAP_ACTION.to_string(), &contact_uuid));
+        }
+    }
+
+    let room = get_or_create_dm_room(&client, &contact_user_id).await?;
+    info!("SOAP|ABCH|ABContactAdd: Invited {} in DM room: {}", &contact_user_id, room.room_id());
+
+    let mut changes = AddressBookChanges::default();
+    changes.contacts.push(StoredContact::new(contact_addr.as_str(), contact_addr.as_str(), true, false));
+    changes.members.push(StoredMember::new(contact_addr.as_str(), MemberRole::Allow, false));
+    client_data.inner.address_book_store.apply(changes);
+
+    let soap_body = AbcontactAddResponseMessageSoapEnvelope::get_response(&contact_uuid, &cache_key);
+    Ok(shared::build_soap_response(soap_body.to_xml()?, StatusCode::OK))
+}
@@ -0,0 +1,57 @@
+use std::str::FromStr;
+
+use anyhow::anyhow;
+use axum::http::StatusCode;
+use axum::response::Response;
+use log::{info, warn};
+use matrix_sdk::Client;
+use msnp::shared::models::email_address::EmailAddress;
+use msnp::shared::models::ticket_token::TicketToken;
+use msnp::soap::abch::ab_service::ab_contact_delete::request::AbcontactDeleteMessageSoapEnvelope;
+use msnp::soap::abch::ab_service::ab_contact_delete::response::AbcontactDeleteResponseMessageSoapEnvelope;
+use msnp::soap::traits::xml::ToXml;
+
+use crate::matrix::directs::leave_dm_room;
+use crate::notification::address_book_store::{AddressBookChanges, MemberRole, StoredContact, StoredMember};
+use crate::notification::client_store::ClientData;
+use crate::shared::identifiers::MatrixIdCompatible;
+use crate::web::soap::error::ABError;
+use crate::web::soap::shared;
+
+/* WLM sends the Block AddMember on its own when "Also block" is ticked, the DM room is only forgotten if it came first */
+pub async fn ab_contact_delete(request: AbcontactDeleteMessageSoapEnvelope, _token: TicketToken, client: Client, client_data: ClientData) -> Result<Response, ABError> {
+    let cache_key = request.header.ok_or(anyhow!("Header missing"))?.application_header.cache_key.unwrap_or_default();
+
+    let contact_ids: Vec<String> = request.body.ab_contact_delete.contacts
+        .map(|contacts| contacts.contact.into_iter().filter_map(|contact| contact.contact_id).collect())
+        .unwrap_or_default();
+
+    let address_book_store = &client_data.inner.address_book_store;
+    let mut changes = AddressBookChanges::default();
+
+    for contact_id in contact_ids {
+        let Some(contact) = address_book_store.find_contact_by_id(&contact_id) else {
+            warn!("SOAP|ABCH|ABContactDelete: Unknown contact id: {}", &contact_id);
+            continue;
+        };
+
+        let contact_user_id = EmailAddress::from_str(&contact.email).ok().and_then(|contact_addr| contact_addr.try_to_owned_user_id());
+        match contact_user_id.and_then(|contact_user_id| client.get_dm_room(&contact_user_id)) {
+            None => {
+                info!("SOAP|ABCH|ABContactDelete: No DM room found for: {}", &contact.email);
+            },
+            Some(room) => {
+                leave_dm_room(&room, address_book_store.is_blocked(&contact.email)).await?;
+            }
+        }
+
+        changes.contacts.push(StoredContact::new(&contact.email, &contact.display_name, contact.pending, true));
+        changes.members.push(StoredMember::new(&contact.email, MemberRole::Reverse, true));
+        changes.members.push(StoredMember::new(&contact.email, MemberRole::Pending, true));
+    }
+
+    address_book_store.apply(changes);
+
+    let soap_body = AbcontactDeleteResponseMessageSoapEnvelope::get_response(&cache_key);
+    Ok(shared::build_soap_response(soap_body.to_xml()?, StatusCode::OK))
+}
@@ -0,0 +1,58 @@
+use anyhow::anyhow;
+use axum::http::StatusCode;
+use axum::response::Response;
+use log::{debug, info, warn};
+use matrix_sdk::Client;
+use msnp::shared::models::ticket_token::TicketToken;
+use msnp::soap::abch::ab_service::ab_contact_update::request::AbcontactUpdateMessageSoapEnvelope;
+use msnp::soap::abch::ab_service::ab_contact_update::response::AbcontactUpdateResponseMessageSoapEnvelope;
+use msnp::soap::traits::xml::ToXml;
+
+use crate::matrix::events::contact_properties::{fetch_contact_properties, save_contact_properties};
+use crate::notification::client_store::ClientData;
+use crate::web::soap::error::ABError;
+use crate::web::soap::shared;
+
+pub async fn ab_contact_update(request: AbcontactUpdateMessageSoapEnvelope, _token: TicketToken, client: Client, client_data: ClientData) -> Result<Response, ABError> {
+    let cache_key = request.header.ok_or(anyhow!("Header missing"))?.application_header.cache_key.unwrap_or_default();
+
+    let me_uuid = client_data.get_user_clone()?.uuid.to_string();
+    let contacts = request.body.body.contacts.map(|contacts| contacts.contact).unwrap_or_default();
+
+    let address_book_store = &client_data.inner.address_book_store;
+    let _contact_properties_guard = client_data.inner.contact_properties_lock.lock().await;
+    let mut contact_properties = fetch_contact_properties(&client).await?;
+    let mut updated_contacts = Vec::new();
+
+    for contact in contacts {
+        let (Some(contact_id), Some(contact_info)) = (contact.contact_id.as_ref(), contact.contact_info.as_ref()) else {
+            warn!("SOAP|ABCH|ABContactUpdate: Contact without id or info");
+            continue;
+        };
+
+        let properties_changed = contact.properties_changed.as_deref().unwrap_or_default();
+
+        if contact_id.eq_ignore_ascii_case(&me_uuid) {
+            debug!("SOAP|ABCH|ABContactUpdate: Ignoring Me contact update: {}", properties_changed);
+            continue;
+        }
+
+        let Some(stored_contact) = address_book_store.find_contact_by_id(contact_id) else {
+            warn!("SOAP|ABCH|ABContactUpdate: Unknown contact id: {}", contact_id);
+            continue;
+        };
+
+        info!("SOAP|ABCH|ABContactUpdate: Updating {} of: {}", properties_changed, &stored_contact.email);
+        contact_properties.update(&stored_contact.email, contact_info, properties_changed);
+        updated_contacts.push(stored_contact.email);
+    }
+
+    if !updated_contacts.is_empty() {
+        client_data.refresh_contact_nicknames(&contact_properties);
+        save_contact_properties(&client, contact_properties).await?;
+        address_book_store.touch_contacts(updated_contacts.iter().map(String::as_str));
+    }
+
+    let soap_body = AbcontactUpdateResponseMessageSoapEnvelope::get_response(&cache_key);
+    Ok(shared::build_soap_response(soap_body.to_xml()?, StatusCode::OK))
+}
@@ -16,11 +16,16 @@ use msnp::shared::models::ticket_token::TicketToken;
 use msnp::shared::models::uuid::Uuid;
 use msnp::soap::abch::ab_service::ab_find_contacts_paged::request::AbfindContactsPagedMessageSoapEnvelope;
 use msnp::soap::abch::ab_service::ab_find_contacts_paged::response::AbfindContactsPagedResponseMessageSoapEnvelope;
-use msnp::soap::abch::msnab_datatypes::{AbHandleType, AddressBookType, CircleRelationshipRole, ContactType, ContactTypeEnum, RelationshipState};
+use msnp::soap::abch::msnab_datatypes::{AbHandleType, AddressBookType, ArrayOfGuid, CircleRelationshipRole, ContactType, ContactTypeEnum, Guid, RelationshipState};
 use msnp::soap::abch::msnab_faults::SoapFaultResponseEnvelope;
 use msnp::soap::traits::xml::ToXml;
 use crate::matrix::directs::resolve_direct_target;
-use crate::notification::client_store::{ClientData, Contact};
+use crate::matrix::events::contact_groups::fetch_contact_groups;
+use crate::matrix::events::contact_properties::fetch_contact_properties;
+use crate::matrix::events::privacy_settings::fetch_privacy_settings;
+use crate::matrix::group_mapping::get_groups_snapshot;
+use crate::notification::address_book_store::{parse_last_change, StoredCircle, StoredCircleMember, StoredContact};
+use crate::notification::client_store::ClientData;
 use crate::shared::identifiers::MatrixIdCompatible;
 use crate::shared::traits::ToUuid;
 use crate::web::soap::error::ABError;
@@ -58,56 +63,60 @@ pub async fn ab_find_contacts_paged(request : AbfindContactsPagedMessageSoapEnve
 async fn handle_circle_request(request: AbfindContactsPagedMessageSoapEnvelope, ab_id: &str, client: Client, client_data: &mut ClientData) -> Result<Response, ABError> {
     let body = request.body.body;
     let cache_key = request.header.expect("to be here").application_header.cache_key.unwrap_or_default();
+    let address_book_store = &client_data.inner.address_book_store;
 
     if body.filter_options.deltas_only {
+        let last_changed = body.filter_options.last_changed.as_deref();
+        if !address_book_store.is_circle_seeded(ab_id) || !address_book_store.can_serve_delta(last_changed) {
+            debug!("SOAP|ABCH|ABFindContactsPaged: Circle {} cache is too old: {:?}, fullsync required", ab_id, last_changed);
+            return Ok(shared::build_soap_response(SoapFaultResponseEnvelope::new_fullsync_required("http://www.msn.com/webservices/AddressBook/ABFindContactsPaged").to_xml()?, StatusCode::OK));
+        }
 
-        let contacts = client_data.inner.soap_holder.circle_contacts.remove(ab_id).map(|(id, contacts)| contacts).unwrap_or(Vec::new());
+        let contacts = address_book_store.get_circle_members(ab_id, last_changed.and_then(parse_last_change)).iter().map(|member| member.to_contact_type()).collect();
 
         let soap_body = AbfindContactsPagedResponseMessageSoapEnvelope::new_circle(ab_id, &cache_key, contacts);
         Ok(shared::build_soap_response(soap_body.to_xml()?, StatusCode::OK))
 
     } else {
-        //TODO have hashmap to avoid computing all the rooms uuid
-        let ab_id_uuid = Uuid::from_str(ab_id).unwrap();
-        let me = client.user_id().expect("to be here");
+        if !address_book_store.is_circle_seeded(ab_id) {
+            seed_circle(ab_id, &client, client_data).await?;
+        }
 
-        let rooms = client.rooms();
-        let found = rooms.iter().find(|r| {
-            let room_uuid = Uuid::from_seed(r.room_id().as_str());
-            room_uuid == ab_id_uuid
-        });
+        let contacts = client_data.inner.address_book_store.get_circle_members(ab_id, None).iter().map(|member| member.to_contact_type()).collect();
 
-        if found.is_none() {
-            return Err(ABError::InternalServerError(anyhow!("todo")));
-        };
+        let soap_body = AbfindContactsPagedResponseMessageSoapEnvelope::new_circle(ab_id, &cache_key, contacts);
+        Ok(shared::build_soap_response(soap_body.to_xml()?, StatusCode::OK))
+    }
 
-        let found = found.unwrap();
-        let me = found.get_member_no_sync(me).await?.unwrap();
 
+}
 
-        let mut contacts = Vec::new();
+async fn seed_circle(ab_id: &str, client: &Client, client_data: &ClientData) -> Result<(), ABError> {
+    let ab_id_uuid = Uuid::from_str(ab_id).map_err(|e| anyhow!("Invalid circle id: {} - {:?}", ab_id, e))?;
 
-        let mut members = found.members_no_sync(RoomMemberships::JOIN.union(RoomMemberships::INVITE)).await?;
+    let rooms = client.rooms();
+    let found = rooms.iter().find(|r| {
+        let room_uuid = Uuid::from_seed(r.room_id().as_str());
+        room_uuid == ab_id_uuid
+    });
 
-        for current in members.drain(..){
-            match current.membership() {
-                MembershipState::Join => {
-                    let msn_user = MsnUser::with_email_addr(EmailAddress::from_user_id(current.user_id()));
-                    contacts.push(ContactType::new_circle_member_contact(&msn_user.uuid, msn_user.get_email_address().as_str(), current.display_name().unwrap_or(msn_user.get_email_address().as_str()), ContactTypeEnum::Live, RelationshipState::Accepted , CircleRelationshipRole::Member , false));
-                }
-                _ => {
-                    let msn_user = MsnUser::with_email_addr(EmailAddress::from_user_id(current.user_id()));
-                    contacts.push(ContactType::new_circle_member_contact(&msn_user.uuid, msn_user.get_email_address().as_str(), current.display_name().unwrap_or(msn_user.get_email_address().as_str()), ContactTypeEnum::LivePending, RelationshipState::WaitingResponse , CircleRelationshipRole::Member,false));
-                }
-            }
-        }
+    let Some(found) = found else {
+        return Err(ABError::InternalServerError(anyhow!("No room found for circle: {}", ab_id)));
+    };
 
+    let mut circle_members = Vec::new();
 
-        let soap_body = AbfindContactsPagedResponseMessageSoapEnvelope::new_circle(ab_id, &cache_key, contacts);
-        Ok(shared::build_soap_response(soap_body.to_xml()?, StatusCode::OK))
-    }
+    let mut members = found.members_no_sync(RoomMemberships::JOIN.union(RoomMemberships::INVITE)).await?;
 
+    for current in members.drain(..){
+        let msn_addr = EmailAddress::from_user_id(current.user_id());
+        let pending = current.membership() != &MembershipState::Join;
+        circle_members.push(StoredCircleMember::new(msn_addr.as_str(), current.display_name().unwrap_or(msn_addr.as_str()), pending, false));
+    }
 
+    let circle = StoredCircle::new(found.room_id().as_str(), &found.compute_display_name().await?.to_string(), None, false);
+    client_data.inner.address_book_store.seed_circle(circle, circle_members);
+    Ok(())
 }
 
 async fn handle_user_contact_list(request : AbfindContactsPagedMessageSoapEnvelope, client: Client, client_data: &mut ClientData) -> Result<Response, ABError> {
@@ -117,47 +126,54 @@ async fn handle_user_contact_list(request : AbfindContactsPagedMessageSoapEnvelo
     let me_user = client_data.get_user_clone()?;
     let uuid = &me_user.uuid;
     let msn_addr = me_user.get_email_address();
-
-    if body.filter_options.deltas_only {
-        let (contacts, circles) = {
-
-           let mut contacts = Vec::new();
-           let mut circles = Vec::new();
-           for current in get_delta_contact_list(client_data)?.drain(..) {
-                match current {
-                    Contact::Contact(contact) => {
-                        contacts.push(contact);
-                    }
-                    Contact::Circle(circle_data) => {
-                        circles.push(circle_data);
-                    }
-                }
-            }
-            (contacts, circles)
-        };
-
-        let soap_body = AbfindContactsPagedResponseMessageSoapEnvelope::new_individual(uuid.clone(), &cache_key, msn_addr.as_str(), msn_addr.as_str(), contacts, circles,false);
-
-        Ok(shared::build_soap_response(soap_body.to_xml()?, StatusCode::OK))
-
-        //Ok(shared::build_soap_response(SoapFaultResponseEnvelope::new_fullsync_required("http://www.msn.com/webservices/AddressBook/ABFindContactsPaged").to_xml()?, StatusCode::OK))
+    let address_book_store = &client_data.inner.address_book_store;
+    let contact_groups = fetch_contact_groups(&client).await?;
+
+    let since = if body.filter_options.deltas_only {
+        let last_changed = body.filter_options.last_changed.as_deref();
+        let can_serve_groups_delta = last_changed.and_then(parse_last_change).is_some_and(|since| contact_groups.can_serve_delta(since));
+        if !address_book_store.is_contacts_seeded() || !address_book_store.can_serve_delta(last_changed) || !can_serve_groups_delta {
+            debug!("SOAP|ABCH|ABFindContactsPaged: Address book cache is too old: {:?}, fullsync required", last_changed);
+            return Ok(shared::build_soap_response(SoapFaultResponseEnvelope::new_fullsync_required("http://www.msn.com/webservices/AddressBook/ABFindContactsPaged").to_xml()?, StatusCode::OK));
+        }
+        last_changed.and_then(parse_last_change)
     } else {
         // Full contact list demanded.
-        //TODO Circle fullsync
-        let mut contacts = get_fullsync_contact_list(&client, user_id).await?;
-        let soap_body = AbfindContactsPagedResponseMessageSoapEnvelope::new_individual(uuid.clone(), &cache_key, msn_addr.as_str(), msn_addr.as_str(), contacts, Vec::new(),false );
-        Ok(shared::build_soap_response(soap_body.to_xml()?, StatusCode::OK))
-    }
+        let contacts = get_fullsync_contact_list(&client, user_id).await?;
+        address_book_store.reconcile_contacts(contacts);
+        None
+    };
 
-}
+    let groups_snapshot = get_groups_snapshot(&client, &contact_groups, address_book_store, client_data.inner.spaces_as_groups, since).await?;
+    let contact_properties = fetch_contact_properties(&client).await?;
+    client_data.refresh_contact_nicknames(&contact_properties);
+
+    /* WLM sends back the BLP it finds in the Me contact when it logs in */
+    let privacy_settings = fetch_privacy_settings(&client).await?;
+    client_data.set_allow_list_only(privacy_settings.allow_list_only);
 
-fn get_delta_contact_list(client_data: &mut ClientData) -> Result<Vec<Contact>, ABError> {
-    let mut contacts = client_data.get_contact_holder_mut().unwrap();
+    let contacts = address_book_store.get_contacts(since).iter().map(|contact| {
+        let mut contact_type = contact.to_contact_type();
+        let group_ids = groups_snapshot.get_group_ids_of(&contact.email);
+        if let Some(contact_info) = contact_type.contact_info.as_mut() {
+            if let Some(properties) = contact_properties.get(&contact.email) {
+                properties.apply_to(contact_info);
+            }
+
+            if !group_ids.is_empty() {
+                contact_info.group_ids = Some(ArrayOfGuid{ guid: group_ids.into_iter().map(|group_id| Guid{ body: group_id }).collect() });
+            }
+        }
+        contact_type
+    }).collect();
+    let groups = groups_snapshot.groups;
+    let circles = address_book_store.get_circles(since).iter().map(|circle| circle.to_circle_data()).collect();
 
-    Ok(mem::replace(&mut *contacts, Vec::new()))
+    let soap_body = AbfindContactsPagedResponseMessageSoapEnvelope::new_individual(uuid.clone(), &cache_key, msn_addr.as_str(), msn_addr.as_str(), contacts, groups, circles, false, privacy_settings.allow_list_only);
+    Ok(shared::build_soap_response(soap_body.to_xml()?, StatusCode::OK))
 }
 
-async fn get_fullsync_contact_list(matrix_client: &Client, me: &UserId) -> Result<Vec<ContactType>, ABError> {
+async fn get_fullsync_contact_list(matrix_client: &Client, me: &UserId) -> Result<Vec<StoredContact>, ABError> {
     let mut out = Vec::new();
 
     for joined_room in matrix_client.joined_rooms() {
@@ -170,16 +186,13 @@ async fn get_fullsync_contact_list(matrix_client: &Client, me: &UserId) -> Resul
                 }
                 Some(direct_target) => {
 
-                    let target_usr = MsnUser::with_email_addr(EmailAddress::from_user_id(&direct_target));
-                    let target_uuid = target_usr.uuid;
-                    let target_msn_addr = target_usr.endpoint_id.email_addr.to_string();
+                    let target_msn_addr = EmailAddress::from_user_id(&direct_target).to_string();
 
                     match joined_room.get_member(&direct_target).await? {
 
                         None => {
                             //If member is not here, still consider him a contact, if we want to click on him and create a dm room with him.
-                            let contact = ContactType::new(&target_uuid, &target_msn_addr, &target_msn_addr, ContactTypeEnum::Live, false);
-                            out.push(contact);
+                            out.push(StoredContact::new(&target_msn_addr, &target_msn_addr, false, false));
                             debug!("SOAP|ABCH|ABFindContactsPaged: + Live(None) - {}", &target_msn_addr);
                         }
 
@@ -187,13 +200,11 @@ async fn get_fullsync_contact_list(matrix_client: &Client, me: &UserId) -> Resul
                             match member.membership() {
                                 //If member is here, handle memberships
                                 MembershipState::Invite => {
-                                    let contact = ContactType::new(&target_uuid, &target_msn_addr, &target_msn_addr, ContactTypeEnum::LivePending, false);
-                                    out.push(contact);
+                                    out.push(StoredContact::new(&target_msn_addr, &target_msn_addr, true, false));
                                     debug!("SOAP|ABCH|ABFindContactsPaged: + LivePending(Invite) - {}", &target_msn_addr);
                                 }
                                 _ => {
-                                    let contact = ContactType::new(&target_uuid, &target_msn_addr, &target_msn_addr, ContactTypeEnum::Live, false);
-                                    out.push(contact);
+                                    out.push(StoredContact::new(&target_msn_addr, &target_msn_addr, false, false));
                                     debug!("SOAP|ABCH|ABFindContactsPaged: + Live({}) - {}", member.membership() ,&target_msn_addr);
                                 }
                             }
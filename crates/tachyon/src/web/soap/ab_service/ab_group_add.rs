@@ -0,0 +1,49 @@
+use std::str::FromStr;
+
+use anyhow::anyhow;
+use axum::http::StatusCode;
+use axum::response::Response;
+use log::info;
+use matrix_sdk::Client;
+use msnp::shared::models::ticket_token::TicketToken;
+use msnp::shared::models::uuid::Uuid;
+use msnp::soap::abch::ab_service::ab_group_add::request::AbgroupAddMessageSoapEnvelope;
+use msnp::soap::abch::ab_service::ab_group_add::response::AbgroupAddResponseMessageSoapEnvelope;
+use msnp::soap::abch::msnab_faults::SoapFaultResponseEnvelope;
+use msnp::soap::traits::xml::ToXml;
+
+use crate::matrix::events::contact_groups::{fetch_contact_groups, save_contact_groups};
+use crate::notification::client_store::ClientData;
+use crate::web::soap::error::ABError;
+use crate::web::soap::shared;
+
+const AB_GROUP_ADD_SOAP_ACTION: &str = "http://www.msn.com/webservices/AddressBook/ABGroupAdd";
+
+pub const MAX_GROUP_NAME_LENGTH: usize = 61;
+
+pub async fn ab_group_add(request: AbgroupAddMessageSoapEnvelope, _token: TicketToken, client: Client, client_data: ClientData) -> Result<Response, ABError> {
+    let cache_key = request.header.ok_or(anyhow!("Header missing"))?.application_header.cache_key.unwrap_or_default();
+
+    let group_info = request.body.body.group_info.group_info;
+    let name = group_info.name.ok_or(anyhow!("ABGroupAdd request had no group name"))?;
+
+    if name.chars().count() > MAX_GROUP_NAME_LENGTH {
+        return shared::build_ab_fault_response(SoapFaultResponseEnvelope::new_group_name_too_long(AB_GROUP_ADD_SOAP_ACTION.to_string()));
+    }
+
+    let _contact_groups_guard = client_data.inner.contact_groups_lock.lock().await;
+
+    let mut contact_groups = fetch_contact_groups(&client).await?;
+
+    if let Some(existing_group_id) = contact_groups.find_by_name(&name) {
+        let existing_group_id = Uuid::from_str(existing_group_id).map_err(|e| anyhow!("Invalid group id: {} - {:?}", existing_group_id, e))?;
+        return shared::build_ab_fault_response(SoapFaultResponseEnvelope::new_group_already_exists(AB_GROUP_ADD_SOAP_ACTION.to_string(), &existing_group_id));
+    }
+
+    let group_id = contact_groups.add_group(&name, group_info.is_favorite.unwrap_or(false));
+    save_contact_groups(&client, contact_groups).await?;
+    info!("SOAP|ABCH|ABGroupAdd: Added group {}: {}", &group_id, &name);
+
+    let soap_body = AbgroupAddResponseMessageSoapEnvelope::get_response(&group_id, &cache_key);
+    Ok(shared::build_soap_response(soap_body.to_xml()?, StatusCode::OK))
+}
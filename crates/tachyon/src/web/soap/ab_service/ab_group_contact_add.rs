@@ -0,0 +1,65 @@
+use std::str::FromStr;
+
+use anyhow::anyhow;
+use axum::http::StatusCode;
+use axum::response::Response;
+use log::{info, warn};
+use matrix_sdk::Client;
+use msnp::shared::models::ticket_token::TicketToken;
+use msnp::shared::models::uuid::Uuid;
+use msnp::soap::abch::ab_service::ab_group_contact_add::request::AbgroupContactAddMessageSoapEnvelope;
+use msnp::soap::abch::ab_service::ab_group_contact_add::response::AbgroupContactAddResponseMessageSoapEnvelope;
+use msnp::soap::abch::msnab_faults::SoapFaultResponseEnvelope;
+use msnp::soap::traits::xml::ToXml;
+
+use crate::matrix::events::contact_groups::{fetch_contact_groups, save_contact_groups};
+use crate::matrix::group_mapping::{add_contact_to_group, resolve_group, MappedGroup};
+use crate::notification::client_store::ClientData;
+use crate::web::soap::error::ABError;
+use crate::web::soap::shared;
+
+const AB_GROUP_CONTACT_ADD_SOAP_ACTION: &str = "http://www.msn.com/webservices/AddressBook/ABGroupContactAdd";
+
+pub async fn ab_group_contact_add(request: AbgroupContactAddMessageSoapEnvelope, _token: TicketToken, client: Client, client_data: ClientData) -> Result<Response, ABError> {
+    let cache_key = request.header.ok_or(anyhow!("Header missing"))?.application_header.cache_key.unwrap_or_default();
+
+    let body = request.body.ab_group_contact_add;
+    let group_ids = body.group_filter.group_ids.map(|group_ids| group_ids.guid).unwrap_or_default();
+    let contact_ids: Vec<String> = body.contacts.map(|contacts| contacts.contact.into_iter().filter_map(|contact| contact.contact_id).collect()).unwrap_or_default();
+
+    let address_book_store = &client_data.inner.address_book_store;
+    let _contact_groups_guard = client_data.inner.contact_groups_lock.lock().await;
+    let mut contact_groups = fetch_contact_groups(&client).await?;
+    let mut group_contacts = Vec::new();
+
+    for group_id in &group_ids {
+        if matches!(resolve_group(&client, &contact_groups, client_data.inner.spaces_as_groups, &group_id.body), MappedGroup::Unknown) {
+            warn!("SOAP|ABCH|ABGroupContactAdd: Unknown group id: {}", &group_id.body);
+            let group_uuid = Uuid::from_str(&group_id.body).map_err(|e| anyhow!("Invalid group id: {} - {:?}", &group_id.body, e))?;
+            return shared::build_ab_fault_response(SoapFaultResponseEnvelope::new_group_doesnt_exist(AB_GROUP_CONTACT_ADD_SOAP_ACTION.to_string(), &group_uuid));
+        }
+    }
+
+    for contact_id in &contact_ids {
+        let Some(contact) = address_book_store.find_contact_by_id(contact_id) else {
+            warn!("SOAP|ABCH|ABGroupContactAdd: Unknown contact id: {}", contact_id);
+            let contact_uuid = Uuid::from_str(contact_id).map_err(|e| anyhow!("Invalid contact id: {} - {:?}", contact_id, e))?;
+            return shared::build_ab_fault_response(SoapFaultResponseEnvelope::new_contact_doesnt_exist(AB_GROUP_CONTACT_ADD_SOAP_ACTION.to_string(), &contact_uuid));
+        };
+
+        for group_id in &group_ids {
+            if add_contact_to_group(&client, &mut contact_groups, client_data.inner.spaces_as_groups, &group_id.body, &contact.email).await? {
+                info!("SOAP|ABCH|ABGroupContactAdd: Added {} to group: {}", &contact.email, &group_id.body);
+            } else {
+                warn!("SOAP|ABCH|ABGroupContactAdd: Couldn't add {} to group: {}", &contact.email, &group_id.body);
+            }
+        }
+        group_contacts.push(contact.email);
+    }
+
+    save_contact_groups(&client, contact_groups).await?;
+    address_book_store.touch_contacts(group_contacts.iter().map(String::as_str));
+
+    let soap_body = AbgroupContactAddResponseMessageSoapEnvelope::get_response(contact_ids.first().map(String::as_str).unwrap_or_default(), &cache_key);
+    Ok(shared::build_soap_response(soap_body.to_xml()?, StatusCode::OK))
+}
@@ -0,0 +1,48 @@
+use anyhow::anyhow;
+use axum::http::StatusCode;
+use axum::response::Response;
+use log::{info, warn};
+use matrix_sdk::Client;
+use msnp::shared::models::ticket_token::TicketToken;
+use msnp::soap::abch::ab_service::ab_group_contact_delete::requets::AbgroupContactDeleteMessageSoapEnvelope;
+use msnp::soap::abch::ab_service::ab_group_contact_delete::response::AbgroupContactDeleteResponseMessageSoapEnvelope;
+use msnp::soap::traits::xml::ToXml;
+
+use crate::matrix::events::contact_groups::{fetch_contact_groups, save_contact_groups};
+use crate::matrix::group_mapping::remove_contact_from_group;
+use crate::notification::client_store::ClientData;
+use crate::web::soap::error::ABError;
+use crate::web::soap::shared;
+
+pub async fn ab_group_contact_delete(request: AbgroupContactDeleteMessageSoapEnvelope, _token: TicketToken, client: Client, client_data: ClientData) -> Result<Response, ABError> {
+    let cache_key = request.header.ok_or(anyhow!("Header missing"))?.application_header.cache_key.unwrap_or_default();
+
+    let body = request.body.body;
+    let group_ids = body.group_filter.group_ids.map(|group_ids| group_ids.guid).unwrap_or_default();
+    let contact_ids: Vec<String> = body.contacts.map(|contacts| contacts.contact.into_iter().filter_map(|contact| contact.contact_id).collect()).unwrap_or_default();
+
+    let address_book_store = &client_data.inner.address_book_store;
+    let _contact_groups_guard = client_data.inner.contact_groups_lock.lock().await;
+    let mut contact_groups = fetch_contact_groups(&client).await?;
+    let mut group_contacts = Vec::new();
+
+    for contact_id in &contact_ids {
+        let Some(contact) = address_book_store.find_contact_by_id(contact_id) else {
+            warn!("SOAP|ABCH|ABGroupContactDelete: Unknown contact id: {}", contact_id);
+            continue;
+        };
+
+        for group_id in &group_ids {
+            if remove_contact_from_group(&client, &mut contact_groups, client_data.inner.spaces_as_groups, &group_id.body, &contact.email).await? {
+                info!("SOAP|ABCH|ABGroupContactDelete: Removed {} from group: {}", &contact.email, &group_id.body);
+            }
+        }
+        group_contacts.push(contact.email);
+    }
+
+    save_contact_groups(&client, contact_groups).await?;
+    address_book_store.touch_contacts(group_contacts.iter().map(String::as_str));
+
+    let soap_body = AbgroupContactDeleteResponseMessageSoapEnvelope::get_response(&cache_key);
+    Ok(shared::build_soap_response(soap_body.to_xml()?, StatusCode::OK))
+}
@@ -0,0 +1,62 @@
+use std::str::FromStr;
+
+use anyhow::anyhow;
+use axum::http::StatusCode;
+use axum::response::Response;
+use log::{info, warn};
+use matrix_sdk::Client;
+use msnp::shared::models::ticket_token::TicketToken;
+use msnp::shared::models::uuid::Uuid;
+use msnp::soap::abch::ab_service::ab_group_delete::request::AbgroupDeleteMessageSoapEnvelope;
+use msnp::soap::abch::ab_service::ab_group_delete::response::AbgroupDeleteResponseMessageSoapEnvelope;
+use msnp::soap::abch::msnab_faults::SoapFaultResponseEnvelope;
+use msnp::soap::traits::xml::ToXml;
+
+use crate::matrix::events::contact_groups::{fetch_contact_groups, save_contact_groups};
+use crate::matrix::group_mapping::{resolve_group, MappedGroup};
+use crate::notification::client_store::ClientData;
+use crate::web::soap::error::ABError;
+use crate::web::soap::shared;
+
+const AB_GROUP_DELETE_SOAP_ACTION: &str = "http://www.msn.com/webservices/AddressBook/ABGroupDelete";
+
+pub async fn ab_group_delete(request: AbgroupDeleteMessageSoapEnvelope, _token: TicketToken, client: Client, client_data: ClientData) -> Result<Response, ABError> {
+    let cache_key = request.header.ok_or(anyhow!("Header missing"))?.application_header.cache_key.unwrap_or_default();
+
+    let group_ids = request.body.ab_group_delete.group_filter.group_ids.map(|group_ids| group_ids.guid).unwrap_or_default();
+
+    let _contact_groups_guard = client_data.inner.contact_groups_lock.lock().await;
+
+    let mut contact_groups = fetch_contact_groups(&client).await?;
+    let mut group_contacts = Vec::new();
+
+    for group_id in group_ids {
+        match resolve_group(&client, &contact_groups, client_data.inner.spaces_as_groups, &group_id.body) {
+            MappedGroup::Favorites => {
+                warn!("SOAP|ABCH|ABGroupDelete: Tried to delete the Favorites group: {}", &group_id.body);
+                return shared::build_ab_fault_response(SoapFaultResponseEnvelope::new_generic("The Favorites group can't be deleted".to_string()));
+            },
+            MappedGroup::Space(space) => {
+                warn!("SOAP|ABCH|ABGroupDelete: Tried to delete Space group {}: {}", &group_id.body, space.room_id());
+                return shared::build_ab_fault_response(SoapFaultResponseEnvelope::new_generic("Space groups are removed by leaving the Space from Matrix".to_string()));
+            },
+            MappedGroup::Unknown => {
+                warn!("SOAP|ABCH|ABGroupDelete: Unknown group: {}", &group_id.body);
+                let group_uuid = Uuid::from_str(&group_id.body).map_err(|e| anyhow!("Invalid group id: {} - {:?}", &group_id.body, e))?;
+                return shared::build_ab_fault_response(SoapFaultResponseEnvelope::new_group_doesnt_exist(AB_GROUP_DELETE_SOAP_ACTION.to_string(), &group_uuid));
+            },
+            MappedGroup::Stored => {
+                if let Some(contacts) = contact_groups.delete_group(&group_id.body) {
+                    info!("SOAP|ABCH|ABGroupDelete: Deleted group: {}", &group_id.body);
+                    group_contacts.extend(contacts);
+                }
+            }
+        }
+    }
+
+    save_contact_groups(&client, contact_groups).await?;
+    client_data.inner.address_book_store.touch_contacts(group_contacts.iter().map(String::as_str));
+
+    let soap_body = AbgroupDeleteResponseMessageSoapEnvelope::get_response(&cache_key);
+    Ok(shared::build_soap_response(soap_body.to_xml()?, StatusCode::OK))
+}
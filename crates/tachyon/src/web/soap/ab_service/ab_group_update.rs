@@ -0,0 +1,66 @@
+use std::str::FromStr;
+
+use anyhow::anyhow;
+use axum::http::StatusCode;
+use axum::response::Response;
+use log::{info, warn};
+use matrix_sdk::Client;
+use msnp::shared::models::ticket_token::TicketToken;
+use msnp::shared::models::uuid::Uuid;
+use msnp::soap::abch::ab_service::ab_group_update::request::AbgroupUpdateMessageSoapEnvelope;
+use msnp::soap::abch::ab_service::ab_group_update::response::AbgroupUpdateResponseMessageSoapEnvelope;
+use msnp::soap::abch::msnab_faults::SoapFaultResponseEnvelope;
+use msnp::soap::traits::xml::ToXml;
+
+use crate::matrix::events::contact_groups::{fetch_contact_groups, save_contact_groups};
+use crate::matrix::group_mapping::{resolve_group, MappedGroup};
+use crate::notification::client_store::ClientData;
+use crate::web::soap::ab_service::ab_group_add::MAX_GROUP_NAME_LENGTH;
+use crate::web::soap::error::ABError;
+use crate::web::soap::shared;
+
+const AB_GROUP_UPDATE_SOAP_ACTION: &str = "http://www.msn.com/webservices/AddressBook/ABGroupUpdate";
+
+/* Renaming is the only group update WLM does */
+pub async fn ab_group_update(request: AbgroupUpdateMessageSoapEnvelope, _token: TicketToken, client: Client, client_data: ClientData) -> Result<Response, ABError> {
+    let cache_key = request.header.ok_or(anyhow!("Header missing"))?.application_header.cache_key.unwrap_or_default();
+
+    let _contact_groups_guard = client_data.inner.contact_groups_lock.lock().await;
+
+    let mut contact_groups = fetch_contact_groups(&client).await?;
+
+    for group in request.body.ab_group_update.groups.group {
+        let Some(name) = group.group_info.name else {
+            warn!("SOAP|ABCH|ABGroupUpdate: Unsupported group update: {}", &group.properties_changed);
+            continue;
+        };
+
+        if name.chars().count() > MAX_GROUP_NAME_LENGTH {
+            return shared::build_ab_fault_response(SoapFaultResponseEnvelope::new_group_name_too_long(AB_GROUP_UPDATE_SOAP_ACTION.to_string()));
+        }
+
+        match resolve_group(&client, &contact_groups, client_data.inner.spaces_as_groups, &group.group_id) {
+            MappedGroup::Space(space) => {
+                warn!("SOAP|ABCH|ABGroupUpdate: Tried to rename Space group {}: {}", &group.group_id, space.room_id());
+                return shared::build_ab_fault_response(SoapFaultResponseEnvelope::new_generic("Space groups are renamed from Matrix".to_string()));
+            },
+            MappedGroup::Unknown => {
+                warn!("SOAP|ABCH|ABGroupUpdate: Unknown group: {}", &group.group_id);
+                let group_uuid = Uuid::from_str(&group.group_id).map_err(|e| anyhow!("Invalid group id: {} - {:?}", &group.group_id, e))?;
+                return shared::build_ab_fault_response(SoapFaultResponseEnvelope::new_group_doesnt_exist(AB_GROUP_UPDATE_SOAP_ACTION.to_string(), &group_uuid));
+            },
+            MappedGroup::Favorites | MappedGroup::Stored => {
+                /* The default Favorites group isn't in our account data until it's renamed */
+                if !contact_groups.rename_group(&group.group_id, &name) {
+                    contact_groups.add_favorites_group(&group.group_id, &name);
+                }
+                info!("SOAP|ABCH|ABGroupUpdate: Renamed group {}: {}", &group.group_id, &name);
+            }
+        }
+    }
+
+    save_contact_groups(&client, contact_groups).await?;
+
+    let soap_body = AbgroupUpdateResponseMessageSoapEnvelope::get_response(&cache_key);
+    Ok(shared::build_soap_response(soap_body.to_xml()?, StatusCode::OK))
+}
@@ -19,6 +19,9 @@ use msnp::soap::abch::ab_service::ab_find_contacts_paged::request::AbfindContact
 use msnp::soap::abch::ab_service::ab_find_contacts_paged::response::AbfindContactsPagedResponseMessageSoapEnvelope;
 use msnp::soap::abch::ab_service::ab_group_add::request::AbgroupAddMessageSoapEnvelope;
 use msnp::soap::abch::ab_service::ab_group_contact_add::request::AbgroupContactAddMessageSoapEnvelope;
+use msnp::soap::abch::ab_service::ab_group_contact_delete::requets::AbgroupContactDeleteMessageSoapEnvelope;
+use msnp::soap::abch::ab_service::ab_group_delete::request::AbgroupDeleteMessageSoapEnvelope;
+use msnp::soap::abch::ab_service::ab_group_update::request::AbgroupUpdateMessageSoapEnvelope;
 use msnp::soap::abch::msnab_datatypes::{ContactType, ContactTypeEnum};
 use msnp::soap::abch::msnab_faults::SoapFaultResponseEnvelope;
 use msnp::soap::abch::request_header::AuthHeaderSoapEnvelope;
@@ -26,7 +29,15 @@ use msnp::soap::traits::xml::{ToXml, TryFromXml};
 use crate::matrix::directs::resolve_direct_target;
 use crate::notification::client_store::ClientStoreFacade;
 use crate::shared::identifiers::MatrixIdCompatible;
+use crate::web::soap::ab_service::ab_contact_add::ab_contact_add;
+use crate::web::soap::ab_service::ab_contact_delete::ab_contact_delete;
+use crate::web::soap::ab_service::ab_contact_update::ab_contact_update;
 use crate::web::soap::ab_service::ab_find_contacts_paged::ab_find_contacts_paged;
+use crate::web::soap::ab_service::ab_group_add::ab_group_add;
+use crate::web::soap::ab_service::ab_group_contact_add::ab_group_contact_add;
+use crate::web::soap::ab_service::ab_group_contact_delete::ab_group_contact_delete;
+use crate::web::soap::ab_service::ab_group_delete::ab_group_delete;
+use crate::web::soap::ab_service::ab_group_update::ab_group_update;
 use crate::web::soap::error::ABError;
 use crate::web::soap::error::ABError::InternalServerError;
 use crate::web::soap::shared;
@@ -53,18 +64,30 @@ pub async fn address_book_service(headers: HeaderMap, State(state): State<Client
             ab_find_contacts_paged(AbfindContactsPagedMessageSoapEnvelope::try_from_xml(&body)?, token, client, client_data).await
         },
         "http://www.msn.com/webservices/AddressBook/ABContactAdd" => {
-            ab_contact_add(AbcontactAddMessageSoapEnvelope::try_from_xml(&body)?, token).await
+            ab_contact_add(AbcontactAddMessageSoapEnvelope::try_from_xml(&body)?, token, client, client_data).await
         },
         "http://www.msn.com/webservices/AddressBook/ABContactDelete" => {
-            ab_contact_delete(AbcontactDeleteMessageSoapEnvelope::try_from_xml(&body)?, token).await
+            ab_contact_delete(AbcontactDeleteMessageSoapEnvelope::try_from_xml(&body)?, token, client, client_data).await
 
         },
         "http://www.msn.com/webservices/AddressBook/ABContactUpdate" => {
-            ab_contact_update(AbcontactUpdateMessageSoapEnvelope::try_from_xml(&body)?, token).await
+            ab_contact_update(AbcontactUpdateMessageSoapEnvelope::try_from_xml(&body)?, token, client, client_data).await
 
         },
         "http://www.msn.com/webservices/AddressBook/ABGroupAdd" => {
-            ab_group_add(AbgroupAddMessageSoapEnvelope::try_from_xml(&body)?, token).await
+            ab_group_add(AbgroupAddMessageSoapEnvelope::try_from_xml(&body)?, token, client, client_data).await
+        },
+        "http://www.msn.com/webservices/AddressBook/ABGroupUpdate" => {
+            ab_group_update(AbgroupUpdateMessageSoapEnvelope::try_from_xml(&body)?, token, client, client_data).await
+        },
+        "http://www.msn.com/webservices/AddressBook/ABGroupDelete" => {
+            ab_group_delete(AbgroupDeleteMessageSoapEnvelope::try_from_xml(&body)?, token, client, client_data).await
+        },
+        "http://www.msn.com/webservices/AddressBook/ABGroupContactAdd" => {
+            ab_group_contact_add(AbgroupContactAddMessageSoapEnvelope::try_from_xml(&body)?, token, client, client_data).await
+        },
+        "http://www.msn.com/webservices/AddressBook/ABGroupContactDelete" => {
+            ab_group_contact_delete(AbgroupContactDeleteMessageSoapEnvelope::try_from_xml(&body)?, token, client, client_data).await
         },
         _ => {
             error!("SOAP|ABCH: Unsupported soap action: {}", &soap_action);
@@ -72,21 +95,3 @@ pub async fn address_book_service(headers: HeaderMap, State(state): State<Client
         }
     }
 }
-
-
-
-async fn ab_contact_add(request : AbcontactAddMessageSoapEnvelope, token: TicketToken) -> Result<Response, ABError> {
-    todo!()
-}
-
-async fn ab_contact_delete(request : AbcontactDeleteMessageSoapEnvelope, token: TicketToken) -> Result<Response, ABError> {
-    todo!()
-}
-
-async fn ab_contact_update(request : AbcontactUpdateMessageSoapEnvelope, token: TicketToken) -> Result<Response, ABError> {
-    todo!()
-}
-
-async fn ab_group_add(request : AbgroupAddMessageSoapEnvelope, token: TicketToken) -> Result<Response, ABError> {
-    todo!()
-}
\ No newline at end of file
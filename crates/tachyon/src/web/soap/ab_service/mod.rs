@@ -1,2 +1,10 @@
 pub mod ab_service;
-pub mod ab_find_contacts_paged;
\ No newline at end of file
+pub mod ab_find_contacts_paged;
+pub mod ab_contact_add;
+pub mod ab_contact_delete;
+pub mod ab_contact_update;
+pub mod ab_group_add;
+pub mod ab_group_update;
+pub mod ab_group_delete;
+pub mod ab_group_contact_add;
+pub mod ab_group_contact_delete;
\ No newline at end of file
@@ -6,3 +6,4 @@ pub mod error;
 pub mod shared;
 pub mod storage_service;
 pub mod rsi;
+pub mod oim;
\ No newline at end of file
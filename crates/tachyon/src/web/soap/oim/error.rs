@@ -0,0 +1,87 @@
+use axum::http::header::ToStrError;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use log::error;
+use thiserror::Error;
+use msnp::soap::error::SoapMarshallError;
+use msnp::soap::rsi::faults::SoapFaultResponseEnvelope;
+use msnp::soap::traits::xml::ToXml;
+use crate::notification::client_store::ClientStoreError;
+use crate::web::soap::shared::build_soap_response;
+
+pub const OIM_STORE_URL: &str = "https://ows.messenger.msn.com/OimWS/oim.asmx";
+
+#[derive(Error, Debug)]
+pub enum OIMStoreError {
+
+    #[error("Couldn't authenticate client")]
+    AuthenticationFailed {source: anyhow::Error},
+    #[error("Client has to solve the lock key challenge: {}", .challenge)]
+    LockKeyChallenge {challenge: String},
+    #[error(transparent)]
+    ClientStoreError(#[from] ClientStoreError),
+    #[error("Mandatory header: {} was missing from request.", .0)]
+    MissingHeader(String),
+    #[error(transparent)]
+    HeaderParseError(#[from] ToStrError),
+    #[error(transparent)]
+    SoapMarshallError(#[from] SoapMarshallError),
+    #[error(transparent)]
+    InternalServerError(#[from] anyhow::Error),
+    #[error(transparent)]
+    MatrixError(#[from] matrix_sdk::Error),
+    #[error("Unsupported Soap Action: {}", .0)]
+    UnsupportedSoapAction(String),
+    #[error("System not available")]
+    SystemNotAvailable
+
+}
+
+impl IntoResponse for OIMStoreError {
+    fn into_response(self) -> Response {
+        error!("SOAP|OIM: {:?}", &self);
+
+        let soap_resp_body = match self {
+            OIMStoreError::AuthenticationFailed { .. } => {
+                SoapFaultResponseEnvelope::new_authentication_failed(OIM_STORE_URL, None, None)
+            },
+            OIMStoreError::LockKeyChallenge { challenge } => {
+                SoapFaultResponseEnvelope::new_authentication_failed(OIM_STORE_URL, None, Some(challenge))
+            },
+            OIMStoreError::MissingHeader(_) | OIMStoreError::HeaderParseError(_) => {
+                SoapFaultResponseEnvelope::new_schema_validator_error(OIM_STORE_URL)
+            },
+            OIMStoreError::UnsupportedSoapAction(soap_action) => {
+                SoapFaultResponseEnvelope::new_unknown_soap_action(soap_action)
+            },
+            OIMStoreError::SoapMarshallError(cause) => {
+                match cause {
+                    SoapMarshallError::DeserializationError { .. } => {
+                        SoapFaultResponseEnvelope::new_schema_validator_error(OIM_STORE_URL)
+                    }
+                    SoapMarshallError::SerializationError { .. } => {
+                        SoapFaultResponseEnvelope::new_generic("Failed to marshall response".into())
+                    }
+                }
+            },
+            OIMStoreError::SystemNotAvailable => {
+                SoapFaultResponseEnvelope::new_system_unavailable()
+            },
+            _ => {
+                SoapFaultResponseEnvelope::new_generic("An error has occured".into())
+            }
+        };
+
+        let body = match soap_resp_body.to_xml() {
+            Ok(response_body) => {
+                response_body
+            }
+            Err(err) => {
+                error!("SOAP|OIM: Couldn't marshall error response: {:?}", err);
+                crate::web::soap::error::MARSHALL_ERROR.to_string()
+            }
+        };
+
+        build_soap_response(body, StatusCode::INTERNAL_SERVER_ERROR)
+    }
+}
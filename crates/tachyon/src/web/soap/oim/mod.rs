@@ -0,0 +1,3 @@
+pub mod oim;
+pub mod error;
+mod store2;
\ No newline at end of file
@@ -0,0 +1,39 @@
+use anyhow::anyhow;
+use axum::extract::State;
+use axum::http::HeaderMap;
+use axum::response::Response;
+
+use msnp::shared::models::ticket_token::TicketToken;
+use msnp::soap::oim::store2::request::Store2MessageSoapEnvelope;
+use msnp::soap::traits::xml::TryFromXml;
+
+use crate::notification::client_store::ClientStoreFacade;
+use crate::web::soap::oim::error::OIMStoreError;
+use crate::web::soap::oim::store2::store2;
+
+pub async fn oim_store_service(headers: HeaderMap, State(state): State<ClientStoreFacade>, body: String) -> Result<Response, OIMStoreError> {
+
+    let soap_action = headers.get("SOAPAction").ok_or(OIMStoreError::MissingHeader("SOAPAction".into()))?.to_str()?.trim_start_matches("\"").trim_end_matches("\"");
+
+    match soap_action {
+        "http://messenger.live.com/ws/2006/09/oim/Store2" => {
+            let request = Store2MessageSoapEnvelope::try_from_xml(&body)?;
+            let header = request.header.as_ref().ok_or(OIMStoreError::AuthenticationFailed { source: anyhow!("Missing Soap Header") })?;
+            let token = TicketToken(header.ticket.get_token().ok_or(OIMStoreError::AuthenticationFailed { source: anyhow!("Missing token in Ticket: {}", &header.ticket.passport) })?.to_string());
+
+            let mut client_data = state.get_client_data(&token.0).ok_or(OIMStoreError::AuthenticationFailed { source: anyhow!("Missing Client Data in client store") })?;
+
+            let client = client_data.get_matrix_client();
+
+            let client_token = client.access_token().ok_or(OIMStoreError::AuthenticationFailed { source: anyhow!("No Token present in Matrix Client") })?;
+            if token != client_token {
+                return Err(OIMStoreError::AuthenticationFailed { source: anyhow!("Supplied Token & Matrix Token don't match: {} == {}", &token.0, &client_token) });
+            }
+
+            store2(request, token, client, &mut client_data).await
+        },
+        _ => {
+            Err(OIMStoreError::UnsupportedSoapAction(soap_action.to_string()))
+        }
+    }
+}
@@ -0,0 +1,66 @@
+use std::str::FromStr;
+
+use anyhow::anyhow;
+use axum::http::StatusCode;
+use axum::response::Response;
+use log::{debug, warn};
+use matrix_sdk::Client;
+use matrix_sdk::ruma::events::room::message::RoomMessageEventContent;
+
+use msnp::shared::converters::challenge::{compute_challenge_response, generate_challenge, get_product_key};
+use msnp::shared::models::email_address::EmailAddress;
+use msnp::shared::models::ticket_token::TicketToken;
+use msnp::soap::oim::store2::request::{Store2MessageSoapEnvelope, Ticket};
+use msnp::soap::oim::store2::response::Store2ResponseSoapEnvelope;
+use msnp::soap::traits::xml::ToXml;
+
+use crate::notification::client_store::ClientData;
+use crate::shared::identifiers::MatrixIdCompatible;
+use crate::web::soap::oim::error::OIMStoreError;
+use crate::web::soap::shared;
+
+/* Offline messages are sent to the contact's DM room, they will be delivered by Matrix whenever the contact comes back */
+pub async fn store2(request: Store2MessageSoapEnvelope, _token: TicketToken, client: Client, client_data: &mut ClientData) -> Result<Response, OIMStoreError> {
+    let header = request.header.ok_or(OIMStoreError::AuthenticationFailed { source: anyhow!("Missing Soap Header") })?;
+
+    verify_lock_key(&header.ticket, client_data)?;
+
+    let me = client_data.get_user()?.get_email_address().clone();
+    if header.from.member_name != me.as_str() {
+        return Err(OIMStoreError::AuthenticationFailed { source: anyhow!("OIM sender {} is not the logged in user {}", &header.from.member_name, &me) });
+    }
+
+    let recipient = EmailAddress::from_str(&header.to.member_name).map_err(|e| anyhow!(e))?;
+    let oim = request.body.get_oim().map_err(|e| anyhow!(e))?;
+
+    let Some(room) = client.get_dm_room(&recipient.to_owned_user_id()) else {
+        warn!("SOAP|OIM: No DM room found for OIM recipient: {}", &recipient);
+        return Err(OIMStoreError::SystemNotAvailable);
+    };
+
+    debug!("SOAP|OIM: Sending OIM {} to {}", oim.seq_number, room.room_id());
+    room.send(RoomMessageEventContent::text_plain(oim.content)).await?;
+
+    let soap_body = Store2ResponseSoapEnvelope::new(header.sequence.message_number);
+    Ok(shared::build_soap_response(soap_body.to_xml()?, StatusCode::OK))
+}
+
+/* The client has to answer a challenge with its product key before it is allowed to store OIMs.
+The lock key stays valid for the whole session. Clients we don't know the product key of can't answer it */
+fn verify_lock_key(ticket: &Ticket, client_data: &ClientData) -> Result<(), OIMStoreError> {
+    let Some(product_key) = get_product_key(&ticket.app_id) else {
+        return Err(OIMStoreError::AuthenticationFailed { source: anyhow!("Unknown product id {}, can't verify lock key", &ticket.app_id) });
+    };
+
+    let mut challenge = client_data.inner.soap_holder.oim_lock_key_challenge.lock().map_err(|e| anyhow!("OIM Lock key mutex was poisonned: {}", e))?;
+
+    if let (Some(current_challenge), false) = (challenge.as_ref(), ticket.lock_key.is_empty()) {
+        if compute_challenge_response(current_challenge, &ticket.app_id, product_key) == ticket.lock_key {
+            return Ok(());
+        }
+    }
+
+    let new_challenge = generate_challenge();
+    *challenge = Some(new_challenge.clone());
+    Err(OIMStoreError::LockKeyChallenge { challenge: new_challenge })
+}
@@ -1,11 +1,19 @@
+use std::collections::HashMap;
+
 use axum::http::StatusCode;
 use axum::response::Response;
+use chrono::{DateTime, Utc};
+use log::{debug, warn};
 use matrix_sdk::Client;
+use matrix_sdk::ruma::{OwnedEventId, OwnedRoomId};
+use matrix_sdk::ruma::api::client::receipt::create_receipt::v3::ReceiptType;
+use matrix_sdk::ruma::events::receipt::ReceiptThread;
 use msnp::shared::models::ticket_token::TicketToken;
 use msnp::soap::rsi::delete_messages::request::DeleteMessagesSoapEnvelope;
 use msnp::soap::rsi::delete_messages::response::DeleteMessagesResponseSoapEnvelope;
 use msnp::soap::rsi::get_metadata::request::GetMetadataMessageSoapEnvelope;
 use msnp::soap::traits::xml::ToXml;
+use crate::matrix::oim::parse_oim_message_id;
 use crate::notification::client_store::ClientData;
 use crate::web::soap::rsi::error::RSIError;
 use crate::web::soap::shared;
@@ -14,8 +22,34 @@ pub async fn delete_messages(request : DeleteMessagesSoapEnvelope, token: Ticket
 
     let message_ids = request.body.body.message_ids.message_id;
 
+    //WLM deletes OIMs once they are displayed, mark the room as read up to the latest one
+    let mut read_markers: HashMap<OwnedRoomId, (DateTime<Utc>, OwnedEventId)> = HashMap::new();
+
     for message_id in message_ids {
-        client_data.remove_oim(&message_id);
+        let Some((_, oim)) = client_data.remove_oim(&message_id) else {
+            continue;
+        };
+
+        let Some((room_id, event_id)) = parse_oim_message_id(&message_id) else {
+            warn!("SOAP|RSI: Couldn't parse OIM message id: {}", &message_id);
+            continue;
+        };
+
+        match read_markers.get(&room_id) {
+            Some((recv_datetime, _)) if *recv_datetime >= oim.recv_datetime => {},
+            _ => {
+                read_markers.insert(room_id, (oim.recv_datetime, event_id));
+            }
+        }
+    }
+
+    for (room_id, (_, event_id)) in read_markers {
+        let Some(room) = client.get_room(&room_id) else {
+            continue;
+        };
+
+        debug!("SOAP|RSI: Sending read receipt for {} in {}", &event_id, &room_id);
+        room.send_single_receipt(ReceiptType::Read, ReceiptThread::Unthreaded, event_id).await?;
     }
 
     let soap_body = DeleteMessagesResponseSoapEnvelope::new();
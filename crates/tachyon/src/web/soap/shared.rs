@@ -2,9 +2,17 @@ use axum::http::StatusCode;
 use axum::response::Response;
 use axum::http::header::CONTENT_TYPE;
 use axum::body::Body;
+use msnp::soap::abch::msnab_faults::SoapFaultResponseEnvelope;
+use msnp::soap::traits::xml::ToXml;
+use crate::web::soap::error::ABError;
 
 pub fn build_soap_response(body: String, status_code: StatusCode) -> Response {
      Response::builder().status(status_code)
         .header(CONTENT_TYPE, "application/soap+xml")
         .body(Body::from(body)).expect("RST2 response to be valid")
 }
+
+/* ABCH client faults (already exists, invalid passport...) */
+pub fn build_ab_fault_response(fault: SoapFaultResponseEnvelope) -> Result<Response, ABError> {
+    Ok(build_soap_response(fault.to_xml()?, StatusCode::INTERNAL_SERVER_ERROR))
+}
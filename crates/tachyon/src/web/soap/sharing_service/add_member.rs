@@ -1,19 +1,77 @@
+use std::str::FromStr;
+
 use anyhow::anyhow;
 use axum::http::StatusCode;
 use axum::response::Response;
+use log::{debug, warn};
 use matrix_sdk::Client;
+use msnp::shared::models::email_address::EmailAddress;
 use msnp::shared::models::ticket_token::TicketToken;
+use msnp::soap::abch::msnab_datatypes::RoleId;
 use msnp::soap::abch::sharing_service::add_member::request::AddMemberMessageSoapEnvelope;
 use msnp::soap::abch::sharing_service::add_member::response::AddMemberResponseMessageSoapEnvelope;
 use msnp::soap::abch::sharing_service::find_membership::request::FindMembershipRequestSoapEnvelope;
 use msnp::soap::traits::xml::ToXml;
+use crate::matrix::directs::{accept_dm_invite, forget_left_dm_rooms, get_pending_dm_invites, reject_dm_invite};
+use crate::matrix::memberships::queue_server_membership_pushes;
+use crate::notification::address_book_store::{AddressBookChanges, MemberRole, StoredMember};
 use crate::notification::client_store::ClientData;
+use crate::shared::identifiers::MatrixIdCompatible;
 use crate::web::soap::error::ABError;
 use crate::web::soap::shared;
 
 pub async fn add_member(request : AddMemberMessageSoapEnvelope, token: TicketToken, client: Client, mut client_data: ClientData) -> Result<Response, ABError> {
     let cache_key = &request.header.ok_or(anyhow!("Header missing"))?.application_header.cache_key.unwrap_or_default();
 
+    let mut changes = AddressBookChanges::default();
+    for membership in request.body.body.memberships.membership {
+        match membership.member_role {
+            RoleId::Allow => {
+                for member in membership.members.member {
+                    if let Some(passport_name) = member.passport_name.as_ref() {
+                        debug!("SOAP|ABCH|AddMember: + Allow - {}", passport_name);
+
+                        /* Accepting an "X added you" prompt joins the DM room they invited us to */
+                        if let Some(user_id) = EmailAddress::from_str(passport_name).ok().and_then(|addr| addr.try_to_owned_user_id()) {
+                            for invited_room in get_pending_dm_invites(&client, &user_id).await? {
+                                accept_dm_invite(&invited_room).await?;
+                            }
+                        }
+
+                        changes.members.push(StoredMember::new(passport_name, MemberRole::Allow, false));
+                    }
+                }
+            },
+            RoleId::Block => {
+                for member in membership.members.member {
+                    if let Some(passport_name) = member.passport_name.as_ref() {
+                        debug!("SOAP|ABCH|AddMember: + Block - {}", passport_name);
+                        let Some(user_id) = EmailAddress::from_str(passport_name).ok().and_then(|addr| addr.try_to_owned_user_id()) else {
+                            warn!("SOAP|ABCH|AddMember: Couldn't block invalid passport: {}", passport_name);
+                            continue;
+                        };
+
+                        for invited_room in get_pending_dm_invites(&client, &user_id).await? {
+                            reject_dm_invite(&invited_room).await?;
+                            changes.members.push(StoredMember::new(passport_name, MemberRole::Pending, true));
+                            changes.members.push(StoredMember::new(passport_name, MemberRole::Reverse, true));
+                        }
+
+                        client.account().ignore_user(&user_id).await?;
+                        forget_left_dm_rooms(&client, &user_id).await?;
+                        changes.members.push(StoredMember::new(passport_name, MemberRole::Block, false));
+                    }
+                }
+            },
+            other => {
+                warn!("SOAP|ABCH|AddMember: Unsupported member role: {:?}", other);
+            }
+        }
+    }
+    queue_server_membership_pushes(&changes, None, &client_data).await?;
+    client_data.inner.address_book_store.apply(changes);
+    client_data.send_contact_list_pushes().await;
+
     let soap_body = AddMemberResponseMessageSoapEnvelope::new(cache_key);
 
     Ok(shared::build_soap_response(soap_body.to_xml()?, StatusCode::OK))
@@ -1,21 +1,77 @@
+use std::str::FromStr;
+
 use anyhow::anyhow;
 use axum::http::StatusCode;
 use axum::response::Response;
+use log::{debug, warn};
 use matrix_sdk::Client;
 
+use msnp::shared::models::email_address::EmailAddress;
 use msnp::shared::models::ticket_token::TicketToken;
+use msnp::soap::abch::msnab_datatypes::RoleId;
 use msnp::soap::abch::sharing_service::delete_member::request::DeleteMemberMessageSoapEnvelope;
 use msnp::soap::abch::sharing_service::delete_member::response::DeleteMemberResponseMessageSoapEnvelope;
 use msnp::soap::traits::xml::ToXml;
 
+use crate::matrix::directs::{get_pending_dm_invites, reject_dm_invite};
+use crate::matrix::memberships::queue_server_membership_pushes;
+use crate::notification::address_book_store::{AddressBookChanges, MemberRole, StoredMember};
 use crate::notification::client_store::ClientData;
+use crate::shared::identifiers::MatrixIdCompatible;
 use crate::web::soap::error::ABError;
 use crate::web::soap::shared;
 
 pub async fn delete_member(request : DeleteMemberMessageSoapEnvelope, token: TicketToken, client: Client, mut client_data: ClientData) -> Result<Response, ABError> {
     let cache_key = &request.header.ok_or(anyhow!("Header missing"))?.application_header.cache_key.unwrap_or_default();
 
+    let mut changes = AddressBookChanges::default();
+    for membership in request.body.body.memberships.membership {
+        let role = match membership.member_role {
+            RoleId::Allow => MemberRole::Allow,
+            RoleId::Block => MemberRole::Block,
+            RoleId::Pending => MemberRole::Pending,
+            other => {
+                warn!("SOAP|ABCH|DeleteMember: Unsupported member role: {:?}", other);
+                continue;
+            }
+        };
+
+        for member in membership.members.member {
+            let Some(passport_name) = member.passport_name.as_ref() else {
+                warn!("SOAP|ABCH|DeleteMember: Member without passport name in role: {:?}", role);
+                continue;
+            };
+
+            debug!("SOAP|ABCH|DeleteMember: - {:?} - {}", role, passport_name);
+            let user_id = EmailAddress::from_str(passport_name).ok().and_then(|addr| addr.try_to_owned_user_id());
+
+            match (role, user_id) {
+                (MemberRole::Block, None) => {
+                    warn!("SOAP|ABCH|DeleteMember: Couldn't unblock invalid passport: {}", passport_name);
+                },
+                (MemberRole::Block, Some(user_id)) => {
+                    client.account().unignore_user(&user_id).await?;
+                },
+                (MemberRole::Pending, Some(user_id)) => {
+                    /* Accepting also removes the Pending member, the invite is only declined when they weren't allowed */
+                    if !client_data.inner.address_book_store.has_member(passport_name, MemberRole::Allow) {
+                        for invited_room in get_pending_dm_invites(&client, &user_id).await? {
+                            reject_dm_invite(&invited_room).await?;
+                            changes.members.push(StoredMember::new(passport_name, MemberRole::Reverse, true));
+                        }
+                    }
+                },
+                _ => {}
+            }
+
+            changes.members.push(StoredMember::new(passport_name, role, true));
+        }
+    }
+    queue_server_membership_pushes(&changes, None, &client_data).await?;
+    client_data.inner.address_book_store.apply(changes);
+    client_data.send_contact_list_pushes().await;
+
     let soap_body = DeleteMemberResponseMessageSoapEnvelope::new(cache_key);
 
     Ok(shared::build_soap_response(soap_body.to_xml()?, StatusCode::OK))
-}
\ No newline at end of file
+}
@@ -1,8 +1,9 @@
 use anyhow::anyhow;
 use axum::http::StatusCode;
 use axum::response::Response;
-use log::info;
+use log::{debug, info};
 use matrix_sdk::Client;
+use matrix_sdk::ruma::events::ignored_user_list::IgnoredUserListEventContent;
 use matrix_sdk::ruma::events::room::member::MembershipState;
 use msnp::shared::models::email_address::EmailAddress;
 use msnp::shared::models::msn_user::MsnUser;
@@ -14,7 +15,8 @@ use msnp::soap::abch::msnab_faults::SoapFaultResponseEnvelope;
 use msnp::soap::abch::sharing_service::find_membership::request::FindMembershipRequestSoapEnvelope;
 use msnp::soap::abch::sharing_service::find_membership::response::factory::FindMembershipResponseFactory;
 use msnp::soap::traits::xml::ToXml;
-use crate::matrix::directs::resolve_direct_target;
+use crate::matrix::directs::{get_own_invite_content, resolve_direct_target};
+use crate::notification::address_book_store::{parse_last_change, MemberRole, StoredMember};
 use crate::notification::client_store::{ClientData, ClientStoreFacade};
 use crate::shared::identifiers::MatrixIdCompatible;
 use crate::shared::traits::ToUuid;
@@ -28,80 +30,65 @@ pub async fn find_membership(request : FindMembershipRequestSoapEnvelope, token:
 
 
     let deltas_only = request.body.request.deltas_only.unwrap_or(false);
+    let address_book_store = &client_data.inner.address_book_store;
 
-    if deltas_only {
-        // Fetch from store. TODO
-        // Ok(shared::build_soap_response(SoapFaultResponseEnvelope::new_fullsync_required("http://www.msn.com/webservices/AddressBook/FindMembership").to_xml()?, StatusCode::OK))
-        let (allow, reverse, block, pending) = get_delta_sync(&mut client_data)?;
-
-        let msg_service = FindMembershipResponseFactory::get_messenger_service(allow, block, reverse, pending, false);
-        let user_id = client.user_id().ok_or(anyhow!("Expected matrix client to have a logged-in user"))?;
-        let email_addr = EmailAddress::from_user_id(user_id);
-        let uuid = email_addr.to_uuid();
-
-        let soap_body = FindMembershipResponseFactory::get_response(
-            uuid,
-            email_addr,
-            &cache_key, msg_service);
-
-        Ok(shared::build_soap_response(soap_body.to_xml()?, StatusCode::OK))
-
+    let since = if deltas_only {
+        let last_change = request.body.request.last_change.as_deref();
+        if !address_book_store.is_members_seeded() || !address_book_store.can_serve_delta(last_change) {
+            debug!("SOAP|ABCH|FindMembership: Membership cache is too old: {:?}, fullsync required", last_change);
+            return Ok(shared::build_soap_response(SoapFaultResponseEnvelope::new_fullsync_required("http://www.msn.com/webservices/AddressBook/FindMembership").to_xml()?, StatusCode::OK));
+        }
+        last_change.and_then(parse_last_change)
     } else {
-        let (allow, reverse, block, pending) = get_fullsync_members(&client).await?;
-        let msg_service = FindMembershipResponseFactory::get_messenger_service(allow, block, reverse, pending, true);
+        let members = get_fullsync_members(&client).await?;
+        address_book_store.reconcile_members(members);
+        None
+    };
 
-        let user_id = client.user_id().ok_or(anyhow!("Expected matrix client to have a logged-in user"))?;
-        let email_addr = EmailAddress::from_user_id(user_id);
-        let uuid = email_addr.to_uuid();
+    let (allow, reverse, block, pending) = split_by_role(address_book_store.get_members(since));
+    let msg_service = FindMembershipResponseFactory::get_messenger_service(allow, block, reverse, pending, !deltas_only);
 
-         let soap_body = FindMembershipResponseFactory::get_response(
-             uuid,
-             email_addr,
-             &cache_key, msg_service);
+    let user_id = client.user_id().ok_or(anyhow!("Expected matrix client to have a logged-in user"))?;
+    let email_addr = EmailAddress::from_user_id(user_id);
+    let uuid = email_addr.to_uuid();
 
-        Ok(shared::build_soap_response(soap_body.to_xml()?, StatusCode::OK))
-    }
+    let soap_body = FindMembershipResponseFactory::get_response(
+        uuid,
+        email_addr,
+        &cache_key, msg_service);
+
+    Ok(shared::build_soap_response(soap_body.to_xml()?, StatusCode::OK))
 }
 
-fn get_delta_sync(client_data: &mut ClientData) -> Result<(Vec<BaseMember>, Vec<BaseMember>, Vec<BaseMember>, Vec<BaseMember>), ABError> {
+fn split_by_role(members: Vec<StoredMember>) -> (Vec<BaseMember>, Vec<BaseMember>, Vec<BaseMember>, Vec<BaseMember>) {
     let mut allow_list = Vec::new();
     let mut reverse_list = Vec::new();
     let mut block_list = Vec::new();
     let mut pending_list = Vec::new();
 
-    let mut memberships = client_data.get_member_holder_mut().unwrap();
-
-    for member in memberships.drain(..) {
-        match member.role_list {
-
-            RoleList::Allow => {
-                allow_list.push(member);
+    for member in members {
+        match member.role {
+            MemberRole::Allow => {
+                allow_list.push(member.to_base_member());
             }
-            RoleList::Block => {
-                block_list.push(member);
+            MemberRole::Block => {
+                block_list.push(member.to_base_member());
             }
-            RoleList::Reverse => {
-                reverse_list.push(member);
+            MemberRole::Reverse => {
+                reverse_list.push(member.to_base_member());
+            }
+            MemberRole::Pending => {
+                pending_list.push(member.to_base_member());
             }
-            RoleList::Pending => {
-                pending_list.push(member);
-            },
-            _ => {
-
-            },
         }
-
     }
 
-    Ok((allow_list, reverse_list, block_list, pending_list))
+    (allow_list, reverse_list, block_list, pending_list)
 }
 
-async fn get_fullsync_members(matrix_client: &Client) -> Result<(Vec<BaseMember>, Vec<BaseMember>, Vec<BaseMember>, Vec<BaseMember>), ABError> {
+async fn get_fullsync_members(matrix_client: &Client) -> Result<Vec<StoredMember>, ABError> {
 
-    let mut allow_list = Vec::new();
-    let mut reverse_list = Vec::new();
-    let mut block_list = Vec::new();
-    let mut pending_list = Vec::new();
+    let mut out = Vec::new();
 
     let me = matrix_client.user_id().expect("A user to be logged in when fetching fullsync members");
 
@@ -111,20 +98,15 @@ async fn get_fullsync_members(matrix_client: &Client) -> Result<(Vec<BaseMember>
             if let Some(direct_target) = direct_target {
 
                 if let Some(member) = joined_room.get_member(&direct_target).await? {
-                    let target_usr = MsnUser::with_email_addr(EmailAddress::from_user_id(&direct_target));
-                    let target_uuid = target_usr.uuid;
-                    let target_msn_addr = target_usr.endpoint_id.email_addr.to_string();
+                    let target_msn_addr = EmailAddress::from_user_id(&direct_target).to_string();
 
                     match member.membership() {
                         MembershipState::Invite => {
-                            let allow_member = BaseMember::new_passport_member(&target_uuid, &target_msn_addr, MemberState::Accepted, RoleList::Allow, false);
-                            allow_list.push(allow_member);
+                            out.push(StoredMember::new(&target_msn_addr, MemberRole::Allow, false));
                         }
                         MembershipState::Join => {
-                            let allow_member = BaseMember::new_passport_member(&target_uuid, &target_msn_addr, MemberState::Accepted, RoleList::Allow, false);
-                            let reverse_member = BaseMember::new_passport_member(&target_uuid, &target_msn_addr, MemberState::Accepted, RoleList::Reverse, false);
-                            allow_list.push(allow_member);
-                            reverse_list.push(reverse_member);
+                            out.push(StoredMember::new(&target_msn_addr, MemberRole::Allow, false));
+                            out.push(StoredMember::new(&target_msn_addr, MemberRole::Reverse, false));
                         }
                         _ => {}
                     }
@@ -135,39 +117,33 @@ async fn get_fullsync_members(matrix_client: &Client) -> Result<(Vec<BaseMember>
         }
     }
 
+    /* Invites we got while offline are only found here, the inviter gets an "X added you" prompt */
     for invited_room in matrix_client.invited_rooms() {
-        if invited_room.is_direct().await? {
-            let direct_target = resolve_direct_target(&invited_room.direct_targets(), &invited_room, me, matrix_client).await?;
-            if let Some(direct_target) = direct_target {
-                if let Some(member) = invited_room.get_member(&direct_target).await? {
-                    let target_usr = MsnUser::with_email_addr(EmailAddress::from_user_id(&direct_target));
-                    let target_uuid = target_usr.uuid;
-                    let target_msn_addr = target_usr.endpoint_id.email_addr.to_string();
-
-                    match member.membership() {
-                        _ => {}
-                        MembershipState::Join => {
-                            let current_reverse_member = BaseMember::new_passport_member(&target_uuid, &target_msn_addr, MemberState::Accepted, RoleList::Reverse, false);
-                            let mut current_pending_member = BaseMember::new_passport_member(&target_uuid, &target_msn_addr, MemberState::Accepted, RoleList::Pending, false);
-                            current_pending_member.display_name = Some(target_msn_addr.clone());
-                            //TODO fetch message
-                            let annotation = Annotation::new_invite("");
-                            let mut annotations = Vec::new();
-                            annotations.push(annotation);
-                            current_pending_member.annotations = Some(ArrayOfAnnotation { annotation: annotations });
-
-                            reverse_list.push(current_reverse_member);
-                            pending_list.push(current_pending_member);
-                        }
-                    }
+        let Some(content) = get_own_invite_content(&invited_room, me).await? else {
+            continue;
+        };
 
-                }
-            }
+        if content.membership != MembershipState::Invite || !content.is_direct.unwrap_or(false) {
+            continue;
         }
+
+        let Some(inviter) = invited_room.invite_details().await?.inviter else {
+            info!("Fullsync Fetch: No inviter found for invited room: {}", &invited_room.room_id());
+            continue;
+        };
+
+        let inviter_msn_addr = EmailAddress::from_user_id(inviter.user_id()).to_string();
+        out.push(StoredMember::new(&inviter_msn_addr, MemberRole::Reverse, false));
+        out.push(StoredMember::new_pending(&inviter_msn_addr, content.reason.as_deref().unwrap_or("")));
     }
 
+    if let Some(raw_ignored_users) = matrix_client.account().account_data::<IgnoredUserListEventContent>().await? {
+        for ignored_user in raw_ignored_users.deserialize().map_err(matrix_sdk::Error::from)?.ignored_users.keys() {
+            out.push(StoredMember::new(&EmailAddress::from_user_id(ignored_user).to_string(), MemberRole::Block, false));
+        }
+    }
 
-    Ok((allow_list, reverse_list, block_list, pending_list))
+    Ok(out)
 
 
 }
\ No newline at end of file
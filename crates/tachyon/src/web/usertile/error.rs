@@ -0,0 +1,31 @@
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use log::error;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum UsertileError {
+
+    #[error("Couldn't parse usertile id: {}", .0)]
+    InvalidUsertileId(String),
+    #[error("No logged in client for {}", .0)]
+    UnknownSession(String),
+    #[error("Usertile not found on the homeserver: {}", .0)]
+    NotFound(String),
+    #[error("Homeserver couldn't serve the usertile")]
+    MatrixError(#[from] matrix_sdk::Error),
+
+}
+
+impl IntoResponse for UsertileError {
+    fn into_response(self) -> Response {
+        error!("WEB|USERTILE: {:?}", &self);
+
+        let status_code = match &self {
+            UsertileError::InvalidUsertileId(_) | UsertileError::UnknownSession(_) | UsertileError::NotFound(_) => StatusCode::NOT_FOUND,
+            UsertileError::MatrixError(_) => StatusCode::BAD_GATEWAY
+        };
+
+        (status_code, self.to_string()).into_response()
+    }
+}
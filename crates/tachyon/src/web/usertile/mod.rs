@@ -0,0 +1,3 @@
+pub mod usertile;
+pub mod usertile_cache;
+pub mod error;
\ No newline at end of file
@@ -0,0 +1,99 @@
+use axum::body::Body;
+use axum::extract::{Path, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::http::header::{CACHE_CONTROL, CONTENT_TYPE, ETAG, IF_NONE_MATCH};
+use axum::response::Response;
+use base64::Engine;
+use base64::engine::general_purpose;
+use log::debug;
+use matrix_sdk::media::{MediaFormat, MediaRequest, MediaThumbnailSettings};
+use matrix_sdk::ruma::{MxcUri, OwnedMxcUri, OwnedUserId, UInt, UserId};
+use matrix_sdk::ruma::api::client::media::get_content_thumbnail::v3::Method;
+use matrix_sdk::ruma::events::room::MediaSource;
+
+use crate::matrix::emoticons::guess_image_mime;
+use crate::notification::client_store::ClientStoreFacade;
+use crate::web::media::media_link::{sign_usertile_id, verify_usertile_id};
+use crate::web::usertile::error::UsertileError;
+use crate::web::usertile::usertile_cache::UsertileCache;
+
+const STATIC_USERTILE_SIZE: u32 = 200;
+const SMALL_USERTILE_SIZE: u32 = 96;
+
+/* The usertile id carries the owner of the session, the Storage Service pre-auth urls have no ticket.
+It is signed so it can't be used to fetch other media with that session */
+pub fn get_usertile_id(user_id: &UserId, mxc: &MxcUri) -> String {
+    let signature = sign_usertile_id(user_id.as_str(), mxc.as_str());
+    format!("{}.{}.{}", general_purpose::URL_SAFE_NO_PAD.encode(user_id.as_str()), general_purpose::URL_SAFE_NO_PAD.encode(mxc.as_str()), signature)
+}
+
+fn parse_usertile_id(usertile_id: &str) -> Option<(OwnedUserId, OwnedMxcUri)> {
+    let mut parts = usertile_id.split('.');
+    let (user_id, mxc, signature) = (parts.next()?, parts.next()?, parts.next()?);
+    if parts.next().is_some() {
+        return None;
+    }
+
+    let user_id = String::from_utf8(general_purpose::URL_SAFE_NO_PAD.decode(user_id).ok()?).ok()?;
+    let mxc = String::from_utf8(general_purpose::URL_SAFE_NO_PAD.decode(mxc).ok()?).ok()?;
+    if !verify_usertile_id(&user_id, &mxc, signature) {
+        return None;
+    }
+
+    let user_id = UserId::parse(user_id).ok()?;
+    let mxc = OwnedMxcUri::from(mxc);
+    if !mxc.is_valid() {
+        return None;
+    }
+
+    Some((user_id, mxc))
+}
+
+pub async fn get_usertile(Path((usertile_id, image_type)): Path<(String, String)>, headers: HeaderMap, State(state): State<ClientStoreFacade>) -> Result<Response, UsertileError> {
+    let (user_id, mxc) = parse_usertile_id(&usertile_id).ok_or(UsertileError::InvalidUsertileId(usertile_id.clone()))?;
+    let size = if image_type == "small" { SMALL_USERTILE_SIZE } else { STATIC_USERTILE_SIZE };
+
+    let etag = UsertileCache::get_etag(&mxc, size);
+    let if_none_match = headers.get(IF_NONE_MATCH).and_then(|value| value.to_str().ok());
+    if if_none_match.is_some_and(|value| value == "*" || value.split(',').any(|tag| tag.trim() == etag)) {
+        return Ok(build_usertile_response(StatusCode::NOT_MODIFIED, &etag, None, Body::empty()));
+    }
+
+    let client_data = state.get_client_data_by_user_id(&user_id).ok_or(UsertileError::UnknownSession(user_id.to_string()))?;
+    let cache = UsertileCache::new(&user_id);
+
+    if let Some(thumbnail) = cache.get(&mxc, size).await {
+        debug!("WEB|USERTILE: Serving {} from cache", &mxc);
+        return Ok(build_usertile_response(StatusCode::OK, &etag, Some(guess_image_mime(&thumbnail)), Body::from(thumbnail)));
+    }
+
+    let thumbnail_settings = MediaThumbnailSettings::new(Method::Scale, UInt::from(size), UInt::from(size));
+    let media_request = MediaRequest{ source: MediaSource::Plain(mxc.clone()), format: MediaFormat::Thumbnail(thumbnail_settings) };
+
+    let thumbnail = match client_data.get_matrix_client().media().get_media_content(&media_request, true).await {
+        Ok(thumbnail) => thumbnail,
+        Err(err) => {
+            if err.as_client_api_error().is_some_and(|api_error| api_error.status_code.as_u16() == 404) {
+                return Err(UsertileError::NotFound(mxc.to_string()));
+            }
+            return Err(UsertileError::MatrixError(err));
+        }
+    };
+
+    cache.insert(&mxc, size, &thumbnail).await;
+    Ok(build_usertile_response(StatusCode::OK, &etag, Some(guess_image_mime(&thumbnail)), Body::from(thumbnail)))
+}
+
+/* Homeservers answer thumbnails in the format of the original picture when they can */
+fn build_usertile_response(status_code: StatusCode, etag: &str, content_type: Option<mime::Mime>, body: Body) -> Response {
+    let mut builder = Response::builder()
+        .status(status_code)
+        .header(ETAG, etag)
+        .header(CACHE_CONTROL, "private, max-age=86400");
+
+    if let Some(content_type) = content_type {
+        builder = builder.header(CONTENT_TYPE, content_type.to_string());
+    }
+
+    builder.body(body).expect("Usertile response to be valid")
+}
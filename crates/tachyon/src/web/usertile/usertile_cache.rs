@@ -0,0 +1,50 @@
+use std::path::PathBuf;
+
+use log::warn;
+use matrix_sdk::ruma::{MxcUri, UserId};
+use sha2::{Digest, Sha256};
+
+use crate::shared::paths::get_usertile_cache_path;
+
+/* mxc content never changes, so thumbnails are cached forever and the ETag only depends on the mxc */
+pub struct UsertileCache {
+    path: Option<PathBuf>
+}
+
+impl UsertileCache {
+
+    pub fn new(user_id: &UserId) -> Self {
+        Self {
+            path: get_usertile_cache_path(user_id)
+        }
+    }
+
+    pub fn get_etag(mxc: &MxcUri, size: u32) -> String {
+        format!("\"{}\"", Self::get_key(mxc, size))
+    }
+
+    pub async fn get(&self, mxc: &MxcUri, size: u32) -> Option<Vec<u8>> {
+        let path = self.path.as_ref()?.join(Self::get_key(mxc, size));
+        tokio::fs::read(path).await.ok()
+    }
+
+    pub async fn insert(&self, mxc: &MxcUri, size: u32, thumbnail: &[u8]) {
+        let Some(path) = self.path.as_ref() else {
+            return;
+        };
+
+        if let Err(err) = tokio::fs::create_dir_all(path).await {
+            warn!("WEB|USERTILE: Couldn't create usertile cache directory: {}", err);
+            return;
+        }
+
+        if let Err(err) = tokio::fs::write(path.join(Self::get_key(mxc, size)), thumbnail).await {
+            warn!("WEB|USERTILE: Couldn't cache usertile {}: {}", mxc, err);
+        }
+    }
+
+    fn get_key(mxc: &MxcUri, size: u32) -> String {
+        let hash = Sha256::digest(mxc.as_str().as_bytes());
+        format!("{}_{}", hex::encode(hash), size)
+    }
+}
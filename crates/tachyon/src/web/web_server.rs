@@ -15,13 +15,16 @@ use tokio::sync::broadcast::Receiver;
 use http_body_util::BodyExt;
 
 use crate::notification::client_store::ClientStoreFacade;
+use crate::web::media::media::get_media;
+use crate::web::usertile::usertile::get_usertile;
 use crate::web::soap::ab_service::ab_service::address_book_service;
+use crate::web::soap::oim::oim::oim_store_service;
 use crate::web::soap::rsi::rsi::rsi;
 use crate::web::soap::sharing_service::sharing_service::sharing_service;
 
 use crate::web::soap::rst2::rst2_handler;
 use crate::web::soap::storage_service::storage_service::storage_service;
-use crate::web::web_endpoints::{firewall_test, get_banner_ads, get_msgr_config, get_profile_pic, get_text_ad, ppcrlcheck, ppcrlconfigsrf, sha1auth, wlidsvcconfig};
+use crate::web::web_endpoints::{firewall_test, get_banner_ads, get_msgr_config, get_text_ad, ppcrlcheck, ppcrlconfigsrf, sha1auth, wlidsvcconfig};
 
 pub struct WebServer;
 
@@ -45,12 +48,14 @@ impl WebServer {
             .route("/wlidsvcconfig.xml", get(wlidsvcconfig))
             .route("/pcrlcheck.srf", get(ppcrlcheck))
             .route("/RST2.srf", post(rst2_handler))
-            .route("/storage/usertile/:image_mxid/:image_type", get(get_profile_pic))
+            .route("/storage/usertile/:usertile_id/:image_type", get(get_usertile))
+            .route("/media/:room_id/:event_id", get(get_media))
             //SOAP
             .route("/abservice/abservice.asmx", post(address_book_service))
             .route("/abservice/SharingService.asmx", post(sharing_service))
             .route("/storageservice/SchematizedStore.asmx", post(storage_service))
             .route("/rsi/rsi.asmx", post(rsi))
+            .route("/OimWS/oim.asmx", post(oim_store_service))
             .with_state(state)
             .layer(middleware::from_fn(my_middleware))
             .fallback(fallback);